@@ -2,6 +2,7 @@ use std::env;
 use std::fs;
 use std::path::Path;
 
+#[allow(clippy::needless_borrow, clippy::needless_borrows_for_generic_args)]
 fn main() {
     // Force build.rs to rerun if Cargo.toml or lson changes
     println!("cargo:rerun-if-changed=Cargo.toml");
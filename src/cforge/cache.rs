@@ -0,0 +1,126 @@
+use std::{collections::{hash_map::DefaultHasher, BTreeMap}, fs, hash::{Hash, Hasher}, path::Path};
+use serde::{Deserialize, Serialize};
+
+/// What was generated from a Copper file the last time it was compiled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub hash: String,
+    pub output: String,
+    pub dependencies: Vec<String>,
+    /// Copper files it imports
+    #[serde(default)]
+    pub imports: Vec<String>,
+}
+
+/// Compilation cache stored in `<output>/cache/compile.json`, lets `compile`
+/// skip the files that didn't change since the last run
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cache {
+    entries: BTreeMap<String, Entry>,
+}
+
+impl Cache {
+    fn path(output_dir: &str) -> String {
+        format!("{}/cache/compile.json", output_dir.trim_end_matches('/'))
+    }
+
+    /// Loads the cache, a missing or unreadable cache is just empty
+    pub fn load(output_dir: &str) -> Self {
+        fs::read_to_string(Self::path(output_dir))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, output_dir: &str) {
+        let path = Self::path(output_dir);
+        fs::create_dir_all(Path::new(&path).parent().unwrap()).unwrap();
+        write_if_changed(&path, &serde_json::to_string_pretty(self).unwrap());
+    }
+
+    /// The entry of `file` if it was compiled with the same hash and its output
+    /// is still there. Deleting a file it imports makes it stale, its import
+    /// has to be reported again.
+    pub fn get(&self, file: &str, hash: &str) -> Option<&Entry> {
+        self.entries.get(file).filter(|entry| {
+            entry.hash == hash
+                && Path::new(&entry.output).exists()
+                && entry.imports.iter().all(|import| Path::new(import).is_file())
+        })
+    }
+
+    pub fn insert(&mut self, file: &str, entry: Entry) {
+        self.entries.insert(file.to_string(), entry);
+    }
+
+    pub fn remove(&mut self, file: &str) {
+        self.entries.remove(file);
+    }
+
+    /// Copper files compiled before that still exist
+    pub fn files(&self) -> impl Iterator<Item = &String> {
+        self.entries.keys().filter(|file| Path::new(file).exists())
+    }
+
+    /// The generated files of the Copper files that still exist
+    pub fn outputs(&self) -> Vec<String> {
+        self.files().map(|file| self.entries[file].output.clone()).collect()
+    }
+}
+
+/// Hashes a source file together with everything else that changes its output
+pub fn hash(source: &str, options: &[&str]) -> String {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    options.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Writes `contents` unless the file already has them, so Cargo doesn't see
+/// a change and rebuild. Returns whether the file was written.
+pub fn write_if_changed(path: &str, contents: &str) -> bool {
+    if fs::read_to_string(path).is_ok_and(|current| current == contents) {
+        return false;
+    }
+
+    fs::write(path, contents).unwrap();
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_depends_on_options() {
+        let source = "println(\"hi\")\n";
+
+        assert_eq!(hash(source, &["0.1.0", "dist/rust/src/main.rs"]), hash(source, &["0.1.0", "dist/rust/src/main.rs"]));
+        assert_ne!(hash(source, &["0.1.0", "dist/rust/src/main.rs"]), hash(source, &["0.2.0", "dist/rust/src/main.rs"]));
+        assert_ne!(hash(source, &["0.1.0"]), hash("println(\"bye\")\n", &["0.1.0"]));
+    }
+
+    #[test]
+    fn test_deleted_import_makes_entry_stale() {
+        let dir = std::env::temp_dir().join(format!("copper-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (output, import) = (dir.join("main.rs"), dir.join("util.crs"));
+        fs::write(&output, "").unwrap();
+        fs::write(&import, "").unwrap();
+
+        let mut cache = Cache::default();
+        cache.insert("main.crs", Entry {
+            hash: "1".to_string(),
+            output: output.to_string_lossy().into_owned(),
+            dependencies: Vec::new(),
+            imports: vec![import.to_string_lossy().into_owned()],
+        });
+        let fresh = cache.get("main.crs", "1").is_some();
+        fs::remove_file(&import).unwrap();
+        let stale = cache.get("main.crs", "1").is_none();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(fresh);
+        assert!(stale);
+    }
+}
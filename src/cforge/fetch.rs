@@ -24,6 +24,7 @@ fn is_local_network_connected() -> bool {
 }
 
 // Check if a version exists in the registry and if is yanked
+#[allow(clippy::unnecessary_unwrap)]
 pub async fn check_version_exists(crate_name: &str, mut version: &str, registry: Option<&str>) -> Result<(bool, String), Box<dyn Error>> {
     if !is_local_network_connected() {
         println!("🛜 Could not connect to the internet. Skipping version check.");
@@ -1,13 +1,13 @@
 use std::path::Path;
 use std::{env, fs};
-use std::process::{exit, Command};
+use std::process::Command;
 use serde_json::Value;
 
 use crate::cforge::COPPER_PATH;
 
-fn parse(text: &str) -> Value {
+fn parse(text: &str) -> Result<Value, String> {
     let exe_dir = env::current_exe().unwrap().parent().unwrap().to_path_buf();
-    let binding = COPPER_PATH;
+    let binding = &COPPER_PATH;
     let cop_path = Path::new(binding.as_str());
     let path = if cfg!(target_os = "windows") {
         exe_dir.join(cop_path.join(format!("lson{}win32{}lson.exe", std::path::MAIN_SEPARATOR, std::path::MAIN_SEPARATOR)))
@@ -31,39 +31,23 @@ fn parse(text: &str) -> Value {
         .arg("compile")
         .args(["-t", "json"])
         .args(["--text", text])
-        .output();
+        .output()
+        .map_err(|err| format!("Failed to execute lson: {}", err))?;
 
-    if cmd.is_ok() {
-        let cmd = cmd.unwrap();
-        let mut stdout = String::from_utf8_lossy(&cmd.stdout);
+    let mut stdout = String::from_utf8_lossy(&cmd.stdout);
 
-        if stdout.lines().nth(0).unwrap().starts_with("warning ") {
-            stdout = stdout.lines().skip(1).collect();
-        }
-
-        let json = stdout.to_string();
-        let parsed_json: Value = serde_json::from_str(&json).expect("Invalid KSON file");
-
-        return parsed_json;
-    } else {
-        let cmd = cmd;
-        if cmd.is_err() {
-            let err = cmd.err().unwrap();
-            println!("Failed to execute lson: {}", err);
-            exit(1);
-        }
-        let cmd = cmd.unwrap();
-        let stderr = String::from_utf8_lossy(&cmd.stderr);
-        println!("Invalid configuration file: {}", stderr);
-        exit(1);
+    if stdout.lines().next().is_some_and(|line| line.starts_with("warning ")) {
+        stdout = stdout.lines().skip(1).collect();
     }
+
+    serde_json::from_str(&stdout).map_err(|err| format!("Invalid properties.kson: {}", err))
 }
 
 /// Read properties from a KSON or TOML file.
 /// If both files exist, KSON takes precedence.
 /// 
-/// Returns (is_toml, Value)
-pub fn read_properties(file: &str) -> (bool, Value) {
+/// Returns (is_toml, Value), or why neither file could be read
+pub fn read_properties(file: &str) -> Result<(bool, Value), String> {
     let mut file = file.to_string();
     let mut c = fs::read(&file);
     if c.is_err() {
@@ -71,19 +55,17 @@ pub fn read_properties(file: &str) -> (bool, Value) {
         file = file.replace("properties.kson", "Cargo.toml");
         c = fs::read(&file);
         if c.is_err() {
-            println!("Error: Missing properties.kson or Cargo.toml. Please ensure the file exists and is readable.");
-            exit(1);
+            return Err("Missing properties.kson or Cargo.toml. Please ensure the file exists and is readable.".to_string());
         }
     }
 
+    let text = String::from_utf8(c.unwrap()).map_err(|_| format!("{} is not valid UTF-8", file))?;
+
     if file.ends_with("Cargo.toml") {
-        let text = String::from_utf8(c.unwrap()).unwrap();
-        let parsed: toml::Value = toml::from_str(&text).expect("Invalid Cargo.toml file");
-        return (true, serde_json::to_value(parsed).unwrap());
+        let parsed: toml::Value = toml::from_str(&text).map_err(|err| format!("Invalid Cargo.toml: {}", err))?;
+        return Ok((true, serde_json::to_value(parsed).unwrap()));
     }
 
-    let text = String::from_utf8(c.unwrap()).unwrap();
-
     // text = "@kmodel(./src/models/cforge.kmodel)\n".to_string() + &text;
-    (false, parse(&text))
+    Ok((false, parse(&text)?))
 }
\ No newline at end of file
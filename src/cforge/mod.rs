@@ -2,15 +2,21 @@ pub mod kson;
 pub mod properties;
 pub mod fetch;
 pub mod vprint;
+pub mod rustc;
+pub mod cache;
+pub mod watch;
+pub mod modules;
 
-use std::{fs, path, process::Command};
+use std::{collections::HashSet, fs, path, process::Command, sync::Arc};
 use colored::Colorize;
 use once_cell::sync::Lazy;
+use tokio::sync::Semaphore;
 
-use crate::{parser, tokenizer::tokenizer::Tokenizer, vprint};
+use crate::{checker, diagnostics::{self, Diagnostic}, emitter::{source_map::SourceMap, Emitter}, parser, tokenizer::tokenizer::Tokenizer, vprint};
+use cache::{Cache, Entry};
 
 pub const VERSION: &str = "0.1.0";
-pub const COPPER_PATH: Lazy<String> = Lazy::new(|| std::env::var("COPPER_PATH").unwrap());
+pub static COPPER_PATH: Lazy<String> = Lazy::new(|| std::env::var("COPPER_PATH").unwrap());
 
 pub fn get_copper_version() -> String {
     let path = std::path::Path::new(&(*COPPER_PATH)).join("Cargo.toml");
@@ -66,123 +72,435 @@ pub fn print() {
     vprint!("Using Copper v{}", get_copper_version());
 }
 
-pub fn compile(files: Vec<String>, input_dir: Option<String>, output_dir: Option<String>) -> Vec<String> {
-    let copper_version = get_copper_version();
-    let mut all_dependencies = Vec::new();
+/// The generated Cargo project inside the output directory
+pub fn project_dir(output_dir: &Option<String>) -> String {
+    format!("{}/rust", output_dir.as_deref().unwrap_or("./dist").trim_end_matches('/'))
+}
 
-    for mut file in files {
-        file = file.replace(path::MAIN_SEPARATOR_STR, "/");
-        vprint!("🔨 Compiling {}", file);
+/// A Copper file with its diagnostics, the files it imports, and the Rust
+/// code generated from it when it has no errors
+struct Transpiled {
+    source: String,
+    diagnostics: Vec<Diagnostic>,
+    imports: Vec<String>,
+    output: Option<(String, SourceMap, Vec<String>)>,
+}
 
-        let c = fs::read(&file).unwrap();
-        let c = String::from_utf8(c).unwrap();
+enum Job {
+    /// Unchanged since the last run
+    Cached(Entry),
+    Transpiling(tokio::task::JoinHandle<Transpiled>),
+}
 
-        let mut tokenizer = Tokenizer::new(c);
-        let tokens = tokenizer.tokenize();
-        let mut parser = parser::Parser::new(tokens);
-        let result_code = parser.parse();
-        
-        // Collect detected dependencies
-        let dependencies = parser.get_required_dependencies();
-        for dep in dependencies {
-            if !all_dependencies.contains(&dep) {
-                all_dependencies.push(dep);
-            }
+// Runs on the worker pool, everything shared is done by `compile` in input order
+fn transpile(source: String, file: &str, root: &str, is_main: bool) -> Transpiled {
+    let mut tokenizer = Tokenizer::new(source.clone());
+    let tokens = tokenizer.tokenize();
+    let mut parser = parser::Parser::new(tokens);
+    let mut program = parser.parse();
+    let (imports, import_diagnostics) = modules::resolve_imports(&mut program, file, root);
+
+    let mut file_diagnostics = tokenizer.diagnostics().to_vec();
+    file_diagnostics.extend(import_diagnostics);
+    for diagnostic in parser.diagnostics() {
+        // Unclosed delimiters are found by both the tokenizer and the parser
+        let duplicate = file_diagnostics.iter().any(|d| d.message == diagnostic.message && d.primary == diagnostic.primary);
+        if !duplicate {
+            file_diagnostics.push(diagnostic.clone());
         }
+    }
+    // Type errors of a file that doesn't parse would be noise
+    let traits = modules::imported_traits(&imports);
+    let mut argument_types = Default::default();
+    if !file_diagnostics.iter().any(|d| d.is_error()) {
+        let checked = checker::check(&program, &traits);
+        file_diagnostics.extend(checked.diagnostics);
+        argument_types = checked.argument_types;
+    }
+
+    file_diagnostics.sort_by_key(|d| (d.primary.location.first_line, d.primary.location.first_column));
+
+    let output = if file_diagnostics.iter().any(|d| d.is_error()) {
+        None
+    } else {
+        let emitter = if is_main { Emitter::new() } else { Emitter::module() };
+        let mut emitter = emitter.with_argument_types(argument_types).with_traits(traits);
+        let code = emitter.emit(&program);
+        Some((code, emitter.source_map().clone(), emitter.get_required_dependencies()))
+    };
 
-        let basepath = &format!("{}/rust/src/", output_dir.clone().unwrap_or("./dist".to_string()));
-        fs::create_dir_all(basepath).unwrap();
+    Transpiled {
+        source,
+        diagnostics: file_diagnostics,
+        imports,
+        output,
+    }
+}
 
-        let mut path = format!("{}{}", basepath, file);
-        path = path.replace(".crs", ".rs").replace("\\", "/");
+/// Compiles the files and returns the dependencies they need, exits when a file has errors
+pub async fn compile(files: Vec<String>, input_dir: Option<String>, output_dir: Option<String>) -> Vec<String> {
+    match try_compile(files, input_dir, output_dir).await {
+        Some(dependencies) => dependencies,
+        None => std::process::exit(1),
+    }
+}
 
-        if let Some(ref input_dir) = input_dir {
-            let input_dir = input_dir.clone().replace("\\", "/").replace(".crs", ".rs");
-            path = path.replacen(&(input_dir+"/"), "", 1);
+/// Compiles the files, and the Copper files they import, and returns the
+/// dependencies they need, or `None` when a file has errors
+pub async fn try_compile(mut files: Vec<String>, input_dir: Option<String>, output_dir: Option<String>) -> Option<Vec<String>> {
+    let copper_version = get_copper_version();
+    let header = format!("// Generated by CForge v{} with Copper v{}", VERSION, copper_version);
+    let mut all_dependencies = Vec::new();
+    let mut failed_files = 0;
+
+    let cache_dir = output_dir.clone().unwrap_or("./dist".to_string());
+    let mut cache = Cache::load(&cache_dir);
+    let mut skipped_files = 0;
+
+    // Imports are resolved against the importing file, and their modules start
+    // at the input directory, or at the working directory for single files
+    let root = input_dir.clone().unwrap_or_default();
+    let input_dir = input_dir.map(|dir| modules::normalize_file(&dir));
+    let basepath = format!("{}/src/", project_dir(&output_dir));
+    let workers = Arc::new(Semaphore::new(std::thread::available_parallelism().map_or(4, |n| n.get())));
+
+    // `./src/main.crs` from the input and `src/main.crs` from an import are the same file
+    files = files.iter().filter(|file| file.ends_with(".crs")).map(|file| modules::normalize_file(file)).collect();
+    let mut seen = files.iter().cloned().collect::<HashSet<_>>();
+
+    // Imported files that weren't asked for are compiled in the next round
+    while !files.is_empty() {
+        // Files are transpiled in parallel, but reported and written in this order
+        files.sort();
+        let mut jobs = Vec::new();
+        let mut imported = Vec::new();
+
+        for file in std::mem::take(&mut files) {
+            let c = match fs::read_to_string(&file) {
+                Ok(c) => c,
+                Err(err) => {
+                    let diagnostic = Diagnostic::error(&format!("Cannot read `{}`: {}", file, err), Default::default())
+                        .with_code("E0200");
+                    diagnostics::report(&[diagnostic], "", &file);
+                    cache.remove(&file);
+                    failed_files += 1;
+                    continue;
+                },
+            };
+
+            // Files of the input directory go to the root of the crate
+            let relative = input_dir
+                .as_ref()
+                .and_then(|dir| file.strip_prefix(&format!("{}/", dir)))
+                .unwrap_or(&file);
+            let path = format!("{}{}", basepath, relative).replace(".crs", ".rs");
+
+            // Unchanged files keep what was generated last time
+            let hash = cache::hash(&c, &[VERSION, &copper_version, &path]);
+            let job = match cache.get(&file, &hash) {
+                Some(entry) => Job::Cached(entry.clone()),
+                None => {
+                    let workers = workers.clone();
+                    let (file, root) = (file.clone(), root.clone());
+                    let is_main = path == format!("{}main.rs", basepath);
+                    Job::Transpiling(tokio::spawn(async move {
+                        let _permit = workers.acquire_owned().await.unwrap();
+                        tokio::task::spawn_blocking(move || transpile(c, &file, &root, is_main)).await.unwrap()
+                    }))
+                },
+            };
+
+            jobs.push((file, path, hash, job));
         }
-        
-        let result = format!("// Generated by CForge v{} with Copper v{}\n{}", VERSION, copper_version, result_code);
-        // Create directories recursively if they don't exist
-        let path_obj = std::path::Path::new(&path);
-        
-        if let Some(parent) = path_obj.parent() {
-            fs::create_dir_all(parent).unwrap();
+
+        for (file, path, hash, job) in jobs {
+            let job = match job {
+                Job::Transpiling(job) => job,
+                Job::Cached(entry) => {
+                    vprint!("⏭️  Skipping {} (unchanged)", file);
+                    for dep in entry.dependencies {
+                        if !all_dependencies.contains(&dep) {
+                            all_dependencies.push(dep);
+                        }
+                    }
+                    imported.extend(entry.imports);
+                    skipped_files += 1;
+                    continue;
+                },
+            };
+
+            vprint!("🔨 Compiling {}", file);
+
+            let transpiled = job.await.unwrap();
+            diagnostics::report(&transpiled.diagnostics, &transpiled.source, &file);
+            imported.extend(transpiled.imports.iter().cloned());
+
+            let Some((result_code, mut source_map, dependencies)) = transpiled.output else {
+                cache.remove(&file);
+                failed_files += 1;
+                continue;
+            };
+
+            // Collect detected dependencies
+            for dep in &dependencies {
+                if !all_dependencies.contains(dep) {
+                    all_dependencies.push(dep.clone());
+                }
+            }
+
+            let result = format!("{}\n{}", header, result_code);
+            source_map.source = file.clone();
+            source_map.shift(1);
+            // Create directories recursively if they don't exist
+            let path_obj = std::path::Path::new(&path);
+            
+            if let Some(parent) = path_obj.parent() {
+                fs::create_dir_all(parent).unwrap();
+            }
+            // Rewriting identical files would make Cargo rebuild them
+            cache::write_if_changed(&path, &result);
+            cache::write_if_changed(&format!("{}.map", path), &serde_json::to_string(&source_map).unwrap());
+
+            cache.insert(&file, Entry {
+                hash,
+                output: path.clone(),
+                dependencies,
+                imports: transpiled.imports,
+            });
+
+            vprint!("  {} {} {}", file, "=>".yellow(), path);
         }
-        fs::write(path.clone(), result).unwrap();
 
-        vprint!("  {} {} {}", file, "=>".yellow(), path);
+        files = imported
+            .into_iter()
+            .map(|file| modules::normalize_file(&file))
+            .filter(|file| seen.insert(file.clone()))
+            .collect();
+    }
+
+    cache.save(&cache_dir);
+
+    if failed_files > 0 {
+        return None;
+    }
+
+    // Every file compiled so far is a module, not only the ones of this run
+    modules::write_module_tree(&basepath, &cache.outputs(), &header);
+
+    if skipped_files > 0 {
+        vprint!("⏭️  {} unchanged file(s) skipped", skipped_files);
     }
 
     println!("✅ Successfully compiled");
-    all_dependencies
+    Some(all_dependencies)
 }
 
-pub fn get_toml_package_name() -> String {
-    let toml =  fs::read("./dist/rust/Cargo.toml").unwrap();
+pub fn get_toml_package_name(output_dir: &Option<String>) -> String {
+    let toml =  fs::read(format!("{}/Cargo.toml", project_dir(output_dir))).unwrap();
     let toml = String::from_utf8(toml).unwrap();
 
     let name = toml.split("name = \"").collect::<Vec<&str>>()[1].split("\"").collect::<Vec<&str>>()[0];
 
-    return name.to_string();
+    name.to_string()
 }
 
-pub fn get_toml_package_version() -> String {
-    let toml =  fs::read("./dist/rust/Cargo.toml").unwrap();
+pub fn get_toml_package_version(output_dir: &Option<String>) -> String {
+    let toml =  fs::read(format!("{}/Cargo.toml", project_dir(output_dir))).unwrap();
     let toml = String::from_utf8(toml).unwrap();
 
     let version = toml.split("version = \"").collect::<Vec<&str>>()[1].split("\"").collect::<Vec<&str>>()[0];
 
-    return version.to_string();
+    version.to_string()
 }
 
-pub fn run() {
-    println!("🚀 Building and running project...");
-    
-    let mut build_cmd = std::process::Command::new("cargo");
-    build_cmd.arg("build");
-    let mut build_args: Vec<String> = Vec::new();
+pub fn check(output_dir: &Option<String>) {
+    println!("🔍 Checking project...");
+    let project_dir = project_dir(output_dir);
+
+    let mut check_cmd = std::process::Command::new("cargo");
+    check_cmd.arg("check").arg("--message-format=json");
     if let Ok(t) = std::env::var("CFORGE_TARGET") {
-        build_args.push("--target".to_string());
-        build_args.push(t);
+        check_cmd.arg("--target").arg(t);
+    }
+    check_cmd.current_dir(&project_dir);
+    let check_result = check_cmd.output().expect("Failed to execute cargo check");
+
+    let stdout = String::from_utf8_lossy(&check_result.stdout);
+    let errors = rustc::report(&stdout, &project_dir);
+
+    if !check_result.status.success() {
+        if errors == 0 {
+            eprintln!("{}", String::from_utf8_lossy(&check_result.stderr));
+        }
+        println!("❌ Check failed");
+        std::process::exit(1);
     }
-    if !build_args.is_empty() {
-        build_cmd.args(&build_args);
+
+    println!("✅ No errors found");
+}
+
+// Builds the Cargo project, reporting rustc's diagnostics on the Copper sources.
+// Returns the path of the binary, exits when the build fails.
+fn cargo_build(project_dir: &str, profile: &str, target: Option<&str>) -> String {
+    match try_cargo_build(project_dir, profile, target) {
+        Some(executable) => executable,
+        None => std::process::exit(1),
     }
-    build_cmd.current_dir("./dist/rust");
+}
+
+/// Builds the Cargo project like `cargo_build`, returns `None` when the build fails
+pub fn try_cargo_build(project_dir: &str, profile: &str, target: Option<&str>) -> Option<String> {
+    let mut build_cmd = std::process::Command::new("cargo");
+    build_cmd.arg("build").arg("--message-format=json").args(["--profile", profile]);
+    if let Some(t) = target {
+        build_cmd.args(["--target", t]);
+    }
+    build_cmd.current_dir(project_dir);
     let build_result = build_cmd.output().expect("Failed to execute cargo build");
 
+    let stdout = String::from_utf8_lossy(&build_result.stdout);
+    let errors = rustc::report(&stdout, project_dir);
+
     if !build_result.status.success() {
-        let stderr = String::from_utf8_lossy(&build_result.stderr);
-        println!("❌ Build failed:\n\n{}", stderr);
-        return;
+        if errors == 0 {
+            eprintln!("{}", String::from_utf8_lossy(&build_result.stderr));
+        }
+        println!("❌ Build failed");
+        return None;
     }
 
-    println!("✅ Build successful");
+    let executable = rustc::executable(&stdout);
+    if executable.is_none() {
+        println!("❌ Cargo did not produce a binary");
+    }
+    executable
+}
 
-    let mut run_cmd = std::process::Command::new("cargo");
-    run_cmd.arg("run");
-    let mut run_args: Vec<String> = Vec::new();
-    if let Ok(t) = std::env::var("CFORGE_TARGET") {
-        run_args.push("--target".to_string());
-        run_args.push(t);
+/// Builds the project with a Cargo profile and copies the binary to
+/// `<output>/bin/<profile>/<name>`, or `<output>/bin/<target>/<profile>/<name>`
+/// when cross-compiling
+pub fn build(output_dir: &Option<String>, profile: &str) {
+    println!("🏗️  Building project with the {} profile...", profile.bold());
+    let target = std::env::var("CFORGE_TARGET").ok();
+    let executable = cargo_build(&project_dir(output_dir), profile, target.as_deref());
+
+    let mut bin_dir = path::PathBuf::from(output_dir.as_deref().unwrap_or("./dist")).join("bin");
+    if let Some(t) = &target {
+        bin_dir = bin_dir.join(t);
+    }
+    let bin_dir = bin_dir.join(profile);
+    fs::create_dir_all(&bin_dir).unwrap();
+
+    let artifact = bin_dir.join(path::Path::new(&executable).file_name().unwrap());
+    fs::copy(&executable, &artifact).unwrap();
+
+    println!("✅ Build successful: {}", artifact.display().to_string().green());
+}
+
+/// Builds the project and runs it with `args`, sharing the terminal with it.
+/// Exits with the exit status of the program.
+///
+/// On Unix the program runs in a process group of its own, in the foreground
+/// of the terminal, so a Ctrl-C typed there reaches it once, and a SIGINT
+/// sent to cforge is forwarded to it.
+pub async fn run(output_dir: &Option<String>, profile: &str, args: &[String]) {
+    println!("🚀 Building and running project...");
+    let target = std::env::var("CFORGE_TARGET").ok();
+    let executable = cargo_build(&project_dir(output_dir), profile, target.as_deref());
+
+    println!("Running {} v{}:\n", get_toml_package_name(output_dir).bold(), get_toml_package_version(output_dir).bold());
+
+    let mut command = tokio::process::Command::new(&executable);
+    command.args(args);
+    #[cfg(unix)]
+    let foreground = own_process_group(&mut command);
+
+    let mut child = command.spawn().expect("Failed to run the compiled project");
+
+    let status = loop {
+        tokio::select! {
+            status = child.wait() => break status.expect("Failed to wait for the compiled project"),
+            // The program decides when to stop, the exit status comes from it
+            _ = tokio::signal::ctrl_c() => forward_interrupt(&child),
+        }
+    };
+
+    // Takes the terminal back from the program
+    #[cfg(unix)]
+    if foreground {
+        unsafe {
+            libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+            libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpgrp());
+        }
     }
-    if !run_args.is_empty() {
-        run_cmd.args(&run_args);
+
+    std::process::exit(exit_code(status));
+}
+
+// Starts the program in a process group of its own, which gets the terminal
+// when cforge has it, like a shell does with its jobs. Returns whether it does.
+#[cfg(unix)]
+fn own_process_group(command: &mut tokio::process::Command) -> bool {
+    command.process_group(0);
+
+    let foreground = unsafe { libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp() };
+    if foreground {
+        unsafe {
+            // Runs in the program before it starts, already in its own group
+            command.pre_exec(|| {
+                libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+                libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpgrp());
+                libc::signal(libc::SIGTTOU, libc::SIG_DFL);
+                Ok(())
+            });
+        }
     }
-    run_cmd.current_dir("./dist/rust");
-    let run = run_cmd.output().expect("Failed to execute cargo run");
 
-    if run.status.success() {
-        let stdout = String::from_utf8_lossy(&run.stdout);
-        println!("Running {} v{}:\n\n{}", get_toml_package_name().bold(), get_toml_package_version().bold(), stdout);
-    } else {
-        let stderr = String::from_utf8_lossy(&run.stderr);
-        println!("❌ Error running compiled project:\n\n{}", stderr);
+    foreground
+}
+
+// Ctrl-C typed in the terminal only reaches the program, so a SIGINT cforge
+// gets was sent to it alone, by `kill -INT` or a supervisor
+fn forward_interrupt(child: &tokio::process::Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGINT);
+        }
     }
+
+    // Windows delivers Ctrl-C to every process on the console
+    #[cfg(not(unix))]
+    let _ = child;
 }
 
-pub async fn generate_toml(extra_dependencies: Vec<String>) {
-    let properties = kson::read_properties(std::env::current_dir().unwrap().join("properties.kson").to_str().unwrap());
+// Programs killed by a signal exit with 128 + the signal, like in a shell
+pub fn exit_code(status: std::process::ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+
+    status.code().unwrap_or(1)
+}
+
+/// Generates the `Cargo.toml` of the project from `properties.kson`, or
+/// `Cargo.toml`, and exits when neither can be read
+pub async fn generate_toml(extra_dependencies: Vec<String>, output_dir: &Option<String>) {
+    if let Err(err) = try_generate_toml(extra_dependencies, output_dir).await {
+        println!("❌ {}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Generates `Cargo.toml` like `generate_toml`, but returns why the
+/// configuration could not be read instead of exiting. The previous
+/// `Cargo.toml` is left as it was then.
+pub async fn try_generate_toml(extra_dependencies: Vec<String>, output_dir: &Option<String>) -> Result<(), String> {
+    let properties = kson::read_properties(std::env::current_dir().unwrap().join("properties.kson").to_str().unwrap())?;
     let toml: String;
 
     vprint!("🔍 Debug: properties.0 (is_toml): {}", properties.0);
@@ -190,7 +508,7 @@ pub async fn generate_toml(extra_dependencies: Vec<String>) {
 
     if properties.0 {
         println!("⚠️  Warning: Detected Cargo.toml file. CForge now uses properties.kson as the main configuration file. Please migrate your configuration to properties.kson. See https://copper-lang.org/docs/cforge/properties for more information.");
-        let mut properties_obj = properties::Properties::from_toml(&properties.1).await;
+        let mut properties_obj = properties::Properties::from_toml(&properties.1).await?;
         
         // Add extra detected dependencies
         for dep in extra_dependencies {
@@ -200,7 +518,7 @@ pub async fn generate_toml(extra_dependencies: Vec<String>) {
         toml = properties_obj.to_toml();
         vprint!("Using Cargo.toml for configuration");
     } else {
-        let mut properties_obj = properties::Properties::from_kson(&properties.1).await;
+        let mut properties_obj = properties::Properties::from_kson(&properties.1).await?;
 
         // Add extra detected dependencies
         for dep in extra_dependencies {
@@ -211,6 +529,44 @@ pub async fn generate_toml(extra_dependencies: Vec<String>) {
         vprint!("Using properties.kson for configuration");
     }
 
-    fs::write("./dist/rust/Cargo.toml", toml).unwrap();
-    vprint!("📦 Cargo.toml generated")
+    cache::write_if_changed(&format!("{}/Cargo.toml", project_dir(output_dir)), &toml);
+    vprint!("📦 Cargo.toml generated");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_compiles_imports_of_dot_input_dir_once() {
+        let project = format!("./target/copper-compile-{}", std::process::id());
+        std::env::set_var("COPPER_PATH", &project);
+        fs::create_dir_all(format!("{}/src/models", project)).unwrap();
+        fs::create_dir_all(format!("{}/src/shared", project)).unwrap();
+        fs::write(format!("{}/src/main.crs", project), "import { User } from ./models/user\n").unwrap();
+        fs::write(format!("{}/src/models/user.crs", project), "import { twice } from ../shared/util\nclass User {}\n").unwrap();
+        fs::write(format!("{}/src/shared/util.crs", project), "func int twice(x: int) {\n    return x * 2\n}\n").unwrap();
+
+        // As `cforge run ./target/.../src` lists them
+        let input_dir = format!("{}/src", project);
+        let files = ["main.crs", "models/user.crs", "shared/util.crs"].map(|file| format!("{}/{}", input_dir, file)).to_vec();
+        let output_dir = format!("{}/dist", project);
+        let compiled = try_compile(files, Some(input_dir), Some(output_dir.clone())).await;
+
+        let src = format!("{}/rust/src", output_dir);
+        let main = fs::read_to_string(format!("{}/main.rs", src)).unwrap_or_default();
+        let mut generated = walkdir::WalkDir::new(&src)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().extension().is_some_and(|extension| extension == "rs"))
+            .map(|entry| entry.path().strip_prefix(&src).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect::<Vec<_>>();
+        generated.sort();
+        fs::remove_dir_all(&project).unwrap();
+
+        assert!(compiled.is_some());
+        assert!(main.ends_with("mod models;\nmod shared;\n"), "{}", main);
+        assert_eq!(generated, ["main.rs", "models/mod.rs", "models/user.rs", "shared/mod.rs", "shared/util.rs"]);
+    }
 }
@@ -0,0 +1,214 @@
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, fs, path::{Component, Path, PathBuf}};
+
+use crate::{diagnostics::Diagnostic, parser::{ast::{ItemKind, Program, StmtKind}, Parser}, tokenizer::tokenizer::Tokenizer};
+use super::cache::write_if_changed;
+
+// Starts the `mod` declarations appended to the generated files. They go at the
+// end so the lines of the source maps stay valid.
+const MODULES_MARKER: &str = "// Modules generated by CForge";
+
+/// Removes `.` and `..` from a relative path, `None` when it goes above its start
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            },
+            other => normalized.push(other),
+        }
+    }
+
+    Some(normalized)
+}
+
+/// Spelling of a Copper file used to tell files apart and to place their
+/// output, `./src\\main.crs` => `src/main.crs`
+pub fn normalize_file(file: &str) -> String {
+    let file = file.replace('\\', "/");
+    match normalize(Path::new(&file)) {
+        Some(normalized) => normalized.to_string_lossy().into_owned(),
+        None => file,
+    }
+}
+
+/// Rust module path of a Copper file inside `root`, `models/user.crs` => `["crate", "models", "user"]`
+fn module_path(file: &Path, root: &Path) -> Option<Vec<String>> {
+    let relative = file.strip_prefix(root).ok()?.with_extension("");
+    let mut path = vec!["crate".to_string()];
+    path.extend(relative.iter().map(|segment| segment.to_string_lossy().into_owned()));
+
+    // `main.crs` is the crate root
+    if path.len() == 2 && path[1] == "main" {
+        path.pop();
+    }
+
+    Some(path)
+}
+
+/// Rewrites the relative imports of `file` into `crate::` paths. Returns the
+/// Copper files they import, and a diagnostic for each one that doesn't exist.
+/// Modules start at `root`, the input directory, or the working directory
+/// when it's empty.
+pub fn resolve_imports(program: &mut Program, file: &str, root: &str) -> (Vec<String>, Vec<Diagnostic>) {
+    let mut imported = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    let directory = Path::new(file).parent().unwrap_or(Path::new(""));
+    let root = normalize(Path::new(root)).unwrap_or_default();
+
+    for stmt in &mut program.body {
+        let StmtKind::Item(item) = &mut stmt.kind else {
+            continue;
+        };
+        let ItemKind::Import(import) = &mut item.kind else {
+            continue;
+        };
+        if !import.relative {
+            continue;
+        }
+
+        let written = match import.path.first().map(String::as_str) {
+            Some("..") => import.path.join("/"),
+            _ => format!("./{}", import.path.join("/")),
+        };
+        let candidate = directory.join(format!("{}.crs", import.path.join("/")));
+        let target = normalize(&candidate);
+
+        let Some(target) = target.clone().filter(|target| target.is_file()) else {
+            diagnostics.push(
+                Diagnostic::error(&format!("Cannot find module `{}`", written), item.location.clone())
+                    .with_code("E0200")
+                    .with_label("no Copper file at this path")
+                    .with_help(&format!("create `{}` or fix the path", target.unwrap_or(candidate).display())),
+            );
+            continue;
+        };
+
+        let Some(path) = module_path(&target, &root) else {
+            diagnostics.push(
+                Diagnostic::error(&format!("Module `{}` is outside of the project", written), item.location.clone())
+                    .with_code("E0200")
+                    .with_label("imported from here")
+                    .with_note(&format!("modules have to be in `{}`", root.display())),
+            );
+            continue;
+        };
+
+        import.path = path;
+        import.relative = false;
+        imported.push(target.to_string_lossy().replace('\\', "/"));
+    }
+
+    (imported, diagnostics)
+}
+
+/// Method names of the traits declared in the Copper `files`, by trait name.
+/// Files that can't be read or parsed are reported when they are compiled.
+pub fn imported_traits(files: &[String]) -> HashMap<String, Vec<String>> {
+    let mut traits = HashMap::new();
+    for file in files {
+        if let Ok(source) = fs::read_to_string(file) {
+            let tokens = Tokenizer::new(source).tokenize();
+            traits.extend(Parser::new(tokens).parse().traits());
+        }
+    }
+    traits
+}
+
+/// Declares the generated modules in their parent: `mod` lines at the end of
+/// `main.rs` and of the generated module files, or a `mod.rs` for directories
+/// without a Copper file of their own. `outputs` are the generated files.
+pub fn write_module_tree(src_dir: &str, outputs: &[String], header: &str) {
+    let src_dir = Path::new(src_dir);
+    let mut children: BTreeMap<Vec<String>, BTreeSet<String>> = BTreeMap::new();
+    let mut files = BTreeSet::new();
+
+    for output in outputs {
+        let Ok(relative) = Path::new(output).strip_prefix(src_dir) else {
+            continue;
+        };
+        let segments = relative
+            .with_extension("")
+            .iter()
+            .map(|segment| segment.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        if segments == ["main"] {
+            continue;
+        }
+
+        for i in 0..segments.len() {
+            children.entry(segments[..i].to_vec()).or_default().insert(segments[i].clone());
+        }
+        files.insert(segments);
+    }
+
+    for (parent, modules) in children {
+        let declaration = if parent.is_empty() { "mod" } else { "pub mod" };
+        let declarations = modules
+            .iter()
+            .map(|module| format!("{} {};", declaration, module))
+            .collect::<Vec<_>>();
+
+        let directory = parent.iter().fold(src_dir.to_path_buf(), |path, segment| path.join(segment));
+        let generated_mod = directory.join("mod.rs");
+
+        let path = if parent.is_empty() {
+            src_dir.join("main.rs")
+        } else if files.contains(&parent) {
+            // A `mod.rs` left from before the module got its own file would be ambiguous
+            if fs::read_to_string(&generated_mod).is_ok_and(|code| code.starts_with(header)) {
+                fs::remove_file(&generated_mod).unwrap();
+            }
+            directory.with_extension("rs")
+        } else {
+            generated_mod
+        };
+
+        let code = match fs::read_to_string(&path) {
+            Ok(code) => code,
+            Err(_) if !parent.is_empty() => format!("{}\n", header),
+            Err(_) => continue,
+        };
+        let code = match code.find(MODULES_MARKER) {
+            Some(start) => code[..start].trim_end().to_string() + "\n",
+            None => code,
+        };
+
+        write_if_changed(
+            &path.to_string_lossy(),
+            &format!("{}\n{}\n{}\n", code, MODULES_MARKER, declarations.join("\n")),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{emitter::Emitter, parser::Parser, tokenizer::tokenizer::Tokenizer};
+
+    #[test]
+    fn test_resolves_relative_imports() {
+        let root = std::env::temp_dir().join(format!("copper-modules-{}", std::process::id()));
+        fs::create_dir_all(root.join("models")).unwrap();
+        fs::write(root.join("models/user.crs"), "class User {}\n").unwrap();
+
+        let source = "import { User } from ./models/user\nimport { Post } from ./models/post\n";
+        let tokens = Tokenizer::new(source.to_string()).tokenize();
+        let mut program = Parser::new(tokens).parse();
+
+        let root = root.to_string_lossy().into_owned();
+        let (imported, diagnostics) = resolve_imports(&mut program, &format!("{}/main.crs", root), &root);
+        let code = Emitter::new().emit(&program);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(imported, vec![format!("{}/models/user.crs", root)]);
+        assert!(code.contains("use crate::models::user::User;"));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "Cannot find module `./models/post`");
+        assert_eq!(diagnostics[0].primary.location.first_line, 2);
+    }
+}
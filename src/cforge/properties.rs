@@ -16,12 +16,10 @@ pub enum PropertyKind {
 
 macro_rules! validate {
     ($struct: expr, $field: ident, $to_validate: expr) => {
-        let err_msg = "Invalid propertie value".to_string();
-
         if let Some($field) = $to_validate {
             $struct.$field = $field;
         } else {
-            panic!("{}", err_msg);
+            return Err(format!("Invalid or missing property value for {}", stringify!($field)));
         }
     };
 }
@@ -52,19 +50,29 @@ pub struct Dependency {
     tag: Option<String>,
 }
 
+pub struct Profile {
+    name: String,
+    settings: Vec<(String, toml::Value)>,
+}
+
 pub struct Properties<'a> {
     name: &'a str,
     version: &'a str,
     edition: u64,
     dependencies: Vec<Dependency>,
+    profiles: Vec<Profile>,
 }
 
+const PROFILE_SETTINGS: [&str; 6] = ["inherits", "opt-level", "lto", "debug", "strip", "panic"];
+
+#[allow(clippy::declare_interior_mutable_const)]
 const METADATA: Lazy<cargo::Metadata> = Lazy::new(|| {
     let mut extractor = cargo::Extractor::new();
     extractor.fetch_metadata()
 });
 
 // Map dependencies from properties.kson to Cargo.toml
+#[allow(clippy::needless_borrow, clippy::unnecessary_unwrap, clippy::borrow_interior_mutable_const)]
 async fn map_deps<'a>(props: &mut Properties<'a>, deps: &'a Value, mode: MapDepMode) {
     let deps = deps.as_object().unwrap();
 
@@ -179,18 +187,59 @@ async fn map_deps<'a>(props: &mut Properties<'a>, deps: &'a Value, mode: MapDepM
     }
 }
 
+// Map profiles from properties.kson to Cargo.toml
+fn map_profiles(props: &mut Properties, profiles: &Value) {
+    let profiles = profiles.as_object().unwrap();
+
+    for (name, settings) in profiles {
+        let mut profile = Profile {
+            name: name.to_string(),
+            settings: Vec::new(),
+        };
+
+        for (key, value) in settings.as_object().into_iter().flatten() {
+            // KSON keys use underscores, Cargo uses dashes
+            let key = key.replace('_', "-");
+            if !PROFILE_SETTINGS.contains(&key.as_str()) {
+                println!("⚠️  Unknown setting {} in profile {}, ignoring it", key.yellow(), name.yellow());
+                continue;
+            }
+
+            // The TOML writer quotes and escapes the values
+            let value = match value {
+                Value::String(_) | Value::Bool(_) | Value::Number(_) => toml::Value::try_from(value).ok(),
+                _ => None,
+            };
+            let Some(value) = value else {
+                println!("⚠️  Invalid value for {} in profile {}, ignoring it", key.yellow(), name.yellow());
+                continue;
+            };
+            profile.settings.push((key, value));
+        }
+
+        // Cargo's own profiles need no `inherits`, custom ones start from `release`
+        if !matches!(name.as_str(), "dev" | "release" | "test" | "bench") && !profile.settings.iter().any(|(key, _)| key == "inherits") {
+            profile.settings.insert(0, ("inherits".to_string(), toml::Value::String("release".to_string())));
+        }
+
+        vprint!("🔍 Debug: Profile '{}' with {} settings", name, profile.settings.len());
+        props.profiles.push(profile);
+    }
+}
 
 impl<'a> Properties<'a> {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             name: "",
             version: "",
             edition: 2018,
             dependencies: Vec::new(),
+            profiles: Vec::new(),
         }
     }
 
-    pub async fn from_toml(toml: &'a Value) -> Self {
+    pub async fn from_toml(toml: &'a Value) -> Result<Self, String> {
         let mut properties = Self::new();
 
         validate!(properties, name, toml["package"]["name"].as_str());
@@ -213,10 +262,14 @@ impl<'a> Properties<'a> {
             map_deps(&mut properties, &toml["build-dependencies"], MapDepMode::Build).await;
         }
 
-        properties
+        if toml["profile"].is_object() {
+            map_profiles(&mut properties, &toml["profile"]);
+        }
+
+        Ok(properties)
     }
 
-    pub async fn from_kson(kson: &'a Value) -> Self {
+    pub async fn from_kson(kson: &'a Value) -> Result<Self, String> {
         let mut properties = Self::new();
 
         vprint!("🔍 Debug: Full KSON JSON: {}", serde_json::to_string_pretty(kson).unwrap());
@@ -246,7 +299,11 @@ impl<'a> Properties<'a> {
             map_deps(&mut properties, &kson["build_dependencies"], MapDepMode::Build).await;
         }
 
-        properties
+        if kson["profiles"].is_object() {
+            map_profiles(&mut properties, &kson["profiles"]);
+        }
+
+        Ok(properties)
     }
 
     pub async fn add_dependency(&mut self, name: &str, version: &str) {
@@ -308,6 +365,14 @@ impl<'a> Properties<'a> {
             format!("\n[dependencies]\n{}", deps_str)
         };
 
+        let mut profiles_section = String::new();
+        for profile in &self.profiles {
+            profiles_section.push_str(&format!("\n[profile.{}]\n", profile.name));
+            for (key, value) in &profile.settings {
+                profiles_section.push_str(&format!("{} = {}\n", key, value));
+            }
+        }
+
         format!(r#"# Cargo.toml generated by CForge v{}
 [package]
 name = "{name}"
@@ -316,12 +381,42 @@ edition = "{edition}"
 
 [[bin]]
 name = "{name}"
-path = "src/main.rs"{dependencies_section}"#, 
+path = "src/main.rs"
+{dependencies_section}{profiles_section}"#, 
             std::env::var("CFORGE_VERSION").unwrap(), 
             name = self.name, 
             version = self.version, 
             edition = self.edition, 
-            dependencies_section = dependencies_section
+            dependencies_section = dependencies_section,
+            profiles_section = profiles_section
         )
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_to_toml() {
+        std::env::set_var("CFORGE_VERSION", "0.1.0");
+        let profiles = serde_json::json!({
+            "release": { "opt_level": 3, "lto": true },
+            "dist": { "strip": "debug\"info" },
+            "small": { "inherits": "dev", "opt_level": "s" },
+        });
+
+        let mut properties = Properties::new();
+        map_profiles(&mut properties, &profiles);
+        let toml = properties.to_toml();
+
+        assert!(toml.contains("\n[profile.release]\nlto = true\nopt-level = 3\n"));
+        assert!(toml.contains("\n[profile.small]\ninherits = \"dev\"\nopt-level = \"s\"\n"));
+
+        // The quote in the value survives the round trip
+        let table = toml.parse::<toml::Table>().unwrap();
+        let dist = &table["profile"]["dist"];
+        assert_eq!(dist["inherits"].as_str(), Some("release"));
+        assert_eq!(dist["strip"].as_str(), Some("debug\"info"));
+    }
+}
@@ -0,0 +1,182 @@
+use std::{collections::HashMap, fs, path::Path};
+use colored::Colorize;
+use serde_json::Value;
+
+use crate::{diagnostics::{Diagnostic, Label, Severity}, emitter::source_map::SourceMap};
+
+/// A generated Rust file with the map and source of the Copper file it came from
+struct Generated {
+    map: SourceMap,
+    source: String,
+}
+
+/// Prints the compiler messages of cargo's `--message-format=json` output.
+/// Messages about generated code are shown on the Copper file they come from,
+/// the others are printed the way rustc rendered them. Returns the number of errors.
+pub fn report(output: &str, project_dir: &str) -> usize {
+    let mut generated: HashMap<String, Option<Generated>> = HashMap::new();
+    let mut errors = 0;
+
+    for line in output.lines() {
+        let Ok(event) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        if event["reason"] != "compiler-message" {
+            continue;
+        }
+
+        let message = &event["message"];
+        let level = message["level"].as_str().unwrap_or_default();
+        let text = message["message"].as_str().unwrap_or_default();
+        let spans = message["spans"].as_array().cloned().unwrap_or_default();
+
+        // Our own summary is printed instead of these
+        if level == "failure-note" || (spans.is_empty() && text.starts_with("aborting due to")) {
+            continue;
+        }
+        if level.starts_with("error") {
+            errors += 1;
+        }
+
+        let primary = spans.iter().find(|span| span["is_primary"] == true);
+        let file_name = primary.and_then(|span| span["file_name"].as_str()).unwrap_or_default();
+        let file = generated
+            .entry(file_name.to_string())
+            .or_insert_with(|| load(&Path::new(project_dir).join(file_name)));
+
+        match file.as_ref().and_then(|file| to_diagnostic(message, &file.map).map(|diagnostic| (diagnostic, file))) {
+            Some((diagnostic, file)) => eprintln!("{}", diagnostic.render(&file.source, &file.map.source)),
+            None => eprint!("{}", message["rendered"].as_str().unwrap_or(text)),
+        }
+    }
+
+    if errors > 0 {
+        eprintln!(
+            "{}: could not compile the generated project due to {} previous error{}",
+            "error".red().bold(),
+            errors,
+            if errors == 1 { "" } else { "s" },
+        );
+    }
+
+    errors
+}
+
+/// Path of the binary reported by cargo's `--message-format=json` output
+pub fn executable(output: &str) -> Option<String> {
+    output
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|event| event["reason"] == "compiler-artifact")
+        .find_map(|event| event["executable"].as_str().map(String::from))
+}
+
+// Source maps are written next to the generated files
+fn load(path: &Path) -> Option<Generated> {
+    let map = fs::read_to_string(format!("{}.map", path.display())).ok()?;
+    let map: SourceMap = serde_json::from_str(&map).ok()?;
+    let source = fs::read_to_string(&map.source).ok()?;
+
+    Some(Generated { map, source })
+}
+
+fn span_position(span: &Value) -> ((usize, usize), (usize, usize)) {
+    let number = |key: &str| span[key].as_u64().unwrap_or_default() as usize;
+
+    (
+        (number("line_start"), number("column_start")),
+        (number("line_end"), number("column_end")),
+    )
+}
+
+/// Converts a rustc diagnostic whose primary span is in the generated file of `map`
+fn to_diagnostic(message: &Value, map: &SourceMap) -> Option<Diagnostic> {
+    let spans = message["spans"].as_array()?;
+    let primary = spans.iter().find(|span| span["is_primary"] == true)?;
+    let (start, end) = span_position(primary);
+    let location = map.lookup(start, end)?.clone();
+
+    let severity = match message["level"].as_str() {
+        Some("warning") => Severity::Warning,
+        _ => Severity::Error,
+    };
+    let mut diagnostic = Diagnostic::new(severity, message["message"].as_str().unwrap_or_default(), location);
+    diagnostic.code = message["code"]["code"].as_str().map(String::from);
+    diagnostic.primary.message = primary["label"].as_str().map(String::from);
+
+    for span in spans.iter().filter(|span| span["is_primary"] != true && span["file_name"] == primary["file_name"]) {
+        let (start, end) = span_position(span);
+        if let Some(location) = map.lookup(start, end) {
+            diagnostic.secondary.push(Label {
+                location: location.clone(),
+                message: span["label"].as_str().map(String::from),
+            });
+        }
+    }
+
+    for child in message["children"].as_array().into_iter().flatten() {
+        let text = child["message"].as_str().unwrap_or_default();
+        match child["level"].as_str() {
+            Some("help") if diagnostic.help.is_none() => diagnostic.help = Some(text.to_string()),
+            Some("help") => diagnostic.notes.push(format!("help: {}", text)),
+            _ => diagnostic.notes.push(text.to_string()),
+        }
+    }
+
+    diagnostic.notes.push(format!(
+        "in the generated code at {}:{}:{}",
+        primary["file_name"].as_str().unwrap_or_default(),
+        start.0,
+        start.1,
+    ));
+
+    Some(diagnostic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{emitter::source_map::Mapping, tokenizer::tokens::LocationData};
+
+    #[test]
+    fn test_points_at_copper_location() {
+        let location = LocationData {
+            first_line: 2,
+            first_column: 9,
+            last_line: 2,
+            last_column: 15,
+            range: (0, 0),
+        };
+        let map = SourceMap {
+            source: "main.crs".to_string(),
+            mappings: vec![Mapping {
+                start: (3, 17),
+                end: (3, 24),
+                location: location.clone(),
+            }],
+        };
+
+        let message = serde_json::json!({
+            "message": "cannot find value `missing` in this scope",
+            "code": { "code": "E0425" },
+            "level": "error",
+            "spans": [{
+                "file_name": "src/main.rs",
+                "line_start": 3,
+                "line_end": 3,
+                "column_start": 17,
+                "column_end": 24,
+                "is_primary": true,
+                "label": "not found in this scope",
+            }],
+            "children": [],
+        });
+
+        let diagnostic = to_diagnostic(&message, &map).unwrap();
+        assert_eq!(diagnostic.code.as_deref(), Some("E0425"));
+        assert_eq!(diagnostic.primary.location, location);
+        assert_eq!(diagnostic.primary.message.as_deref(), Some("not found in this scope"));
+        assert_eq!(diagnostic.notes, vec!["in the generated code at src/main.rs:3:17"]);
+    }
+}
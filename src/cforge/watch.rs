@@ -0,0 +1,192 @@
+use std::{collections::HashMap, fs, path::Path, time::{Duration, SystemTime}};
+use colored::Colorize;
+use tokio::process::Child;
+
+use crate::{cforge::{self, cache::Cache}, vprint};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const CONFIG_FILES: [&str; 2] = ["properties.kson", "Cargo.toml"];
+
+/// What `cforge watch` works on
+pub struct WatchOptions {
+    pub files: Vec<String>,
+    pub input_dir: Option<String>,
+    pub output_dir: Option<String>,
+    /// Rebuild and restart the program after every change
+    pub restart: bool,
+    pub profile: String,
+    pub args: Vec<String>,
+}
+
+// The Copper files to compile, directories are walked again to see new files
+// and the files imported by single files are taken from the compile cache
+fn sources(options: &WatchOptions) -> Vec<String> {
+    match &options.input_dir {
+        Some(dir) => walkdir::WalkDir::new(dir)
+            .follow_links(true)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| e.path().is_file() && e.path().extension().is_some_and(|ext| ext == "crs"))
+            .map(|e| e.path().to_string_lossy().into_owned())
+            .collect(),
+        None => {
+            let cache = Cache::load(options.output_dir.as_deref().unwrap_or("./dist"));
+            let mut files = options.files.clone();
+            for file in cache.files() {
+                if !files.contains(file) {
+                    files.push(file.clone());
+                }
+            }
+            files
+        },
+    }
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+fn snapshot(files: &[String]) -> HashMap<String, SystemTime> {
+    files
+        .iter()
+        .map(String::as_str)
+        .chain(CONFIG_FILES)
+        .filter_map(|file| modified(file).map(|time| (file.to_string(), time)))
+        .collect()
+}
+
+async fn restart(options: &WatchOptions, child: &mut Option<Child>) {
+    if let Some(mut running) = child.take() {
+        let _ = running.kill().await;
+    }
+
+    let target = std::env::var("CFORGE_TARGET").ok();
+    let Some(executable) = cforge::try_cargo_build(&cforge::project_dir(&options.output_dir), &options.profile, target.as_deref()) else {
+        return;
+    };
+
+    println!("🚀 Running {}\n", Path::new(&executable).file_name().unwrap().to_string_lossy().bold());
+    match tokio::process::Command::new(&executable).args(&options.args).spawn() {
+        Ok(spawned) => *child = Some(spawned),
+        Err(err) => println!("❌ Failed to run the compiled project: {}", err),
+    }
+}
+
+// Generates `Cargo.toml` again, reports a configuration that cannot be read
+// and keeps the last one that could
+async fn update_toml(dependencies: &Option<Vec<String>>, options: &WatchOptions) -> bool {
+    match cforge::try_generate_toml(dependencies.clone().unwrap_or_default(), &options.output_dir).await {
+        Ok(()) => true,
+        Err(err) => {
+            println!("❌ {}", err.trim_end());
+            println!("⚠️  Keeping the last valid configuration until this is fixed");
+            false
+        },
+    }
+}
+
+/// Compiles the project, then recompiles the Copper files that change until
+/// Ctrl-C is pressed. `Cargo.toml` is only generated again when the
+/// configuration or the needed dependencies change, and stays as it was while
+/// the configuration has errors.
+pub async fn watch(options: WatchOptions) {
+    let files = sources(&options);
+    let mut snapshot = snapshot(&files);
+    let mut child = None;
+
+    // `None` until every file compiled once, the dependencies are incomplete before that
+    let mut dependencies = cforge::try_compile(files, options.input_dir.clone(), options.output_dir.clone()).await;
+    update_toml(&dependencies, &options).await;
+    if options.restart {
+        restart(&options, &mut child).await;
+    }
+
+    println!("👀 Watching for changes, press Ctrl-C to stop");
+
+    loop {
+        let exited = async {
+            match child.as_mut() {
+                Some(running) => running.wait().await.ok(),
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            _ = tokio::time::sleep(POLL_INTERVAL) => {},
+            status = exited => {
+                if let Some(status) = status {
+                    println!("\n🏁 Program exited with code {}, waiting for changes", cforge::exit_code(status));
+                }
+                child = None;
+                continue;
+            },
+            _ = tokio::signal::ctrl_c() => {
+                if let Some(mut running) = child.take() {
+                    let _ = running.kill().await;
+                }
+                println!("\n👋 Stopped watching");
+                return;
+            },
+        }
+
+        let current = self::snapshot(&sources(&options));
+        let changed = current
+            .iter()
+            .filter(|(file, time)| snapshot.get(*file) != Some(*time))
+            .map(|(file, _)| file.clone())
+            .collect::<Vec<_>>();
+        snapshot = current;
+
+        if changed.is_empty() {
+            continue;
+        }
+
+        println!("\n🔄 {} changed", changed.join(", ").yellow());
+
+        let config_changed = changed.iter().any(|file| CONFIG_FILES.contains(&file.as_str()));
+        let sources_changed = changed
+            .into_iter()
+            .filter(|file| !CONFIG_FILES.contains(&file.as_str()))
+            .collect::<Vec<_>>();
+
+        // Unchanged files are skipped by the compile cache anyway
+        let sources_changed = match dependencies {
+            Some(_) => sources_changed,
+            None => sources(&options),
+        };
+
+        let recompiled = !sources_changed.is_empty();
+        let mut dependencies_changed = false;
+        if recompiled {
+            vprint!("🔨 Recompiling {} file(s)", sources_changed.len());
+            let Some(found) = cforge::try_compile(sources_changed, options.input_dir.clone(), options.output_dir.clone()).await else {
+                // The snapshot already moved past the configuration, so apply it now
+                if config_changed {
+                    update_toml(&dependencies, &options).await;
+                }
+                continue;
+            };
+
+            let known = dependencies.get_or_insert_with(|| {
+                dependencies_changed = true;
+                Vec::new()
+            });
+            for dep in found {
+                if !known.contains(&dep) {
+                    known.push(dep);
+                    dependencies_changed = true;
+                }
+            }
+        }
+
+        let mut config_read = true;
+        if config_changed || dependencies_changed {
+            config_read = update_toml(&dependencies, &options).await;
+        }
+
+        // A broken configuration does not change what runs
+        if options.restart && (config_read || recompiled) {
+            restart(&options, &mut child).await;
+        }
+    }
+}
@@ -0,0 +1,1291 @@
+//! Type checks a parsed Copper program before any Rust is emitted, so type
+//! errors point at the Copper source rather than at the generated code.
+//!
+//! The checker only knows what the file declares: its classes, structs,
+//! enums and functions, the local variables it can infer a type for, and
+//! Rust's primitive and collection types. Whatever it can't tell the type of,
+//! such as generics, imported items or most method calls, is left to rustc.
+
+use std::collections::HashMap;
+
+use crate::{
+    diagnostics::Diagnostic,
+    parser::{ast::*, utils::{convert_type, is_float_literal, number_suffix, split_number}},
+    tokenizer::tokens::LocationData,
+};
+
+const INTEGER_TYPES: [&str; 12] = ["i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize"];
+// Types the checker knows without a declaration, by their last path segment
+const BUILTIN_TYPES: [&str; 10] = ["f32", "f64", "bool", "char", "str", "String", "Vec", "HashMap", "HashSet", "Option"];
+// Copper names of Rust types, for messages
+const COPPER_NAMES: [(&str, &str); 14] = [
+    ("i64", "int"),
+    ("f64", "float"),
+    ("u64", "uint"),
+    ("i8", "int8"),
+    ("i16", "int16"),
+    ("i32", "int32"),
+    ("u8", "uint8"),
+    ("u16", "uint16"),
+    ("u32", "uint32"),
+    ("f32", "float32"),
+    ("String", "string"),
+    ("Vec", "list"),
+    ("HashMap", "map"),
+    ("HashSet", "set"),
+];
+
+/// Type of an expression as far as the checker can tell
+#[derive(Debug, Clone, PartialEq)]
+enum Ty {
+    /// A type by the last segment of its Rust path, `i64`, `String`, `HashMap<String, i64>`
+    Named(String, Vec<Ty>),
+    /// `&T` or `&mut T`
+    Ref(Box<Ty>),
+    /// `()` when empty
+    Tuple(Vec<Ty>),
+    /// Array or slice, their length isn't checked
+    Array(Box<Ty>),
+    /// Number literal without a suffix, fits any integer type
+    Integer,
+    /// Float literal without a suffix
+    Float,
+    /// Anything the checker doesn't know the type of, fits every type
+    Unknown,
+}
+
+impl Ty {
+    fn named(name: &str) -> Self {
+        Ty::Named(name.to_string(), Vec::new())
+    }
+
+    fn is_named(&self, name: &str) -> bool {
+        matches!(self, Ty::Named(named, _) if named == name)
+    }
+
+    fn is_integer(&self) -> bool {
+        match self {
+            Ty::Integer => true,
+            Ty::Named(name, _) => INTEGER_TYPES.contains(&name.as_str()),
+            _ => false,
+        }
+    }
+
+    fn is_float(&self) -> bool {
+        match self {
+            Ty::Float => true,
+            Ty::Named(name, _) => name == "f32" || name == "f64",
+            _ => false,
+        }
+    }
+
+    fn is_number(&self) -> bool {
+        self.is_integer() || self.is_float()
+    }
+
+    fn is_literal(&self) -> bool {
+        matches!(self, Ty::Integer | Ty::Float)
+    }
+
+    // Looks through references, `&&T` => `T`
+    fn referent(&self) -> &Ty {
+        match self {
+            Ty::Ref(inner) => inner.referent(),
+            ty => ty,
+        }
+    }
+
+    // `integer` or `` `int` `` in messages
+    fn describe(&self) -> String {
+        match self {
+            Ty::Integer => "integer".to_string(),
+            Ty::Float => "floating-point number".to_string(),
+            ty => format!("`{}`", ty),
+        }
+    }
+}
+
+/// Copper's spelling of the type
+impl std::fmt::Display for Ty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |types: &[Ty]| types.iter().map(Ty::to_string).collect::<Vec<_>>().join(", ");
+
+        match self {
+            Ty::Named(name, args) if name == "Option" && args.len() == 1 => write!(f, "{}?", args[0]),
+            Ty::Named(name, args) => {
+                let name = COPPER_NAMES.iter().find(|(rust, _)| rust == name).map_or(name.as_str(), |(_, copper)| copper);
+                match args.is_empty() {
+                    true => write!(f, "{}", name),
+                    false => write!(f, "{}<{}>", name, join(args)),
+                }
+            },
+            Ty::Ref(inner) => write!(f, "&{}", inner),
+            Ty::Tuple(types) if types.is_empty() => write!(f, "void"),
+            Ty::Tuple(types) => write!(f, "tuple<{}>", join(types)),
+            Ty::Array(inner) => write!(f, "{}[]", inner),
+            Ty::Integer => write!(f, "int"),
+            Ty::Float => write!(f, "float"),
+            Ty::Unknown => write!(f, "_"),
+        }
+    }
+}
+
+/// A function, method or constructor the arguments of calls are checked against
+#[derive(Debug, Clone)]
+struct Signature {
+    /// `Function`, `Method` or `Constructor`
+    kind: &'static str,
+    /// As written in calls, `add`, `greet` or `Point::new`
+    name: String,
+    /// Name, type, type as written and location of each parameter
+    params: Vec<(String, Ty, Type, LocationData)>,
+    returns: Ty,
+    location: LocationData,
+}
+
+#[derive(Debug, Clone)]
+struct FieldInfo {
+    ty: Ty,
+    location: LocationData,
+}
+
+/// A class, struct or enum declared in the file, with the functions of its `impl` blocks
+#[derive(Debug, Default)]
+struct TypeInfo {
+    parent: Option<String>,
+    fields: HashMap<String, FieldInfo>,
+    /// Methods taking `self`
+    methods: HashMap<String, Signature>,
+    /// Constructors and static methods, called as `Name::function(...)`
+    functions: HashMap<String, Signature>,
+}
+
+/// Types of call arguments as their parameters declare them, by the range of
+/// the argument in the source
+pub type ArgumentTypes = HashMap<(usize, usize), Type>;
+
+/// What the checker found in a program
+pub struct Checked {
+    pub diagnostics: Vec<Diagnostic>,
+    /// The emitter turns list, map and string literal arguments into these types
+    pub argument_types: ArgumentTypes,
+}
+
+/// Type checks `program` and returns the errors it found. `imported_traits`
+/// are the traits of the Copper files it imports.
+pub fn check(program: &Program, imported_traits: &HashMap<String, Vec<String>>) -> Checked {
+    let mut checker = Checker {
+        traits: program.traits().into_keys().chain(imported_traits.keys().cloned()).collect(),
+        ..Default::default()
+    };
+    checker.collect(&program.body);
+    for stmt in &program.body {
+        match &stmt.kind {
+            StmtKind::Item(item) => checker.check_item(item),
+            _ => checker.check_stmt(stmt),
+        }
+    }
+    Checked {
+        diagnostics: checker.diagnostics,
+        argument_types: checker.argument_types,
+    }
+}
+
+#[derive(Default)]
+struct Checker {
+    types: HashMap<String, TypeInfo>,
+    /// Variant names by enum
+    enums: HashMap<String, Vec<String>>,
+    functions: HashMap<String, Signature>,
+    constants: HashMap<String, Ty>,
+    /// Local variables, innermost scope last
+    scopes: Vec<HashMap<String, Ty>>,
+    /// Generic parameters in scope, their types are unknown
+    generics: Vec<String>,
+    /// Class, struct or type of the `impl` being checked, `Self` refers to it
+    class: Option<String>,
+    /// Declared return type of the function being checked, `None` in closures
+    returns: Option<(Ty, LocationData)>,
+    argument_types: ArgumentTypes,
+    /// Copper traits whose methods are known, the others can't be implemented by classes
+    traits: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    // Declarations of the file, the types first as signatures refer to them
+    fn collect(&mut self, body: &[Stmt]) {
+        let items = body
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                StmtKind::Item(item) => Some(item),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for item in &items {
+            match &item.kind {
+                ItemKind::Class(class) => {
+                    let parent = class.extends.as_ref().and_then(type_path_name).map(str::to_string);
+                    self.types.insert(class.name.clone(), TypeInfo { parent, ..Default::default() });
+                },
+                ItemKind::Struct(structure) => {
+                    self.types.insert(structure.name.clone(), TypeInfo::default());
+                },
+                ItemKind::Enum(enumeration) => {
+                    let variants = enumeration.variants.iter().map(|variant| variant.name.clone()).collect();
+                    self.enums.insert(enumeration.name.clone(), variants);
+                    self.types.insert(enumeration.name.clone(), TypeInfo::default());
+                },
+                _ => {},
+            }
+        }
+
+        for item in &items {
+            match &item.kind {
+                ItemKind::Function(function) => {
+                    self.generics = generic_names(&function.generics);
+                    let signature = self.signature("Function", &function.name, function);
+                    self.functions.insert(function.name.clone(), signature);
+                },
+                ItemKind::Class(class) => self.collect_class(class),
+                ItemKind::Struct(structure) => {
+                    self.generics = generic_names(&structure.generics);
+                    let fields = self.fields(&structure.fields);
+                    self.types.get_mut(&structure.name).unwrap().fields = fields;
+                },
+                ItemKind::Impl(implementation) => {
+                    let Some(name) = type_path_name(&implementation.target).filter(|name| self.types.contains_key(*name)) else {
+                        continue;
+                    };
+                    self.class = Some(name.to_string());
+                    for method in &implementation.methods {
+                        self.generics = generic_names(&implementation.generics);
+                        self.generics.extend(generic_names(&method.generics));
+                        self.add_method(name, method);
+                    }
+                },
+                ItemKind::Const(constant) => {
+                    let ty = self.resolve(&constant.ty);
+                    self.constants.insert(constant.name.clone(), ty);
+                },
+                _ => {},
+            }
+            self.generics.clear();
+            self.class = None;
+        }
+    }
+
+    fn collect_class(&mut self, class: &Class) {
+        self.class = Some(class.name.clone());
+        self.generics = generic_names(&class.generics);
+
+        let fields = self.fields(&class.fields);
+        let returns = Ty::named(&class.name);
+        let constructors = class.constructors
+            .iter()
+            .map(|constructor| {
+                let name = format!("{}::{}", class.name, constructor.name);
+                let signature = Signature {
+                    kind: "Constructor",
+                    name,
+                    params: self.params(&constructor.params),
+                    returns: returns.clone(),
+                    location: constructor.location.clone(),
+                };
+                (constructor.name.clone(), signature)
+            })
+            .collect::<Vec<_>>();
+
+        let info = self.types.get_mut(&class.name).unwrap();
+        info.fields = fields;
+        info.functions.extend(constructors);
+
+        let class_generics = self.generics.clone();
+        for method in &class.methods {
+            self.generics = class_generics.clone();
+            self.generics.extend(generic_names(&method.generics));
+            self.add_method(&class.name, method);
+        }
+    }
+
+    // A method of a class or struct, or a static function without `self`
+    fn add_method(&mut self, name: &str, method: &Function) {
+        let takes_self = method.params.iter().any(|param| matches!(param, Param::SelfParam { .. }));
+        let (kind, called) = match takes_self {
+            true => ("Method", method.name.clone()),
+            false => ("Function", format!("{}::{}", name, method.name)),
+        };
+        let signature = self.signature(kind, &called, method);
+
+        let info = self.types.get_mut(name).unwrap();
+        match takes_self {
+            true => info.methods.insert(method.name.clone(), signature),
+            false => info.functions.insert(method.name.clone(), signature),
+        };
+    }
+
+    fn signature(&self, kind: &'static str, name: &str, function: &Function) -> Signature {
+        Signature {
+            kind,
+            name: name.to_string(),
+            params: self.params(&function.params),
+            returns: function.return_type.as_ref().map_or(Ty::Tuple(Vec::new()), |ty| self.resolve(ty)),
+            location: function.location.clone(),
+        }
+    }
+
+    // Named parameters, `self` isn't passed as an argument
+    fn params(&self, params: &[Param]) -> Vec<(String, Ty, Type, LocationData)> {
+        params
+            .iter()
+            .filter_map(|param| match param {
+                Param::SelfParam { .. } => None,
+                Param::Named { name, ty, location, .. } => Some((name.clone(), self.resolve(ty), ty.clone(), location.clone())),
+            })
+            .collect()
+    }
+
+    fn is_variant(&self, enumeration: &str, name: &str) -> bool {
+        self.enums.get(enumeration).is_some_and(|variants| variants.iter().any(|variant| variant == name))
+    }
+
+    fn fields(&self, fields: &[Field]) -> HashMap<String, FieldInfo> {
+        fields
+            .iter()
+            .map(|field| (field.name.clone(), FieldInfo { ty: self.resolve(&field.ty), location: field.location.clone() }))
+            .collect()
+    }
+
+    /// The checker's view of a declared type, unknown for generics and the
+    /// types neither Rust nor the file declares
+    fn resolve(&self, ty: &Type) -> Ty {
+        match &ty.kind {
+            TypeKind::Path { segments, args } => {
+                let Some(last) = segments.last() else {
+                    return Ty::Unknown;
+                };
+                if segments.len() == 1 && self.generics.contains(last) {
+                    return Ty::Unknown;
+                }
+
+                let name = match last.as_str() {
+                    "Self" => match &self.class {
+                        Some(class) => class.clone(),
+                        None => return Ty::Unknown,
+                    },
+                    name => convert_type(name).rsplit("::").next().unwrap().to_string(),
+                };
+                if name == "()" {
+                    return Ty::Tuple(Vec::new());
+                }
+                if !self.is_known(&name) {
+                    return Ty::Unknown;
+                }
+
+                Ty::Named(name, args.iter().map(|arg| self.resolve(arg)).collect())
+            },
+            TypeKind::Reference { inner, .. } => Ty::Ref(Box::new(self.resolve(inner))),
+            TypeKind::Tuple(types) => Ty::Tuple(types.iter().map(|ty| self.resolve(ty)).collect()),
+            TypeKind::Array { inner, .. } => Ty::Array(Box::new(self.resolve(inner))),
+            TypeKind::Optional(inner) => Ty::Named("Option".to_string(), vec![self.resolve(inner)]),
+            _ => Ty::Unknown,
+        }
+    }
+
+    fn is_known(&self, name: &str) -> bool {
+        INTEGER_TYPES.contains(&name)
+            || BUILTIN_TYPES.contains(&name)
+            || self.types.contains_key(name)
+    }
+
+    /// Whether a value of type `found` can be used where `expected` is
+    fn fits(&self, expected: &Ty, found: &Ty) -> bool {
+        match (expected, found) {
+            (Ty::Unknown, _) | (_, Ty::Unknown) => true,
+            (Ty::Integer, found) => found.is_integer(),
+            (expected, Ty::Integer) => expected.is_integer(),
+            (Ty::Float, found) => found.is_float(),
+            (expected, Ty::Float) => expected.is_float(),
+            (Ty::Named(name, args), Ty::Named(found_name, found_args)) => {
+                name == found_name
+                    && (args.len() != found_args.len() || args.iter().zip(found_args).all(|(arg, found)| self.fits(arg, found)))
+            },
+            // Deref coercion, `&String` is a `&str` and `&Child` a `&Parent`
+            (Ty::Ref(inner), Ty::Ref(found)) => self.derefs(found).iter().any(|found| self.fits(inner, found)),
+            (Ty::Tuple(types), Ty::Tuple(found)) => {
+                types.len() == found.len() && types.iter().zip(found).all(|(ty, found)| self.fits(ty, found))
+            },
+            (Ty::Array(inner), Ty::Array(found)) => self.fits(inner, found),
+            _ => false,
+        }
+    }
+
+    // `ty` and the types it derefs to
+    fn derefs(&self, ty: &Ty) -> Vec<Ty> {
+        let mut derefs = vec![ty.clone()];
+        while derefs.len() < 16 {
+            let next = match derefs.last().unwrap() {
+                Ty::Named(name, _) if name == "String" => Ty::named("str"),
+                Ty::Named(name, args) if name == "Vec" && args.len() == 1 => Ty::Array(Box::new(args[0].clone())),
+                Ty::Named(name, _) => match self.types.get(name).and_then(|info| info.parent.as_ref()) {
+                    Some(parent) if self.types.contains_key(parent) => Ty::named(parent),
+                    _ => break,
+                },
+                _ => break,
+            };
+            derefs.push(next);
+        }
+        derefs
+    }
+
+    /// Looks a member up in the class and its parents. `None` when a parent
+    /// isn't declared in the file, the member may still be there.
+    fn lookup<'a, T>(&'a self, name: &str, find: impl Fn(&'a TypeInfo) -> Option<&'a T>) -> Option<Option<&'a T>> {
+        let mut current = self.types.get(name)?;
+        // Classes extending each other are rustc's to report
+        for _ in 0..16 {
+            if let Some(found) = find(current) {
+                return Some(Some(found));
+            }
+            match &current.parent {
+                Some(parent) => current = self.types.get(parent)?,
+                None => return Some(None),
+            }
+        }
+        None
+    }
+
+    fn field(&self, ty: &Ty, name: &str) -> Option<Option<&FieldInfo>> {
+        match ty.referent() {
+            Ty::Named(class, _) => self.lookup(class, |info| info.fields.get(name)),
+            _ => None,
+        }
+    }
+
+    fn bind(&mut self, name: &str, ty: Ty) {
+        self.scopes.last_mut().unwrap().insert(name.to_string(), ty);
+    }
+
+    fn bind_pattern(&mut self, pattern: &Pattern, ty: Ty) {
+        match &pattern.kind {
+            PatternKind::Ident { name, .. } => self.bind(name, ty),
+            // The names it binds shadow the variables, their types are unknown
+            PatternKind::Verbatim(text) => {
+                for name in pattern_names(text) {
+                    self.bind(name, Ty::Unknown);
+                }
+            },
+        }
+    }
+
+    fn variable(&self, name: &str) -> Option<&Ty> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        if self.scopes.is_empty() {
+            self.scopes.push(HashMap::new());
+        }
+
+        match &stmt.kind {
+            StmtKind::Let { pattern, ty, value } => {
+                let declared = ty.as_ref().map(|ty| self.resolve(ty));
+                let found = match (value, &declared, ty) {
+                    (Some(value), Some(expected), Some(ty)) => {
+                        self.check_declared(value, expected, &(ty.location.clone(), "expected because of this type".to_string()));
+                        Ty::Unknown
+                    },
+                    (Some(value), _, _) => self.infer(value),
+                    (None, _, _) => Ty::Unknown,
+                };
+                self.bind_pattern(pattern, declared.unwrap_or(found));
+            },
+            StmtKind::Expr { expr, .. } => {
+                self.infer(expr);
+            },
+            StmtKind::Item(item) => {
+                // A nested function shadows the ones of the file, its calls aren't checked
+                if let ItemKind::Function(function) = &item.kind {
+                    self.bind(&function.name, Ty::Unknown);
+                }
+                self.check_item(item);
+            },
+        }
+    }
+
+    fn check_item(&mut self, item: &Item) {
+        // Items don't see the variables, generics or `Self` around them
+        let scopes = std::mem::take(&mut self.scopes);
+        let generics = std::mem::take(&mut self.generics);
+        let class = self.class.take();
+        let returns = self.returns.take();
+
+        self.check_item_kind(&item.kind);
+
+        self.scopes = scopes;
+        self.generics = generics;
+        self.class = class;
+        self.returns = returns;
+    }
+
+    // The emitter sorts the methods of a class into trait impls by the method
+    // names of the traits, which only Copper traits have
+    fn check_implements(&mut self, class: &Class) {
+        for ty in &class.implements {
+            let Some(name) = type_path_name(ty).filter(|name| !self.traits.iter().any(|known| known == name)) else {
+                continue;
+            };
+            let diagnostic = Diagnostic::error(&format!("Cannot find the methods of trait `{}`", name), ty.location.clone())
+                .with_code("E0303")
+                .with_label("not declared in this file or in an imported Copper file")
+                .with_help(&format!(
+                    "import the Copper file declaring `{}`, or remove it from `implements` and write `impl {} for {}` yourself",
+                    name, name, class.name,
+                ));
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn check_item_kind(&mut self, kind: &ItemKind) {
+        match kind {
+            ItemKind::Function(function) => self.check_function(function, Vec::new()),
+            ItemKind::Class(class) => {
+                self.check_implements(class);
+                self.class = Some(class.name.clone());
+                let generics = generic_names(&class.generics);
+                for constructor in &class.constructors {
+                    self.generics = generics.clone();
+                    self.enter_class(class);
+                    for param in &constructor.params {
+                        self.bind_param(param);
+                    }
+                    self.check_block(&constructor.body);
+                }
+                for method in &class.methods {
+                    self.enter_class(class);
+                    self.check_function(method, generics.clone());
+                }
+            },
+            ItemKind::Impl(implementation) => {
+                self.class = type_path_name(&implementation.target).map(str::to_string);
+                for method in &implementation.methods {
+                    self.scopes = vec![HashMap::new()];
+                    let self_ty = self.class.as_deref().map_or(Ty::Unknown, |name| self.resolve_name(name));
+                    self.bind("self", self_ty);
+                    self.check_function(method, generic_names(&implementation.generics));
+                }
+            },
+            ItemKind::Trait(definition) => {
+                for method in &definition.methods {
+                    self.scopes = vec![HashMap::from([("self".to_string(), Ty::Unknown)])];
+                    self.check_function(method, generic_names(&definition.generics));
+                }
+            },
+            ItemKind::Const(constant) => {
+                self.scopes = vec![HashMap::new()];
+                self.infer(&constant.value);
+            },
+            _ => {},
+        }
+    }
+
+    // The scope of a constructor or method, with `self` and `super`
+    fn enter_class(&mut self, class: &Class) {
+        self.scopes = vec![HashMap::new()];
+        self.bind("self", Ty::named(&class.name));
+        if let Some(parent) = &class.extends {
+            let parent = self.resolve(parent);
+            self.bind("super", parent);
+        }
+    }
+
+    // A declared type name, unknown when it's generic or not declared
+    fn resolve_name(&self, name: &str) -> Ty {
+        match self.is_known(name) && !self.generics.iter().any(|generic| generic == name) {
+            true => Ty::named(name),
+            false => Ty::Unknown,
+        }
+    }
+
+    // `generics` are those of the class, `impl` or trait of a method
+    fn check_function(&mut self, function: &Function, mut generics: Vec<String>) {
+        let Some(body) = &function.body else {
+            return;
+        };
+
+        generics.extend(generic_names(&function.generics));
+        self.generics = generics;
+        if self.scopes.is_empty() {
+            self.scopes.push(HashMap::new());
+        }
+        for param in &function.params {
+            self.bind_param(param);
+        }
+
+        self.returns = function.return_type.as_ref().map(|ty| (self.resolve(ty), ty.location.clone()));
+        self.check_block(body);
+        self.returns = None;
+        self.scopes.clear();
+    }
+
+    fn bind_param(&mut self, param: &Param) {
+        if let Param::Named { name, ty, .. } = param {
+            let ty = self.resolve(ty);
+            self.bind(name, ty);
+        }
+    }
+
+    // The type of the value of the block
+    fn check_block(&mut self, block: &Block) -> Ty {
+        self.scopes.push(HashMap::new());
+        let mut ty = Ty::Unknown;
+        for (i, stmt) in block.stmts.iter().enumerate() {
+            match &stmt.kind {
+                StmtKind::Expr { expr, semicolon: false } if i + 1 == block.stmts.len() => ty = self.infer(expr),
+                _ => self.check_stmt(stmt),
+            }
+        }
+        self.scopes.pop();
+        ty
+    }
+
+    // A value declared with a type, or passed to a parameter. String literals
+    // declared as `string` and array literals declared as `list` or `set` are
+    // converted by the emitter, in collection literals too. `reason` points at
+    // the declared type.
+    fn check_declared(&mut self, value: &Expr, expected: &Ty, reason: &(LocationData, String)) {
+        let items = match expected {
+            Ty::Named(_, args) => args.clone(),
+            Ty::Array(inner) => vec![inner.as_ref().clone()],
+            Ty::Tuple(types) => types.clone(),
+            _ => Vec::new(),
+        };
+        let item = |index: usize| items.get(index).cloned().unwrap_or(Ty::Unknown);
+
+        match &value.kind {
+            ExprKind::Literal(Literal::String(_)) if *expected == Ty::named("String") => {},
+            ExprKind::List(values) | ExprKind::Set(values) => {
+                values.iter().for_each(|value| self.check_declared(value, &item(0), reason));
+            },
+            ExprKind::Array(values) if matches!(expected, Ty::Array(_)) || expected.is_named("Vec") || expected.is_named("HashSet") => {
+                values.iter().for_each(|value| self.check_declared(value, &item(0), reason));
+            },
+            ExprKind::Map(entries) => {
+                for (key, value) in entries {
+                    self.check_declared(key, &item(0), reason);
+                    self.check_declared(value, &item(1), reason);
+                }
+            },
+            ExprKind::Tuple(values) if values.len() > 1 && values.len() == items.len() => {
+                for (i, value) in values.iter().enumerate() {
+                    self.check_declared(value, &item(i), reason);
+                }
+            },
+            _ => self.expect(value, expected, Some(reason.clone())),
+        }
+    }
+
+    /// Checks that `value` fits where `expected` is. `reason` points at what
+    /// makes the type expected.
+    fn expect(&mut self, value: &Expr, expected: &Ty, reason: Option<(LocationData, String)>) {
+        let found = self.infer(value);
+        if self.fits(expected, &found) {
+            return;
+        }
+
+        let mut diagnostic = Diagnostic::error("Mismatched types", value.location.clone())
+            .with_code("E0300")
+            .with_label(&format!("expected {}, found {}", expected.describe(), found.describe()));
+        if let Some((location, message)) = reason {
+            diagnostic = diagnostic.with_secondary(location, &message);
+        }
+        if let Some(help) = self.conversion(expected, &found) {
+            diagnostic = diagnostic.with_help(&help);
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    // How to turn a `found` into an `expected`, when it's a common mistake
+    fn conversion(&self, expected: &Ty, found: &Ty) -> Option<String> {
+        match (expected, found) {
+            (Ty::Named(name, _), Ty::Ref(inner)) if name == "String" && **inner == Ty::named("str") => {
+                Some("convert it to a `string` with `.to_string()`".to_string())
+            },
+            (Ty::Named(name, args), found) if name == "Option" && args.len() == 1 && self.fits(&args[0], found) => {
+                Some("wrap it in `Some(...)`".to_string())
+            },
+            (Ty::Ref(inner), found) if self.fits(inner, found) => Some("borrow it with `&`".to_string()),
+            (expected, Ty::Integer) if expected.is_float() => Some("add `.0` to make it a float".to_string()),
+            (expected, found) if expected.is_number() && *expected != Ty::Integer && found.is_number() => {
+                Some(format!("convert it with `as {}`", expected))
+            },
+            _ => None,
+        }
+    }
+
+    fn check_args(&mut self, signature: &Signature, args: &[Expr], location: &LocationData) {
+        if args.len() != signature.params.len() {
+            let plural = |count: usize| if count == 1 { "" } else { "s" };
+            let expected = format!("{} argument{}", signature.params.len(), plural(signature.params.len()));
+            let message = format!(
+                "{} `{}` takes {} but {} {} given",
+                signature.kind,
+                signature.name,
+                expected,
+                args.len(),
+                if args.len() == 1 { "was" } else { "were" },
+            );
+            self.diagnostics.push(
+                Diagnostic::error(&message, location.clone())
+                    .with_code("E0301")
+                    .with_label(&format!("expected {}", expected))
+                    .with_secondary(signature.location.clone(), &format!("`{}` is declared here", signature.name)),
+            );
+            for arg in args {
+                self.infer(arg);
+            }
+            return;
+        }
+
+        for (arg, (name, ty, written, location)) in args.iter().zip(&signature.params) {
+            self.check_declared(arg, ty, &(location.clone(), format!("parameter `{}` is declared here", name)));
+            self.argument_types.insert(arg.location.range, written.clone());
+        }
+    }
+
+    // The function a path calls, unless a local variable has its name
+    fn callee(&self, path: &Path) -> Option<Signature> {
+        match path.segments.as_slice() {
+            [function] if self.variable(&function.name).is_none() => self.functions.get(&function.name).cloned(),
+            [class, function] => {
+                let class = match class.name.as_str() {
+                    "Self" => self.class.as_deref()?,
+                    name => name,
+                };
+                self.types.get(class)?.functions.get(&function.name).cloned()
+            },
+            _ => None,
+        }
+    }
+
+    // `self.name = value` and other assignments to fields or variables
+    fn check_assign(&mut self, op: Option<BinaryOp>, target: &Expr, value: &Expr) {
+        if let ExprKind::Field { base, name } = &target.kind {
+            let base = self.infer(base);
+            match self.field(&base, name) {
+                Some(Some(field)) if op.is_none() || field.ty.is_number() => {
+                    let field = field.clone();
+                    let reason = format!("field `{}` is declared here", name);
+                    self.expect(value, &field.ty, Some((field.location, reason)));
+                },
+                Some(None) => {
+                    self.unknown_field(&base, name, &target.location);
+                    self.infer(value);
+                },
+                _ => {
+                    self.infer(value);
+                },
+            }
+            return;
+        }
+
+        let target = self.infer(target);
+        match op.is_none() || target.is_number() {
+            true => self.expect(value, &target, None),
+            false => {
+                self.infer(value);
+            },
+        }
+    }
+
+    fn unknown_field(&mut self, ty: &Ty, name: &str, location: &LocationData) {
+        let Ty::Named(class, _) = ty.referent() else {
+            return;
+        };
+
+        let mut fields = self.types[class].fields.keys().map(|field| format!("`{}`", field)).collect::<Vec<_>>();
+        fields.sort();
+        let mut diagnostic = Diagnostic::error(&format!("`{}` has no field `{}`", class, name), location.clone())
+            .with_code("E0302")
+            .with_label("unknown field");
+        if !fields.is_empty() {
+            diagnostic = diagnostic.with_note(&format!("its fields are {}", fields.join(", ")));
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Infers the type of `expr`, checking what's inside of it
+    fn infer(&mut self, expr: &Expr) -> Ty {
+        match &expr.kind {
+            ExprKind::Literal(literal) => literal_type(literal),
+            ExprKind::Interpolated(parts) => {
+                for part in parts {
+                    if let StringPart::Expr(expr) = part {
+                        self.infer(expr);
+                    }
+                }
+                Ty::named("String")
+            },
+            ExprKind::Path(path) => match path.segments.as_slice() {
+                [segment] => {
+                    let name = segment.name.as_str();
+                    self.variable(name).or_else(|| self.constants.get(name)).cloned().unwrap_or(Ty::Unknown)
+                },
+                [enumeration, variant] if self.is_variant(&enumeration.name, &variant.name) => Ty::named(&enumeration.name),
+                _ => Ty::Unknown,
+            },
+            ExprKind::Unary { op, expr } => {
+                let ty = self.infer(expr);
+                match op {
+                    UnaryOp::Neg | UnaryOp::Not => ty,
+                    UnaryOp::Deref => match ty {
+                        Ty::Ref(inner) => *inner,
+                        _ => Ty::Unknown,
+                    },
+                    UnaryOp::Ref | UnaryOp::RefMut => Ty::Ref(Box::new(ty)),
+                }
+            },
+            ExprKind::Binary { op, lhs, rhs } => self.infer_binary(*op, lhs, rhs),
+            ExprKind::Assign { op, target, value } => {
+                self.check_assign(*op, target, value);
+                Ty::Tuple(Vec::new())
+            },
+            ExprKind::Call { callee, args } => {
+                let signature = match &callee.kind {
+                    ExprKind::Path(path) => self.callee(path),
+                    _ => None,
+                };
+                if let Some(signature) = signature {
+                    self.check_args(&signature, args, &expr.location);
+                    return signature.returns;
+                }
+
+                self.infer(callee);
+                for arg in args {
+                    self.infer(arg);
+                }
+                match &callee.kind {
+                    // Tuple variants, `Shape::Circle(1.0)`
+                    ExprKind::Path(path) => match path.segments.as_slice() {
+                        [enumeration, variant] if self.is_variant(&enumeration.name, &variant.name) => Ty::named(&enumeration.name),
+                        _ => Ty::Unknown,
+                    },
+                    _ => Ty::Unknown,
+                }
+            },
+            ExprKind::MethodCall { receiver, method, args, .. } => {
+                let receiver = self.infer(receiver);
+                let signature = match receiver.referent() {
+                    Ty::Named(class, _) => self.lookup(class, |info| info.methods.get(method)).flatten().cloned(),
+                    _ => None,
+                };
+                if let Some(signature) = signature {
+                    self.check_args(&signature, args, &expr.location);
+                    return signature.returns;
+                }
+
+                for arg in args {
+                    self.infer(arg);
+                }
+                match method.as_str() {
+                    "to_string" => Ty::named("String"),
+                    "len" => Ty::named("usize"),
+                    "clone" => receiver.referent().clone(),
+                    _ => Ty::Unknown,
+                }
+            },
+            ExprKind::Field { base, name } => {
+                let base = self.infer(base);
+                match (base.referent(), name.parse::<usize>()) {
+                    (Ty::Tuple(types), Ok(index)) => types.get(index).cloned().unwrap_or(Ty::Unknown),
+                    _ => match self.field(&base, name) {
+                        Some(Some(field)) => field.ty.clone(),
+                        Some(None) => {
+                            self.unknown_field(&base, name, &expr.location);
+                            Ty::Unknown
+                        },
+                        None => Ty::Unknown,
+                    },
+                }
+            },
+            ExprKind::Index { base, index } => {
+                let base = self.infer(base);
+                self.infer(index);
+                match (base.referent(), &index.kind) {
+                    (_, ExprKind::Range { .. }) => Ty::Unknown,
+                    (Ty::Array(inner), _) => inner.as_ref().clone(),
+                    (Ty::Named(name, args), _) if name == "Vec" && args.len() == 1 => args[0].clone(),
+                    (Ty::Named(name, args), _) if name == "HashMap" && args.len() == 2 => args[1].clone(),
+                    _ => Ty::Unknown,
+                }
+            },
+            ExprKind::Macro { path, args, .. } => {
+                if let MacroArgs::Exprs(args) = args {
+                    for arg in args {
+                        self.infer(arg);
+                    }
+                }
+                match path.as_ident() {
+                    Some("format") => Ty::named("String"),
+                    _ => Ty::Unknown,
+                }
+            },
+            ExprKind::Cast { expr, ty } => {
+                self.infer(expr);
+                self.resolve(ty)
+            },
+            ExprKind::Try(expr) => match self.infer(expr) {
+                Ty::Named(name, args) if (name == "Option" || name == "Result") && !args.is_empty() => args[0].clone(),
+                _ => Ty::Unknown,
+            },
+            ExprKind::Let { pattern, value } => {
+                self.infer(value);
+                self.bind_pattern(pattern, Ty::Unknown);
+                Ty::named("bool")
+            },
+            ExprKind::Range { start, end, .. } => {
+                for bound in [start, end].into_iter().flatten() {
+                    self.infer(bound);
+                }
+                Ty::Unknown
+            },
+            ExprKind::Closure { params, body, .. } => {
+                self.scopes.push(HashMap::new());
+                for param in params {
+                    let ty = param.ty.as_ref().map_or(Ty::Unknown, |ty| self.resolve(ty));
+                    self.bind_pattern(&param.pattern, ty);
+                }
+                let returns = self.returns.take();
+                self.infer(body);
+                self.returns = returns;
+                self.scopes.pop();
+                Ty::Unknown
+            },
+            ExprKind::Block(block) | ExprKind::Unsafe(block) => self.check_block(block),
+            ExprKind::If { cond, then, otherwise } => {
+                self.infer(cond);
+                let then = self.check_block(then);
+                match otherwise {
+                    Some(otherwise) if self.infer(otherwise) == then => then,
+                    _ => Ty::Unknown,
+                }
+            },
+            ExprKind::While { cond, body, .. } => {
+                self.infer(cond);
+                self.check_block(body);
+                Ty::Unknown
+            },
+            ExprKind::Loop { body, .. } => {
+                self.check_block(body);
+                Ty::Unknown
+            },
+            ExprKind::For { pattern, iter, body, .. } => {
+                let item = match (&iter.kind, self.infer(iter)) {
+                    (ExprKind::Range { start, end, .. }, _) => {
+                        let bounds = [start, end].into_iter().flatten().map(|bound| self.infer(bound)).collect::<Vec<_>>();
+                        bounds.iter().find(|ty| !ty.is_literal()).or(bounds.first()).cloned().unwrap_or(Ty::Unknown)
+                    },
+                    (_, Ty::Named(name, args)) if (name == "Vec" || name == "HashSet") && args.len() == 1 => args[0].clone(),
+                    (_, Ty::Array(inner)) => *inner,
+                    (_, Ty::Ref(inner)) => match *inner {
+                        Ty::Named(name, args) if name == "Vec" && args.len() == 1 => Ty::Ref(Box::new(args[0].clone())),
+                        Ty::Array(inner) => Ty::Ref(inner),
+                        _ => Ty::Unknown,
+                    },
+                    _ => Ty::Unknown,
+                };
+
+                self.scopes.push(HashMap::new());
+                self.bind_pattern(pattern, item);
+                self.check_block(body);
+                self.scopes.pop();
+                Ty::Unknown
+            },
+            ExprKind::Match { expr, arms } => {
+                self.infer(expr);
+                for arm in arms {
+                    self.scopes.push(HashMap::new());
+                    self.bind_pattern(&arm.pattern, Ty::Unknown);
+                    if let Some(guard) = &arm.guard {
+                        self.infer(guard);
+                    }
+                    self.infer(&arm.body);
+                    self.scopes.pop();
+                }
+                Ty::Unknown
+            },
+            ExprKind::Return(value) => {
+                if let Some(value) = value {
+                    match self.returns.clone() {
+                        Some((ty, location)) => self.expect(value, &ty, Some((location, "expected because of this return type".to_string()))),
+                        None => {
+                            self.infer(value);
+                        },
+                    }
+                }
+                Ty::Unknown
+            },
+            ExprKind::Break { value, .. } => {
+                if let Some(value) = value {
+                    self.infer(value);
+                }
+                Ty::Unknown
+            },
+            ExprKind::Continue(_) | ExprKind::Regex { .. } => Ty::Unknown,
+            ExprKind::StructLiteral { path, fields, base } => self.infer_struct_literal(path, fields, base.as_deref(), &expr.location),
+            ExprKind::Tuple(values) => Ty::Tuple(values.iter().map(|value| self.infer(value)).collect()),
+            ExprKind::Array(values) => {
+                let types = values.iter().map(|value| self.infer(value)).collect::<Vec<_>>();
+                let item = types.iter().find(|ty| !ty.is_literal()).or(types.first()).cloned();
+                Ty::Array(Box::new(item.unwrap_or(Ty::Unknown)))
+            },
+            ExprKind::List(values) | ExprKind::Set(values) => {
+                for value in values {
+                    self.infer(value);
+                }
+                Ty::Unknown
+            },
+            ExprKind::Map(entries) => {
+                for (key, value) in entries {
+                    self.infer(key);
+                    self.infer(value);
+                }
+                Ty::Unknown
+            },
+            ExprKind::ArrayRepeat { value, len } => {
+                self.infer(len);
+                Ty::Array(Box::new(self.infer(value)))
+            },
+            ExprKind::Paren(expr) => self.infer(expr),
+            ExprKind::Json(value) => {
+                self.infer_json(value);
+                Ty::Unknown
+            },
+        }
+    }
+
+    fn infer_binary(&mut self, op: BinaryOp, lhs: &Expr, rhs: &Expr) -> Ty {
+        let (left, right) = (self.infer(lhs), self.infer(rhs));
+        match op {
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => Ty::named("bool"),
+            BinaryOp::And | BinaryOp::Or => Ty::named("bool"),
+            // Shifting works across integer types
+            BinaryOp::Shl | BinaryOp::Shr => left,
+            _ if left.is_number() && right.is_number() => {
+                if !self.fits(&left, &right) {
+                    let diagnostic = Diagnostic::error("Mismatched types", rhs.location.clone())
+                        .with_code("E0300")
+                        .with_label(&format!("expected {}, found {}", left.describe(), right.describe()))
+                        .with_secondary(lhs.location.clone(), "expected because of this value")
+                        .with_note(&format!("both sides of `{}` need the same number type", op.as_str()));
+                    let diagnostic = match self.conversion(&left, &right) {
+                        Some(help) => diagnostic.with_help(&help),
+                        None => diagnostic,
+                    };
+                    self.diagnostics.push(diagnostic);
+                }
+                if left.is_literal() { right } else { left }
+            },
+            BinaryOp::Add if left == Ty::named("String") => left,
+            _ => Ty::Unknown,
+        }
+    }
+
+    fn infer_struct_literal(&mut self, path: &Path, fields: &[FieldInit], base: Option<&Expr>, location: &LocationData) -> Ty {
+        if let Some(base) = base {
+            self.infer(base);
+        }
+
+        let name = match path.as_ident() {
+            Some("Self") => self.class.clone(),
+            Some(name) if self.types.contains_key(name) => Some(name.to_string()),
+            _ => None,
+        };
+        let Some(name) = name else {
+            for field in fields {
+                if let Some(value) = &field.value {
+                    self.infer(value);
+                }
+            }
+            return Ty::Unknown;
+        };
+
+        let ty = Ty::named(&name);
+        for init in fields {
+            let shorthand;
+            let value = match &init.value {
+                Some(value) => value,
+                None => {
+                    shorthand = Expr::new(ExprKind::Path(Path::single(&init.name)), location.clone());
+                    &shorthand
+                },
+            };
+
+            match self.field(&ty, &init.name) {
+                Some(Some(field)) => {
+                    let field = field.clone();
+                    let reason = format!("field `{}` is declared here", init.name);
+                    self.expect(value, &field.ty, Some((field.location, reason)));
+                },
+                Some(None) => {
+                    self.unknown_field(&ty, &init.name, &value.location);
+                    self.infer(value);
+                },
+                None => {
+                    self.infer(value);
+                },
+            }
+        }
+        ty
+    }
+
+    fn infer_json(&mut self, value: &JsonValue) {
+        match value {
+            JsonValue::Object(entries) => entries.iter().for_each(|(_, value)| self.infer_json(value)),
+            JsonValue::Array(values) => values.iter().for_each(|value| self.infer_json(value)),
+            JsonValue::Value(expr) => {
+                self.infer(expr);
+            },
+        }
+    }
+}
+
+fn literal_type(literal: &Literal) -> Ty {
+    match literal {
+        Literal::Number(value) => {
+            let (digits, suffix) = split_number(value.trim_start_matches('-'));
+            match number_suffix(suffix) {
+                Some(rust) => Ty::named(&rust),
+                None if is_float_literal(digits, None) => Ty::Float,
+                None => Ty::Integer,
+            }
+        },
+        // Byte strings are byte arrays
+        Literal::String(value) if value.starts_with('b') => Ty::Unknown,
+        Literal::String(_) => Ty::Ref(Box::new(Ty::named("str"))),
+        Literal::Char(value) if value.starts_with('b') => Ty::named("u8"),
+        Literal::Char(_) => Ty::named("char"),
+        Literal::Bool(_) => Ty::named("bool"),
+    }
+}
+
+// `Parent` of `Parent<T>`, `None` for types that aren't paths
+fn type_path_name(ty: &Type) -> Option<&str> {
+    match &ty.kind {
+        TypeKind::Path { segments, .. } => segments.last().map(String::as_str),
+        _ => None,
+    }
+}
+
+fn generic_names(generics: &Generics) -> Vec<String> {
+    generics.params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type { name, .. } | GenericParam::Const { name, .. } => Some(name.clone()),
+            GenericParam::Lifetime { .. } => None,
+        })
+        .collect()
+}
+
+// The names a pattern passed through as written may bind, `Some((x, y))` => `x`, `y`
+fn pattern_names(pattern: &str) -> Vec<&str> {
+    pattern
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| word.starts_with(|c: char| c.is_lowercase() || c == '_') && *word != "_")
+        .filter(|word| !matches!(*word, "mut" | "ref" | "true" | "false"))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::tokenizer::Tokenizer};
+
+    fn check_source(source: &str) -> Vec<Diagnostic> {
+        let tokens = Tokenizer::new(source.to_string()).tokenize();
+        check(&Parser::new(tokens).parse(), &HashMap::new()).diagnostics
+    }
+
+    #[test]
+    fn test_reports_type_errors() {
+        let diagnostics = check_source(concat!(
+            "func int add(a: int, b: int) {\n",
+            "    return a + b\n",
+            "}\n",
+            "class Point {\n",
+            "    x: float\n",
+            "    Point(x: float) {\n",
+            "        self.x = \"zero\"\n",
+            "    }\n",
+            "    void move_by(mut self, dx: float) {\n",
+            "        self.z = dx\n",
+            "    }\n",
+            "}\n",
+            "total = add(1, 2, 3)\n",
+            "label: string = total\n",
+            "mut point = Point::new(1)\n",
+            "point.move_by(total as float)\n",
+            "names: list<string> = [\"ann\", \"bob\"]\n",
+        ));
+
+        let found = diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref().unwrap(), d.primary.location.first_line, d.primary.message.clone().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(found, vec![
+            ("E0300", 7, "expected `float`, found `&str`".to_string()),
+            ("E0302", 10, "unknown field".to_string()),
+            ("E0301", 13, "expected 2 arguments".to_string()),
+            ("E0300", 14, "expected `string`, found `int`".to_string()),
+            ("E0300", 15, "expected `float`, found integer".to_string()),
+        ]);
+        assert_eq!(diagnostics[2].message, "Function `add` takes 2 arguments but 3 were given");
+        assert_eq!(diagnostics[4].help.as_deref(), Some("add `.0` to make it a float"));
+    }
+
+    #[test]
+    fn test_enum_functions() {
+        let diagnostics = check_source(concat!(
+            "enum Shape {\n",
+            "    Empty\n",
+            "    Circle(float)\n",
+            "}\n",
+            "impl Shape {\n",
+            "    func Shape? parse(text: &str) {\n",
+            "        return None\n",
+            "    }\n",
+            "}\n",
+            "parsed: Shape? = Shape::parse(\"empty\")\n",
+            "circle: Shape = Shape::Circle(1.0)\n",
+            "empty: Shape? = Shape::Empty\n",
+            "other = Shape::parse(\"a\", \"b\")\n",
+        ));
+
+        let found = diagnostics.iter().map(|d| (d.code.as_deref().unwrap(), d.primary.location.first_line)).collect::<Vec<_>>();
+        assert_eq!(found, vec![("E0300", 12), ("E0301", 13)]);
+    }
+
+    #[test]
+    fn test_unknown_traits() {
+        let source = concat!(
+            "trait Named {\n",
+            "    func string name(self)\n",
+            "}\n",
+            "class Dog implements Named, Animal {\n",
+            "    func string name(self) {\n",
+            "        return \"dog\".to_string()\n",
+            "    }\n",
+            "}\n",
+        );
+        let program = Parser::new(Tokenizer::new(source.to_string()).tokenize()).parse();
+        let imported = HashMap::from([("Animal".to_string(), vec!["speak".to_string()])]);
+        let diagnostics = check(&program, &imported).diagnostics;
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let diagnostics = check(&program, &HashMap::new()).diagnostics;
+        let found = diagnostics.iter().map(|d| (d.code.as_deref().unwrap(), d.message.as_str())).collect::<Vec<_>>();
+        assert_eq!(found, vec![("E0303", "Cannot find the methods of trait `Animal`")]);
+    }
+}
@@ -0,0 +1,274 @@
+use colored::{ColoredString, Colorize};
+
+use crate::tokenizer::tokens::LocationData;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn paint(&self, text: &str) -> ColoredString {
+        match self {
+            Severity::Error => text.red().bold(),
+            Severity::Warning => text.yellow().bold(),
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A span of source code, optionally with a message shown under it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub location: LocationData,
+    pub message: Option<String>,
+}
+
+/// A problem found while compiling a Copper file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    pub primary: Label,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: &str, location: LocationData) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.to_string(),
+            primary: Label {
+                location,
+                message: None,
+            },
+            secondary: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn error(message: &str, location: LocationData) -> Self {
+        Self::new(Severity::Error, message, location)
+    }
+
+    pub fn warning(message: &str, location: LocationData) -> Self {
+        Self::new(Severity::Warning, message, location)
+    }
+
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    /// Sets the message shown under the primary span
+    pub fn with_label(mut self, message: &str) -> Self {
+        self.primary.message = Some(message.to_string());
+        self
+    }
+
+    pub fn with_secondary(mut self, location: LocationData, message: &str) -> Self {
+        self.secondary.push(Label {
+            location,
+            message: Some(message.to_string()),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn with_help(mut self, help: &str) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Renders the diagnostic with the lines of `source` it points at.
+    pub fn render(&self, source: &str, file: &str) -> String {
+        let lines = source.lines().collect::<Vec<_>>();
+        let mut labels = vec![(&self.primary, true)];
+        labels.extend(self.secondary.iter().map(|label| (label, false)));
+        labels.retain(|(label, _)| label.location.first_line > 0);
+        labels.sort_by_key(|(label, _)| (label.location.first_line, label.location.first_column));
+
+        let gutter_width = labels
+            .iter()
+            .map(|(label, _)| label.location.first_line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let gutter = " ".repeat(gutter_width);
+        let bar = "|".blue().bold();
+
+        let mut output = match &self.code {
+            Some(code) => format!("{}: {}\n", self.severity.paint(&format!("{}[{}]", self.severity, code)), self.message.bold()),
+            None => format!("{}: {}\n", self.severity.paint(&self.severity.to_string()), self.message.bold()),
+        };
+
+        let location = &self.primary.location;
+        if location.first_line > 0 {
+            output.push_str(&format!("{}{} {}:{}:{}\n", gutter, "-->".blue().bold(), file, location.first_line, location.first_column));
+        } else {
+            output.push_str(&format!("{}{} {}\n", gutter, "-->".blue().bold(), file));
+        }
+
+        if !labels.is_empty() {
+            output.push_str(&format!("{} {}\n", gutter, bar));
+        }
+
+        let mut previous_line = None;
+        for (label, is_primary) in &labels {
+            let line = label.location.first_line;
+            let text = lines.get(line as usize - 1).copied().unwrap_or_default();
+
+            if previous_line.is_some_and(|previous| line > previous + 1) {
+                output.push_str(&format!("{}\n", "...".blue().bold()));
+            }
+            if previous_line != Some(line) {
+                output.push_str(&format!("{} {} {}\n", format!("{:>width$}", line, width = gutter_width).blue().bold(), bar, text));
+            }
+            previous_line = Some(line);
+
+            let start = label.location.first_column.max(1);
+            let line_width = text.chars().count();
+            let end = if label.location.last_line == line {
+                label.location.last_column.max(start)
+            } else {
+                line_width.max(start)
+            };
+
+            let marker = if *is_primary { "^" } else { "-" }.repeat(end - start + 1);
+            let mut underline = format!("{}{}", " ".repeat(start - 1), marker);
+            if let Some(message) = &label.message {
+                underline.push(' ');
+                underline.push_str(message);
+            }
+
+            let underline = if *is_primary {
+                self.severity.paint(&underline)
+            } else {
+                underline.blue().bold()
+            };
+            output.push_str(&format!("{} {} {}\n", gutter, bar, underline));
+        }
+
+        if !self.notes.is_empty() || self.help.is_some() {
+            output.push_str(&format!("{} {}\n", gutter, bar));
+        }
+        for note in &self.notes {
+            output.push_str(&format!("{} {} {}: {}\n", gutter, "=".blue().bold(), "note".bold(), note));
+        }
+        if let Some(help) = &self.help {
+            output.push_str(&format!("{} {} {}: {}\n", gutter, "=".blue().bold(), "help".bold(), help));
+        }
+
+        output
+    }
+}
+
+/// Prints every diagnostic of a file followed by a summary, returns the number of errors
+pub fn report(diagnostics: &[Diagnostic], source: &str, file: &str) -> usize {
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic.render(source, file));
+    }
+
+    let errors = diagnostics.iter().filter(|diagnostic| diagnostic.is_error()).count();
+    let warnings = diagnostics.len() - errors;
+
+    if errors > 0 {
+        eprintln!(
+            "{}: could not compile `{}` due to {} previous error{}{}",
+            "error".red().bold(),
+            file,
+            errors,
+            if errors == 1 { "" } else { "s" },
+            if warnings > 0 { format!("; {} warning{} emitted", warnings, if warnings == 1 { "" } else { "s" }) } else { String::new() },
+        );
+    } else if warnings > 0 {
+        eprintln!(
+            "{}: `{}` generated {} warning{}",
+            "warning".yellow().bold(),
+            file,
+            warnings,
+            if warnings == 1 { "" } else { "s" },
+        );
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(line: isize, first_column: usize, last_column: usize) -> LocationData {
+        LocationData {
+            first_line: line,
+            first_column,
+            last_line: line,
+            last_column,
+            range: (0, 0),
+        }
+    }
+
+    #[test]
+    fn test_render_snippet() {
+        colored::control::set_override(false);
+
+        let source = "x = 1\nhello(x, y\n";
+        let diagnostic = Diagnostic::error("Expected `,` or `)`, found end of line", location(2, 11, 11))
+            .with_code("E0100")
+            .with_label("expected `)`")
+            .with_secondary(location(2, 6, 6), "unclosed delimiter")
+            .with_help("add the missing `)`");
+
+        assert_eq!(
+            diagnostic.render(source, "main.crs"),
+            concat!(
+                "error[E0100]: Expected `,` or `)`, found end of line\n",
+                " --> main.crs:2:11\n",
+                "  |\n",
+                "2 | hello(x, y\n",
+                "  |      - unclosed delimiter\n",
+                "  |           ^ expected `)`\n",
+                "  |\n",
+                "  = help: add the missing `)`\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_collects_every_error() {
+        use crate::{parser::Parser, tokenizer::tokenizer::Tokenizer};
+
+        let source = "func void greet(name) {\n}\n\nx = 1 2\ny = (1 + )\nz = 3\n";
+        let tokens = Tokenizer::new(source.to_string()).tokenize();
+        let mut parser = Parser::new(tokens);
+        let program = parser.parse();
+
+        let lines = parser.diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.primary.location.first_line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![1, 4, 5]);
+        assert_eq!(program.body.len(), 1);
+    }
+}
@@ -0,0 +1,1796 @@
+pub mod result;
+pub mod source_map;
+
+use std::collections::HashMap;
+
+use crate::{checker::ArgumentTypes, parser::{ast::*, utils::{convert_type, convert_type_with_marking, REGEX_FLAGS}}, tokenizer::tokens::LocationData};
+use result::Result;
+use source_map::{escape_markers, SourceMap, MARK_END, MARK_ID_END, MARK_START};
+
+const INDENT: &str = "    ";
+const ENUM_DERIVES: [&str; 3] = ["Debug", "Clone", "PartialEq"];
+// Types implementing all of `ENUM_DERIVES` when their arguments do
+const DERIVING_TYPES: [&str; 27] = [
+    "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize", "f32", "f64", "bool", "char",
+    "()", "str", "String", "Vec", "Option", "Box", "HashMap", "HashSet", "JsonValue", "XmlValue", "TomlValue",
+];
+// Getters return these by value, other types by reference
+const COPY_TYPES: [&str; 16] = [
+    "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize", "f32", "f64", "bool", "char",
+];
+
+/// Turns a parsed Copper [`Program`] into Rust source code.
+#[derive(Default)]
+pub struct Emitter {
+    result: Result,
+    indent: usize,
+    locations: Vec<LocationData>,
+    source_map: SourceMap,
+    /// Top-level items are visible to the other modules of the crate
+    shared_items: bool,
+    /// Method names of the traits declared in the program and in the Copper
+    /// files it imports, by trait name
+    traits: HashMap<String, Vec<String>>,
+    /// Traits the types declared in the program derive, by type name
+    derives: HashMap<String, Vec<String>>,
+    /// Field holding the parent of the class being emitted, `super` refers to it
+    parent_field: Option<String>,
+    /// Parameter types of call arguments, found by the checker
+    argument_types: ArgumentTypes,
+}
+
+impl Emitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Emitter for a file other than `main`, whose items are used by the
+    /// files importing it
+    pub fn module() -> Self {
+        Self {
+            shared_items: true,
+            ..Self::default()
+        }
+    }
+
+    /// Emits list, map and string literal arguments as the types of their
+    /// parameters, `f([1, 2])` passes a `Vec` to `f(items: list<int>)`
+    pub fn with_argument_types(mut self, argument_types: ArgumentTypes) -> Self {
+        self.argument_types = argument_types;
+        self
+    }
+
+    /// Traits of the Copper files the program imports, classes implementing
+    /// them get their methods in `impl Trait for Class` blocks
+    pub fn with_traits(mut self, traits: HashMap<String, Vec<String>>) -> Self {
+        self.traits = traits;
+        self
+    }
+
+    pub fn emit(&mut self, program: &Program) -> String {
+        let mut items = Vec::new();
+        let mut main_body = Vec::new();
+
+        // Items stay at module level, everything else runs inside `main`
+        for stmt in &program.body {
+            match &stmt.kind {
+                StmtKind::Item(item) => items.push(item),
+                _ => main_body.push(stmt.clone()),
+            }
+        }
+
+        // Classes put the methods of the traits they implement in their own impl blocks
+        self.traits.extend(program.traits());
+        self.collect_derives(&items);
+
+        for item in items {
+            let code = self.emit_item(item);
+            self.result.append(&code);
+            self.result.append("\n\n");
+        }
+
+        if !main_body.is_empty() {
+            let block = Block {
+                stmts: main_body,
+                location: Default::default(),
+            };
+            let body = self.emit_block(&block, false);
+            self.result.append(&format!("fn main() {}\n", body));
+        }
+
+        if self.result.uses_regex && !self.result.has_required_import("regex") {
+            self.result.add_required_import("regex");
+        }
+        self.result.add_data_type_aliases();
+        self.result.add_module_docs(&program.docs.iter().map(|doc| escape_markers(doc)).collect::<Vec<_>>());
+
+        let (code, source_map) = SourceMap::extract(&self.result.get(), &self.locations);
+        self.source_map = source_map;
+        code
+    }
+
+    pub fn get_required_dependencies(&self) -> Vec<String> {
+        self.result.get_required_dependencies()
+    }
+
+    /// Where the lines of the last emitted code come from
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    // Marks `code` as generated from `location` so it ends up in the source map
+    fn mapped(&mut self, location: &LocationData, code: String) -> String {
+        if location.first_line <= 0 {
+            return code;
+        }
+
+        self.locations.push(location.clone());
+        format!("{}{}{}{}{}", MARK_START, self.locations.len() - 1, MARK_ID_END, code, MARK_END)
+    }
+
+    fn indentation(&self) -> String {
+        INDENT.repeat(self.indent)
+    }
+
+    // Parentheses around conditions are optional in Copper, Rust warns about
+    // them. They stay when a `{` inside could be read as the start of the body.
+    fn emit_condition(&mut self, cond: &Expr) -> String {
+        match &cond.kind {
+            ExprKind::Paren(inner) => {
+                let code = self.emit_expr(inner);
+                if code.contains('{') { format!("({})", code) } else { code }
+            },
+            _ => self.emit_expr(cond),
+        }
+    }
+
+    // Doc comment lines before a member, the member goes on at the same indentation
+    fn emit_docs(&self, docs: &[String]) -> String {
+        docs.iter().map(|doc| format!("{}\n{}", escape_markers(doc), self.indentation())).collect()
+    }
+
+    fn emit_item(&mut self, item: &Item) -> String {
+        let mut code = String::new();
+        for attribute in &item.attributes {
+            code.push_str(&escape_markers(attribute));
+            code.push('\n');
+            code.push_str(&self.indentation());
+        }
+
+        let body = match &item.kind {
+            ItemKind::Import(import) => self.emit_import(import),
+            ItemKind::Use(path) => format!("use {};", escape_markers(path)),
+            ItemKind::Function(function) => self.emit_function(function, function.is_pub, false),
+            ItemKind::Class(class) => self.emit_class(class),
+            ItemKind::Struct(structure) => self.emit_struct(structure),
+            ItemKind::Enum(enumeration) => self.emit_enum(enumeration, &item.attributes),
+            ItemKind::Trait(declared) => self.emit_trait(declared),
+            ItemKind::Impl(implementation) => self.emit_impl(implementation),
+            ItemKind::Const(constant) => self.emit_const(constant),
+            ItemKind::Verbatim(text) => escape_markers(text),
+        };
+
+        code.push_str(&body);
+        self.mapped(&item.location, code)
+    }
+
+    fn emit_import(&mut self, import: &Import) -> String {
+        let path = import.path.join("::");
+
+        match &import.kind {
+            ImportKind::Alias(alias) => format!("use {} as {};", path, alias),
+            ImportKind::List(names) if names.len() == 1 => format!("use {}::{};", path, names[0]),
+            ImportKind::List(names) => format!("use {}::{{{}}};", path, names.join(", ")),
+            ImportKind::All => format!("use {}::*;", path),
+        }
+    }
+
+    fn emit_visibility(is_pub: bool) -> &'static str {
+        if is_pub { "pub " } else { "" }
+    }
+
+    // Visibility of an item, module-level items of shared modules can be imported
+    fn item_visibility(&self, is_pub: bool) -> &'static str {
+        if !is_pub && self.shared_items && self.indent == 0 {
+            "pub(crate) "
+        } else {
+            Self::emit_visibility(is_pub)
+        }
+    }
+
+    fn emit_function(&mut self, function: &Function, is_pub: bool, borrow_self: bool) -> String {
+        let params = function.params
+            .iter()
+            .map(|param| self.emit_param(param, borrow_self && function.is_copper))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let return_type = match &function.return_type {
+            Some(ty) if !is_unit_type(ty) => format!(" -> {}", self.emit_type(ty)),
+            _ => String::new(),
+        };
+        let returns_value = !return_type.is_empty();
+        let body = match &function.body {
+            Some(body) => format!(" {}", self.emit_block(body, returns_value)),
+            None => ";".to_string(),
+        };
+
+        format!(
+            "{}fn {}{}({}){}{}{}",
+            self.item_visibility(is_pub),
+            function.name,
+            self.emit_generics(&function.generics),
+            params,
+            return_type,
+            self.emit_where_clause(&function.generics),
+            body,
+        )
+    }
+
+    // `<T: Display, const N: usize>`, Copper types in the bounds are converted too
+    fn emit_generics(&mut self, generics: &Generics) -> String {
+        if generics.params.is_empty() {
+            return String::new();
+        }
+
+        let params = generics.params
+            .iter()
+            .map(|param| match param {
+                GenericParam::Lifetime { name, bounds } => format!("{}{}", name, self.emit_bounds(bounds)),
+                GenericParam::Type { name, bounds, default } => {
+                    let default = default.as_ref().map(|ty| format!(" = {}", self.emit_type(ty))).unwrap_or_default();
+                    format!("{}{}{}", name, self.emit_bounds(bounds), default)
+                },
+                GenericParam::Const { name, ty } => format!("const {}: {}", name, self.emit_type(ty)),
+            })
+            .collect::<Vec<_>>();
+        format!("<{}>", params.join(", "))
+    }
+
+    // `: Display + Clone`, nothing when there are no bounds
+    fn emit_bounds(&mut self, bounds: &[Type]) -> String {
+        if bounds.is_empty() {
+            return String::new();
+        }
+        let bounds = bounds.iter().map(|bound| self.emit_type(bound)).collect::<Vec<_>>();
+        format!(": {}", bounds.join(" + "))
+    }
+
+    // ` where T: Display, U: Clone`, it goes right before the body
+    fn emit_where_clause(&mut self, generics: &Generics) -> String {
+        if generics.predicates.is_empty() {
+            return String::new();
+        }
+        let predicates = generics.predicates
+            .iter()
+            .map(|predicate| format!("{}{}", self.emit_type(&predicate.ty), self.emit_bounds(&predicate.bounds)))
+            .collect::<Vec<_>>();
+        format!(" where {}", predicates.join(", "))
+    }
+
+    // A method with its doc comments, on its own lines at the current indentation
+    fn emit_method(&mut self, method: &Function, is_pub: bool, borrow_self: bool) -> String {
+        let docs = self.emit_docs(&method.docs);
+        format!("{}{}{}\n", self.indentation(), docs, self.emit_function(method, is_pub, borrow_self))
+    }
+
+    // `{ ... }` of an impl or a trait, the members are separated by blank lines
+    fn emit_members(&mut self, members: Vec<String>) -> String {
+        if members.is_empty() {
+            return "{}".to_string();
+        }
+        format!("{{\n{}{}}}", members.join("\n"), self.indentation())
+    }
+
+    // Copper `func` methods take `self` by reference unless told otherwise
+    fn emit_param(&mut self, param: &Param, borrow_self: bool) -> String {
+        match param {
+            Param::SelfParam { reference, mutable } => {
+                match (*reference || borrow_self, mutable) {
+                    (true, true) => "&mut self".to_string(),
+                    (true, false) => "&self".to_string(),
+                    (false, true) => "mut self".to_string(),
+                    (false, false) => "self".to_string(),
+                }
+            },
+            Param::Named { name, mutable, ty, .. } => {
+                format!("{}{}: {}", if *mutable { "mut " } else { "" }, name, self.emit_type(ty))
+            },
+        }
+    }
+
+    fn emit_class(&mut self, class: &Class) -> String {
+        let where_clause = self.emit_where_clause(&class.generics);
+        let mut code = format!(
+            "{}struct {}{}{} {{\n",
+            self.item_visibility(false),
+            class.name,
+            self.emit_generics(&class.generics),
+            where_clause,
+        );
+        self.indent += 1;
+        // The parent is embedded, `Deref` makes its fields and methods reachable
+        let parent = class.extends.as_ref().map(|ty| (parent_field(ty), self.emit_type(ty)));
+        if let Some((field, ty)) = &parent {
+            code.push_str(&format!("{}{}: {},\n", self.indentation(), field, ty));
+        }
+        for field in &class.fields {
+            code.push_str(&format!(
+                "{}{}{}{}: {},\n",
+                self.indentation(),
+                self.emit_docs(&field.docs),
+                Self::emit_visibility(field.is_pub),
+                field.name,
+                self.emit_type(&field.ty),
+            ));
+        }
+        self.indent -= 1;
+        code.push_str(&self.indentation());
+        code.push('}');
+
+        // Methods of a Copper trait go to `impl Trait for Class`
+        let mut implemented = vec![Vec::new(); class.implements.len()];
+        let mut members = Vec::new();
+        self.parent_field = parent.as_ref().map(|(field, _)| field.clone());
+        self.indent += 1;
+        if !class.constants.is_empty() {
+            let constants = class.constants
+                .iter()
+                .map(|constant| format!("{}{}\n", self.indentation(), self.emit_const(constant)))
+                .collect();
+            members.push(constants);
+        }
+        for constructor in &class.constructors {
+            let docs = self.emit_docs(&constructor.docs);
+            let constructor = self.emit_constructor(class, constructor);
+            members.push(format!("{}{}{}\n", self.indentation(), docs, constructor));
+        }
+        for field in class.fields.iter().filter(|field| field.getter || field.setter) {
+            members.push(self.emit_accessors(field));
+        }
+        for method in &class.methods {
+            let from_trait = class.implements.iter().position(|ty| {
+                type_name(ty)
+                    .and_then(|name| self.traits.get(name))
+                    .is_some_and(|methods| methods.contains(&method.name))
+            });
+            match from_trait {
+                Some(index) => implemented[index].push(self.emit_method(method, false, true)),
+                None => members.push(self.emit_method(method, method.is_pub, true)),
+            }
+        }
+        self.indent -= 1;
+        self.parent_field = None;
+
+        // Impl blocks declare the parameters without defaults and pass them to the class
+        let mut impl_generics = class.generics.clone();
+        for param in &mut impl_generics.params {
+            if let GenericParam::Type { default, .. } = param {
+                *default = None;
+            }
+        }
+        let impl_generics = self.emit_generics(&impl_generics);
+        let self_type = format!("{}{}", class.name, generic_args(&class.generics));
+
+        if !members.is_empty() {
+            let body = self.emit_members(members);
+            code.push_str(&format!("\n\n{}impl{} {}{} {}", self.indentation(), impl_generics, self_type, where_clause, body));
+        }
+        for (ty, methods) in class.implements.iter().zip(implemented) {
+            let name = self.emit_type(ty);
+            let body = self.emit_members(methods);
+            code.push_str(&format!(
+                "\n\n{}impl{} {} for {}{} {}",
+                self.indentation(), impl_generics, name, self_type, where_clause, body,
+            ));
+        }
+
+        if let Some((field, ty)) = parent {
+            let indentation = self.indentation();
+            code.push_str(&format!(
+                concat!(
+                    "\n\n{0}impl{4} std::ops::Deref for {1}{5} {{\n",
+                    "{0}    type Target = {2};\n\n",
+                    "{0}    fn deref(&self) -> &{2} {{\n",
+                    "{0}        &self.{3}\n",
+                    "{0}    }}\n",
+                    "{0}}}\n\n",
+                    "{0}impl{4} std::ops::DerefMut for {1}{5} {{\n",
+                    "{0}    fn deref_mut(&mut self) -> &mut {2} {{\n",
+                    "{0}        &mut self.{3}\n",
+                    "{0}    }}\n",
+                    "{0}}}",
+                ),
+                indentation, self_type, ty, field, impl_generics, where_clause,
+            ));
+        }
+        code
+    }
+
+    // `self.field = value` assignments in the constructor body become the
+    // fields of the returned `Self { ... }`, other statements run before it.
+    // `super(...)` builds the parent, with no arguments when it isn't called.
+    fn emit_constructor(&mut self, class: &Class, constructor: &Constructor) -> String {
+        let mut stmts = Vec::new();
+        let mut values = Vec::new();
+        let mut parent_args = Vec::new();
+
+        for stmt in &constructor.body.stmts {
+            if let (Some(_), Some(args)) = (&class.extends, super_call(stmt)) {
+                parent_args = args.to_vec();
+                continue;
+            }
+            match self_field_assignment(stmt) {
+                Some((name, value)) => values.push((name, value.clone())),
+                None => stmts.push(stmt.clone()),
+            }
+        }
+
+        let params = constructor.params
+            .iter()
+            .map(|param| self.emit_param(param, false))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let visibility = Self::emit_visibility(constructor.is_pub);
+        let mut code = format!("{}fn {}({}) -> Self {{\n", visibility, constructor.name, params);
+        self.indent += 1;
+
+        for stmt in &stmts {
+            code.push_str(&self.indentation());
+            code.push_str(&self.emit_stmt(stmt, false, false));
+            code.push('\n');
+        }
+
+        code.push_str(&format!("{}Self {{\n", self.indentation()));
+        self.indent += 1;
+        if let Some(parent) = &class.extends {
+            let ty = self.emit_type(parent);
+            let ty = if ty.contains('<') { format!("<{}>", ty) } else { ty };
+            let args = self.emit_exprs(&parent_args);
+            code.push_str(&format!("{}{}: {}::new({}),\n", self.indentation(), parent_field(parent), ty, args));
+        }
+        for field in &class.fields {
+            let value = match values.iter().rev().find(|(name, _)| *name == field.name) {
+                Some((_, value)) if is_path_to(value, &field.name) => None,
+                Some((_, value)) => Some(self.emit_expr(value)),
+                None => Some("Default::default()".to_string()),
+            };
+
+            match value {
+                Some(value) => code.push_str(&format!("{}{}: {},\n", self.indentation(), field.name, value)),
+                None => code.push_str(&format!("{}{},\n", self.indentation(), field.name)),
+            }
+        }
+        self.indent -= 1;
+        code.push_str(&format!("{}}}\n", self.indentation()));
+
+        self.indent -= 1;
+        code.push_str(&self.indentation());
+        code.push('}');
+        code
+    }
+
+    // `name()` and `set_name(value)` of the fields marked `#[get]` and `#[set]`
+    fn emit_accessors(&mut self, field: &Field) -> String {
+        let indentation = self.indentation();
+        let ty = self.emit_type(&field.ty);
+        let mut methods = Vec::new();
+
+        if field.getter {
+            let (returned, borrow) = match ty.as_str() {
+                "String" => ("&str".to_string(), "&"),
+                copied if COPY_TYPES.contains(&copied) => (ty.clone(), ""),
+                _ => (format!("&{}", ty), "&"),
+            };
+            methods.push(format!(
+                "{0}pub fn {1}(&self) -> {2} {{\n{0}{3}{4}self.{1}\n{0}}}\n",
+                indentation, field.name, returned, INDENT, borrow,
+            ));
+        }
+        if field.setter {
+            methods.push(format!(
+                "{0}pub fn set_{1}(&mut self, {1}: {2}) {{\n{0}{3}self.{1} = {1};\n{0}}}\n",
+                indentation, field.name, ty, INDENT,
+            ));
+        }
+
+        methods.join("\n")
+    }
+
+    fn emit_struct(&mut self, structure: &Struct) -> String {
+        let header = format!(
+            "{}struct {}{}{}",
+            self.item_visibility(structure.is_pub),
+            structure.name,
+            self.emit_generics(&structure.generics),
+            self.emit_where_clause(&structure.generics),
+        );
+
+        if structure.fields.is_empty() {
+            return format!("{};", header);
+        }
+
+        let mut code = format!("{} {{\n", header);
+        self.indent += 1;
+        for field in &structure.fields {
+            code.push_str(&format!(
+                "{}{}{}{}: {},\n",
+                self.indentation(),
+                self.emit_docs(&field.docs),
+                Self::emit_visibility(field.is_pub),
+                field.name,
+                self.emit_type(&field.ty),
+            ));
+        }
+        self.indent -= 1;
+        code.push_str(&self.indentation());
+        code.push('}');
+        code
+    }
+
+    // Structs and classes derive what their attributes list. Enums also derive
+    // the `ENUM_DERIVES` their variants support, which depends on other enums.
+    fn collect_derives(&mut self, items: &[&Item]) {
+        let mut enums = Vec::new();
+        for item in items {
+            let written = written_derives(&item.attributes).into_iter().map(str::to_string).collect::<Vec<_>>();
+            match &item.kind {
+                ItemKind::Struct(structure) => {
+                    self.derives.insert(structure.name.clone(), written);
+                },
+                ItemKind::Class(class) => {
+                    self.derives.insert(class.name.clone(), written);
+                },
+                ItemKind::Enum(enumeration) => {
+                    let mut derives = written.clone();
+                    derives.extend(ENUM_DERIVES.iter().filter(|derive| !written.iter().any(|w| w == *derive)).map(|d| d.to_string()));
+                    self.derives.insert(enumeration.name.clone(), derives);
+                    enums.push((enumeration, written));
+                },
+                _ => {},
+            }
+        }
+
+        // Derives only get removed, so this ends once nothing changes
+        loop {
+            let mut changed = false;
+            for (enumeration, written) in &enums {
+                let mut derives = written.clone();
+                derives.extend(
+                    self.enum_derives(enumeration)
+                        .into_iter()
+                        .filter(|derive| !written.iter().any(|w| w == derive))
+                        .map(str::to_string),
+                );
+                if self.derives.get(&enumeration.name) != Some(&derives) {
+                    self.derives.insert(enumeration.name.clone(), derives);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    // The `ENUM_DERIVES` every type in the variants of `enumeration` implements
+    fn enum_derives(&self, enumeration: &Enum) -> Vec<&'static str> {
+        let generics = enumeration
+            .generics
+            .params
+            .iter()
+            .filter_map(|param| match param {
+                GenericParam::Type { name, .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let types = enumeration
+            .variants
+            .iter()
+            .flat_map(|variant| match &variant.kind {
+                VariantKind::Unit => Vec::new(),
+                VariantKind::Tuple(types) => types.iter().collect(),
+                VariantKind::Struct(fields) => fields.iter().map(|field| &field.ty).collect(),
+            })
+            .collect::<Vec<_>>();
+
+        ENUM_DERIVES
+            .iter()
+            .filter(|derive| types.iter().all(|ty| self.implements(ty, derive, &generics)))
+            .copied()
+            .collect()
+    }
+
+    // Whether `ty` implements the derivable trait `derive`. Generic parameters
+    // are assumed to, types this file knows nothing about are not.
+    fn implements(&self, ty: &Type, derive: &str, generics: &[&str]) -> bool {
+        match &ty.kind {
+            TypeKind::Path { segments, args } => {
+                let Some(last) = segments.last() else { return false };
+                let converted = convert_type(last);
+                let name = converted.rsplit("::").next().unwrap_or_default();
+                let known = (segments.len() == 1 && generics.contains(&name))
+                    || DERIVING_TYPES.contains(&name)
+                    || self.derives.get(name).is_some_and(|derives| derives.iter().any(|d| d == derive));
+                known && args.iter().all(|arg| self.implements(arg, derive, generics))
+            },
+            // `&T` is `Clone` whatever `T` is, `&mut T` never is
+            TypeKind::Reference { mutable, inner, .. } => match derive {
+                "Clone" => !mutable,
+                _ => self.implements(inner, derive, generics),
+            },
+            TypeKind::Tuple(types) => types.iter().all(|ty| self.implements(ty, derive, generics)),
+            TypeKind::Array { inner, .. } | TypeKind::Optional(inner) => self.implements(inner, derive, generics),
+            TypeKind::Binding { ty, .. } => self.implements(ty, derive, generics),
+            TypeKind::Infer | TypeKind::Verbatim(_) => false,
+        }
+    }
+
+    // Enums derive `Debug`, `Clone` and `PartialEq` when their variants support
+    // them, unless `attributes` already do
+    fn emit_enum(&mut self, enumeration: &Enum, attributes: &[String]) -> String {
+        let derived = written_derives(attributes);
+        let derives = self
+            .enum_derives(enumeration)
+            .into_iter()
+            .filter(|derive| !derived.contains(derive))
+            .collect::<Vec<_>>();
+
+        let mut code = String::new();
+        if !derives.is_empty() {
+            code.push_str(&format!("#[derive({})]\n{}", derives.join(", "), self.indentation()));
+        }
+        code.push_str(&format!(
+            "{}enum {}{}{} {{\n",
+            self.item_visibility(enumeration.is_pub),
+            enumeration.name,
+            self.emit_generics(&enumeration.generics),
+            self.emit_where_clause(&enumeration.generics),
+        ));
+
+        self.indent += 1;
+        for variant in &enumeration.variants {
+            code.push_str(&self.indentation());
+            code.push_str(&self.emit_docs(&variant.docs));
+            code.push_str(&variant.name);
+
+            match &variant.kind {
+                VariantKind::Unit => {},
+                VariantKind::Tuple(types) => {
+                    let types = types.iter().map(|ty| self.emit_type(ty)).collect::<Vec<_>>();
+                    code.push_str(&format!("({})", types.join(", ")));
+                },
+                // One line, unless the fields have doc comments
+                VariantKind::Struct(fields) if fields.iter().all(|field| field.docs.is_empty()) => {
+                    let fields = fields
+                        .iter()
+                        .map(|field| format!("{}: {}", field.name, self.emit_type(&field.ty)))
+                        .collect::<Vec<_>>();
+                    code.push_str(&format!(" {{ {} }}", fields.join(", ")));
+                },
+                VariantKind::Struct(fields) => {
+                    code.push_str(" {\n");
+                    self.indent += 1;
+                    for field in fields {
+                        code.push_str(&format!(
+                            "{}{}{}: {},\n",
+                            self.indentation(),
+                            self.emit_docs(&field.docs),
+                            field.name,
+                            self.emit_type(&field.ty),
+                        ));
+                    }
+                    self.indent -= 1;
+                    code.push_str(&self.indentation());
+                    code.push('}');
+                },
+            }
+
+            if let Some(discriminant) = &variant.discriminant {
+                code.push_str(&format!(" = {}", self.emit_expr(discriminant)));
+            }
+            code.push_str(",\n");
+        }
+        self.indent -= 1;
+
+        code.push_str(&self.indentation());
+        code.push('}');
+        code
+    }
+
+    fn emit_impl(&mut self, implementation: &Impl) -> String {
+        let generics = self.emit_generics(&implementation.generics);
+        let target = self.emit_type(&implementation.target);
+        let where_clause = self.emit_where_clause(&implementation.generics);
+        let header = match &implementation.trait_name {
+            Some(trait_name) => format!("impl{} {} for {}{}", generics, self.emit_type(trait_name), target, where_clause),
+            None => format!("impl{} {}{}", generics, target, where_clause),
+        };
+
+        // Methods of a trait impl can't be `pub`
+        let is_trait = implementation.trait_name.is_some();
+        self.indent += 1;
+        let methods = implementation.methods
+            .iter()
+            .map(|method| self.emit_method(method, method.is_pub && !is_trait, true))
+            .collect();
+        self.indent -= 1;
+
+        format!("{} {}", header, self.emit_members(methods))
+    }
+
+    fn emit_trait(&mut self, declared: &Trait) -> String {
+        let header = format!(
+            "{}trait {}{}{}{}",
+            self.item_visibility(declared.is_pub),
+            declared.name,
+            self.emit_generics(&declared.generics),
+            self.emit_bounds(&declared.bounds),
+            self.emit_where_clause(&declared.generics),
+        );
+
+        self.indent += 1;
+        let methods = declared.methods.iter().map(|method| self.emit_method(method, false, true)).collect();
+        self.indent -= 1;
+
+        format!("{} {}", header, self.emit_members(methods))
+    }
+
+    fn emit_const(&mut self, constant: &Const) -> String {
+        format!(
+            "{}{} {}: {} = {};",
+            self.item_visibility(constant.is_pub),
+            if constant.is_static { "static" } else { "const" },
+            constant.name,
+            self.emit_type(&constant.ty),
+            self.emit_expr(&constant.value),
+        )
+    }
+
+    /// Emits a block. When `returns_value` is false the block is used as a
+    /// statement, so its trailing expression is terminated as well.
+    fn emit_block(&mut self, block: &Block, returns_value: bool) -> String {
+        if block.stmts.is_empty() {
+            return "{}".to_string();
+        }
+
+        let mut code = "{\n".to_string();
+        self.indent += 1;
+
+        let last = block.stmts.len() - 1;
+        for (index, stmt) in block.stmts.iter().enumerate() {
+            let is_tail = index == last && returns_value;
+            code.push_str(&self.indentation());
+            code.push_str(&self.emit_stmt(stmt, is_tail, index < last));
+            code.push('\n');
+        }
+
+        self.indent -= 1;
+        code.push_str(&self.indentation());
+        code.push('}');
+        code
+    }
+
+    fn emit_stmt(&mut self, stmt: &Stmt, is_tail: bool, blank_line_after_item: bool) -> String {
+        let code = match &stmt.kind {
+            StmtKind::Let { pattern, ty, value } => {
+                let mut code = format!("let {}", self.emit_pattern(pattern));
+                if let Some(ty) = ty {
+                    code.push_str(&format!(": {}", self.emit_type(ty)));
+                }
+                if let Some(value) = value {
+                    code.push_str(&format!(" = {}", self.emit_value(value, ty.as_ref())));
+                }
+                code.push(';');
+                code
+            },
+            StmtKind::Expr { expr, semicolon } => {
+                let is_value = is_tail && !*semicolon;
+                let code = self.emit_expr_in(expr, is_value);
+
+                if is_value || expr.is_block_like() {
+                    code
+                } else {
+                    code + ";"
+                }
+            },
+            StmtKind::Item(item) => {
+                let code = self.emit_item(item);
+                if blank_line_after_item {
+                    code + "\n"
+                } else {
+                    code
+                }
+            },
+        };
+
+        self.mapped(&stmt.location, code)
+    }
+
+    fn emit_pattern(&mut self, pattern: &Pattern) -> String {
+        match &pattern.kind {
+            PatternKind::Ident { name, mutable } => {
+                format!("{}{}", if *mutable { "mut " } else { "" }, name)
+            },
+            PatternKind::Verbatim(text) => escape_markers(text),
+        }
+    }
+
+    pub fn emit_type(&mut self, ty: &Type) -> String {
+        match &ty.kind {
+            TypeKind::Path { segments, args } => {
+                let name = if segments.len() == 1 {
+                    let (converted, data_type) = convert_type_with_marking(&segments[0]);
+                    match data_type.as_deref() {
+                        Some("json") => self.result.mark_json_usage(),
+                        Some("xml") => self.result.mark_xml_usage(),
+                        Some("toml") => self.result.mark_toml_usage(),
+                        _ => {}
+                    }
+                    converted
+                } else {
+                    segments.join("::")
+                };
+
+                if args.is_empty() {
+                    name
+                } else {
+                    let args = args.iter().map(|arg| self.emit_type(arg)).collect::<Vec<_>>();
+                    format!("{}<{}>", name, args.join(", "))
+                }
+            },
+            TypeKind::Reference { lifetime, mutable, inner } => {
+                let mut code = "&".to_string();
+                if let Some(lifetime) = lifetime {
+                    code.push_str(lifetime);
+                    code.push(' ');
+                }
+                if *mutable {
+                    code.push_str("mut ");
+                }
+                code.push_str(&self.emit_type(inner));
+                code
+            },
+            TypeKind::Tuple(types) => {
+                let types = types.iter().map(|ty| self.emit_type(ty)).collect::<Vec<_>>();
+                if types.len() == 1 {
+                    format!("({},)", types[0])
+                } else {
+                    format!("({})", types.join(", "))
+                }
+            },
+            TypeKind::Array { inner, len } => match len {
+                Some(len) => format!("[{}; {}]", self.emit_type(inner), self.emit_expr(len)),
+                None => format!("[{}]", self.emit_type(inner)),
+            },
+            TypeKind::Optional(inner) => format!("Option<{}>", self.emit_type(inner)),
+            TypeKind::Infer => "_".to_string(),
+            TypeKind::Verbatim(text) => escape_markers(text),
+            TypeKind::Binding { name, ty } => format!("{} = {}", name, self.emit_type(ty)),
+        }
+    }
+
+    fn emit_path(&mut self, path: &Path) -> String {
+        path.segments
+            .iter()
+            .map(|segment| {
+                if segment.generics.is_empty() {
+                    segment.name.clone()
+                } else {
+                    let generics = segment.generics.iter().map(|ty| self.emit_type(ty)).collect::<Vec<_>>();
+                    format!("{}::<{}>", segment.name, generics.join(", "))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+
+    // A value of a declared type. String literals declared as `string` become
+    // `String`s, in collection literals too.
+    fn emit_value(&mut self, value: &Expr, ty: Option<&Type>) -> String {
+        let item_types = match ty.map(|ty| &ty.kind) {
+            Some(TypeKind::Path { args, .. }) => args.as_slice(),
+            Some(TypeKind::Array { inner, .. }) => std::slice::from_ref(inner.as_ref()),
+            Some(TypeKind::Tuple(types)) => types.as_slice(),
+            _ => &[],
+        };
+        let (first, second) = (item_types.first(), item_types.get(1));
+        // Array literals passed to a `list` or `set` parameter
+        let collection = match ty.map(|ty| &ty.kind) {
+            Some(TypeKind::Path { segments, .. }) => segments.last().map(|name| convert_type(name).rsplit("::").next().unwrap().to_string()),
+            _ => None,
+        };
+
+        match &value.kind {
+            ExprKind::Literal(Literal::String(_)) if ty.is_some_and(is_string_type) => {
+                format!("{}.to_string()", self.emit_expr(value))
+            },
+            ExprKind::List(items) => format!("vec![{}]", self.emit_values(items, first)),
+            ExprKind::Set(items) if items.is_empty() => "std::collections::HashSet::new()".to_string(),
+            ExprKind::Set(items) => format!("std::collections::HashSet::from([{}])", self.emit_values(items, first)),
+            ExprKind::Map(entries) if entries.is_empty() => "std::collections::HashMap::new()".to_string(),
+            ExprKind::Map(entries) => {
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| format!("({}, {})", self.emit_value(key, first), self.emit_value(value, second)))
+                    .collect::<Vec<_>>();
+                format!("std::collections::HashMap::from([{}])", entries.join(", "))
+            },
+            ExprKind::Array(items) if collection.as_deref() == Some("Vec") => format!("vec![{}]", self.emit_values(items, first)),
+            ExprKind::Array(items) if collection.as_deref() == Some("HashSet") => match items.is_empty() {
+                true => "std::collections::HashSet::new()".to_string(),
+                false => format!("std::collections::HashSet::from([{}])", self.emit_values(items, first)),
+            },
+            ExprKind::Array(items) if first.is_some() => format!("[{}]", self.emit_values(items, first)),
+            ExprKind::Tuple(items) if items.len() > 1 && items.len() == item_types.len() => {
+                let items = items
+                    .iter()
+                    .zip(item_types)
+                    .map(|(item, ty)| self.emit_value(item, Some(ty)))
+                    .collect::<Vec<_>>();
+                format!("({})", items.join(", "))
+            },
+            _ => self.emit_expr(value),
+        }
+    }
+
+    fn emit_values(&mut self, values: &[Expr], ty: Option<&Type>) -> String {
+        values.iter().map(|value| self.emit_value(value, ty)).collect::<Vec<_>>().join(", ")
+    }
+
+    // Arguments the checker found a parameter type for are values of that type
+    fn emit_args(&mut self, args: &[Expr]) -> String {
+        args
+            .iter()
+            .map(|arg| match self.argument_types.get(&arg.location.range).cloned() {
+                Some(ty) => self.emit_value(arg, Some(&ty)),
+                None => self.emit_expr(arg),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn emit_exprs(&mut self, exprs: &[Expr]) -> String {
+        exprs.iter().map(|expr| self.emit_expr(expr)).collect::<Vec<_>>().join(", ")
+    }
+
+    pub fn emit_expr(&mut self, expr: &Expr) -> String {
+        self.emit_expr_in(expr, true)
+    }
+
+    /// `is_value` is false for block-like expressions used as statements,
+    /// whose blocks then don't produce a value either
+    fn emit_expr_in(&mut self, expr: &Expr, is_value: bool) -> String {
+        let code = match &expr.kind {
+            ExprKind::Literal(literal) => match literal {
+                Literal::Number(value) | Literal::String(value) | Literal::Char(value) => escape_markers(value),
+                Literal::Bool(value) => value.to_string(),
+            },
+            ExprKind::Interpolated(parts) => {
+                let (format, args) = format_args(parts, None);
+                format!("format!({}, {})", escape_markers(&format), self.emit_exprs(&args))
+            },
+            ExprKind::Path(path) => match (&self.parent_field, path.as_ident()) {
+                (Some(field), Some("super")) => format!("self.{}", field),
+                _ => self.emit_path(path),
+            },
+            ExprKind::Unary { op, expr } => format!("{}{}", op.as_str(), self.emit_expr(expr)),
+            ExprKind::Binary { op, lhs, rhs } => {
+                format!("{} {} {}", self.emit_expr(lhs), op.as_str(), self.emit_expr(rhs))
+            },
+            ExprKind::Assign { op, target, value } => {
+                let op = op.map_or("=".to_string(), |op| format!("{}=", op.as_str()));
+                format!("{} {} {}", self.emit_expr(target), op, self.emit_expr(value))
+            },
+            ExprKind::Call { callee, args } => {
+                format!("{}({})", self.emit_expr(callee), self.emit_args(args))
+            },
+            ExprKind::MethodCall { receiver, method, generics, args } => {
+                let generics = if generics.is_empty() {
+                    String::new()
+                } else {
+                    let generics = generics.iter().map(|ty| self.emit_type(ty)).collect::<Vec<_>>();
+                    format!("::<{}>", generics.join(", "))
+                };
+                format!("{}.{}{}({})", self.emit_expr(receiver), method, generics, self.emit_args(args))
+            },
+            ExprKind::Field { base, name } => format!("{}.{}", self.emit_expr(base), name),
+            ExprKind::Index { base, index } => format!("{}[{}]", self.emit_expr(base), self.emit_expr(index)),
+            ExprKind::Macro { path, delimiter, args } => {
+                let args = match args {
+                    MacroArgs::Exprs(exprs) => self.emit_exprs(exprs),
+                    MacroArgs::Verbatim(text) => escape_markers(text),
+                };
+                let path = self.emit_path(path);
+                match delimiter {
+                    Delimiter::Paren => format!("{}!({})", path, args),
+                    Delimiter::Bracket => format!("{}![{}]", path, args),
+                    Delimiter::Brace => format!("{}! {{ {} }}", path, args),
+                }
+            },
+            ExprKind::Cast { expr, ty } => format!("{} as {}", self.emit_expr(expr), self.emit_type(ty)),
+            ExprKind::Try(expr) => format!("{}?", self.emit_expr(expr)),
+            ExprKind::Let { pattern, value } => {
+                format!("let {} = {}", self.emit_pattern(pattern), self.emit_expr(value))
+            },
+            ExprKind::Range { start, end, inclusive } => {
+                let start = start.as_ref().map(|expr| self.emit_expr(expr)).unwrap_or_default();
+                let end = end.as_ref().map(|expr| self.emit_expr(expr)).unwrap_or_default();
+                format!("{}{}{}", start, if *inclusive { "..=" } else { ".." }, end)
+            },
+            ExprKind::Closure { is_move, params, return_type, body } => {
+                let params = params
+                    .iter()
+                    .map(|param| match &param.ty {
+                        Some(ty) => format!("{}: {}", self.emit_pattern(&param.pattern), self.emit_type(ty)),
+                        None => self.emit_pattern(&param.pattern),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let return_type = return_type
+                    .as_ref()
+                    .map(|ty| format!(" -> {}", self.emit_type(ty)))
+                    .unwrap_or_default();
+                format!("{}|{}|{} {}", if *is_move { "move " } else { "" }, params, return_type, self.emit_expr(body))
+            },
+            ExprKind::Block(block) => self.emit_block(block, is_value),
+            ExprKind::Unsafe(block) => format!("unsafe {}", self.emit_block(block, is_value)),
+            ExprKind::If { cond, then, otherwise } => {
+                let mut code = format!("if {} {}", self.emit_condition(cond), self.emit_block(then, is_value));
+                if let Some(otherwise) = otherwise {
+                    code.push_str(&format!(" else {}", self.emit_expr_in(otherwise, is_value)));
+                }
+                code
+            },
+            ExprKind::While { label, cond, body } => {
+                format!("{}while {} {}", emit_label(label), self.emit_condition(cond), self.emit_block(body, false))
+            },
+            ExprKind::Loop { label, body } => {
+                format!("{}loop {}", emit_label(label), self.emit_block(body, false))
+            },
+            ExprKind::For { label, pattern, iter, body } => {
+                format!(
+                    "{}for {} in {} {}",
+                    emit_label(label),
+                    self.emit_pattern(pattern),
+                    self.emit_expr(iter),
+                    self.emit_block(body, false),
+                )
+            },
+            ExprKind::Match { expr, arms } => {
+                let mut code = format!("match {} {{\n", self.emit_condition(expr));
+                self.indent += 1;
+                for arm in arms {
+                    let guard = arm.guard
+                        .as_ref()
+                        .map(|guard| format!(" if {}", self.emit_expr(guard)))
+                        .unwrap_or_default();
+                    let body = self.emit_expr_in(&arm.body, is_value);
+                    code.push_str(&format!(
+                        "{}{}{} => {}{}\n",
+                        self.indentation(),
+                        self.emit_pattern(&arm.pattern),
+                        guard,
+                        body,
+                        if arm.body.is_block_like() { "" } else { "," },
+                    ));
+                }
+                self.indent -= 1;
+                code.push_str(&self.indentation());
+                code.push('}');
+                code
+            },
+            ExprKind::Return(value) => match value {
+                Some(value) => format!("return {}", self.emit_expr(value)),
+                None => "return".to_string(),
+            },
+            ExprKind::Break { label, value } => {
+                let mut code = "break".to_string();
+                if let Some(label) = label {
+                    code.push(' ');
+                    code.push_str(label);
+                }
+                if let Some(value) = value {
+                    code.push(' ');
+                    code.push_str(&self.emit_expr(value));
+                }
+                code
+            },
+            ExprKind::Continue(label) => match label {
+                Some(label) => format!("continue {}", label),
+                None => "continue".to_string(),
+            },
+            ExprKind::StructLiteral { path, fields, base } => {
+                let mut entries = fields
+                    .iter()
+                    .map(|field| match &field.value {
+                        Some(value) => format!("{}: {}", field.name, self.emit_expr(value)),
+                        None => field.name.clone(),
+                    })
+                    .collect::<Vec<_>>();
+                if let Some(base) = base {
+                    entries.push(format!("..{}", self.emit_expr(base)));
+                }
+
+                let path = self.emit_path(path);
+                if entries.is_empty() {
+                    format!("{} {{}}", path)
+                } else {
+                    format!("{} {{ {} }}", path, entries.join(", "))
+                }
+            },
+            ExprKind::Tuple(items) => {
+                if items.len() == 1 {
+                    format!("({},)", self.emit_expr(&items[0]))
+                } else {
+                    format!("({})", self.emit_exprs(items))
+                }
+            },
+            ExprKind::Array(items) => format!("[{}]", self.emit_exprs(items)),
+            ExprKind::List(_) | ExprKind::Set(_) | ExprKind::Map(_) => self.emit_value(expr, None),
+            ExprKind::ArrayRepeat { value, len } => format!("[{}; {}]", self.emit_expr(value), self.emit_expr(len)),
+            ExprKind::Paren(expr) => format!("({})", self.emit_expr(expr)),
+            ExprKind::Json(value) => {
+                self.result.mark_json_usage();
+                format!("json!({})", self.emit_json(value))
+            },
+            // Patterns are checked while compiling, building them can't fail
+            ExprKind::Regex { pattern, flags } if flags.is_empty() => {
+                self.result.mark_regex_usage();
+                format!("__regex__::Regex::new({}).unwrap()", raw_string(pattern))
+            },
+            ExprKind::Regex { pattern, flags } => {
+                self.result.mark_regex_usage();
+                let options = flags
+                    .chars()
+                    .filter_map(|flag| REGEX_FLAGS.iter().find(|(known, _)| *known == flag))
+                    .map(|(_, option)| format!(".{}(true)", option))
+                    .collect::<String>();
+                format!("__regex__::RegexBuilder::new({}){}.build().unwrap()", raw_string(pattern), options)
+            },
+        };
+
+        self.mapped(&expr.location, code)
+    }
+
+    fn emit_json(&mut self, value: &JsonValue) -> String {
+        match value {
+            JsonValue::Object(entries) => {
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, self.emit_json(value)))
+                    .collect::<Vec<_>>();
+                format!("{{{}}}", entries.join(", "))
+            },
+            JsonValue::Array(items) => {
+                let items = items.iter().map(|item| self.emit_json(item)).collect::<Vec<_>>();
+                format!("[{}]", items.join(", "))
+            },
+            JsonValue::Value(expr) => self.emit_expr(expr),
+        }
+    }
+}
+
+/// Format string and arguments of an interpolated string. When it is the
+/// format string of a macro with `existing` positional arguments already, the
+/// text is kept as is and the parts become the arguments after them. Otherwise
+/// braces in the text are escaped.
+pub(crate) fn format_args(parts: &[StringPart], existing: Option<usize>) -> (String, Vec<Expr>) {
+    let mut format = String::from("\"");
+    let mut args = Vec::new();
+
+    for part in parts {
+        match part {
+            StringPart::Text(text) if existing.is_some() => format.push_str(text),
+            StringPart::Text(text) => format.push_str(&text.replace('{', "{{").replace('}', "}}")),
+            StringPart::Expr(expr) => {
+                match existing {
+                    Some(existing) if existing > 0 => format.push_str(&format!("{{{}}}", existing + args.len())),
+                    _ => format.push_str("{}"),
+                }
+                args.push((**expr).clone());
+            },
+        }
+    }
+
+    format.push('"');
+    (format, args)
+}
+
+// Raw string literal of `text`, with enough `#` for the quotes in it
+fn raw_string(text: &str) -> String {
+    let hashes = (0..)
+        .find(|count| !text.contains(&format!("\"{}", "#".repeat(*count))))
+        .unwrap();
+    let hashes = "#".repeat(hashes);
+    format!("r{}\"{}\"{}", hashes, escape_markers(text), hashes)
+}
+
+fn emit_label(label: &Option<String>) -> String {
+    label.as_ref().map(|label| format!("{}: ", label)).unwrap_or_default()
+}
+
+// Traits listed in the `#[derive(...)]` ones of `attributes`
+fn written_derives(attributes: &[String]) -> Vec<&str> {
+    attributes
+        .iter()
+        .filter(|attribute| attribute.starts_with("#[derive("))
+        .flat_map(|attribute| attribute.trim_start_matches("#[derive(").trim_end_matches(")]").split(','))
+        .map(str::trim)
+        .collect()
+}
+
+// `void` and `()` return types are left out of the signature
+fn is_unit_type(ty: &Type) -> bool {
+    match &ty.kind {
+        TypeKind::Path { segments, args } => args.is_empty() && segments.len() == 1 && segments[0] == "void",
+        TypeKind::Tuple(types) => types.is_empty(),
+        _ => false,
+    }
+}
+
+// `Animal` of `Animal` or `zoo::Animal<T>`
+fn type_name(ty: &Type) -> Option<&str> {
+    match &ty.kind {
+        TypeKind::Path { segments, .. } => segments.last().map(String::as_str),
+        _ => None,
+    }
+}
+
+fn is_string_type(ty: &Type) -> bool {
+    matches!(&ty.kind, TypeKind::Path { segments, args } if args.is_empty() && (segments == &["string"] || segments == &["String"]))
+}
+
+fn is_path_to(expr: &Expr, name: &str) -> bool {
+    matches!(&expr.kind, ExprKind::Path(path) if path.as_ident() == Some(name))
+}
+
+// The parameters of a generic class as arguments, `<T, 'a>` of `Box<T: Display, 'a>`
+fn generic_args(generics: &Generics) -> String {
+    if generics.params.is_empty() {
+        return String::new();
+    }
+    let names = generics.params
+        .iter()
+        .map(|param| match param {
+            GenericParam::Lifetime { name, .. } | GenericParam::Type { name, .. } | GenericParam::Const { name, .. } => name.as_str(),
+        })
+        .collect::<Vec<_>>();
+    format!("<{}>", names.join(", "))
+}
+
+// Name of the field holding the parent class, `UserAccount` => `user_account`
+fn parent_field(ty: &Type) -> String {
+    let mut field = String::new();
+    let mut previous_lower = false;
+    for c in type_name(ty).unwrap_or("parent").chars() {
+        if c.is_uppercase() && previous_lower {
+            field.push('_');
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        field.extend(c.to_lowercase());
+    }
+    field
+}
+
+// Matches `super(args)`
+fn super_call(stmt: &Stmt) -> Option<&[Expr]> {
+    let StmtKind::Expr { expr, .. } = &stmt.kind else {
+        return None;
+    };
+    match &expr.kind {
+        ExprKind::Call { callee, args } if is_path_to(callee, "super") => Some(args),
+        _ => None,
+    }
+}
+
+// Matches `self.field = value`
+fn self_field_assignment(stmt: &Stmt) -> Option<(String, &Expr)> {
+    let StmtKind::Expr { expr, .. } = &stmt.kind else {
+        return None;
+    };
+    let ExprKind::Assign { op: None, target, value } = &expr.kind else {
+        return None;
+    };
+    let ExprKind::Field { base, name } = &target.kind else {
+        return None;
+    };
+
+    match &base.kind {
+        ExprKind::Path(path) if path.segments.len() == 1 && path.segments[0].name == "self" => {
+            Some((name.clone(), value))
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, tokenizer::tokenizer::Tokenizer};
+
+    fn emit(source: &str) -> String {
+        let tokens = Tokenizer::new(source.to_string()).tokenize();
+        let mut parser = Parser::new(tokens);
+        let program = parser.parse();
+        assert!(parser.diagnostics().is_empty(), "{:?}", parser.diagnostics());
+        Emitter::new().emit(&program)
+    }
+
+    #[test]
+    fn test_top_level_statements_go_to_main() {
+        let code = emit("func void hello() {\n    println(\"Hello\")\n}\n\nmut count = 1\ncount++\nhello()\n");
+
+        assert!(code.starts_with("fn hello() {\n    println!(\"Hello\");\n}"));
+        assert!(code.contains("fn main() {\n    let mut count = 1;\n    count += 1;\n    hello();\n}"));
+    }
+
+    #[test]
+    fn test_imports() {
+        let code = emit(concat!(
+            "import { HashMap, HashSet } from std.collections\n",
+            "import * from std.io\n",
+            "name = String::from(\"a\")\n",
+            "big = i64::from(count)\n",
+            "from = 1\n",
+            "import = from + 1\n",
+        ));
+
+        assert!(code.starts_with("use std::collections::{HashMap, HashSet};\n\nuse std::io::*;"));
+        assert!(code.contains("let name = String::from(\"a\");"));
+        assert!(code.contains("let big = i64::from(count);"));
+        assert!(code.contains("let from = 1;\n    let import = from + 1;"));
+    }
+
+    #[test]
+    fn test_class() {
+        let code = emit(concat!(
+            "class Person {\n",
+            "    name: string\n",
+            "    age: int\n",
+            "\n",
+            "    Person(name: string) {\n",
+            "        self.name = name\n",
+            "    }\n",
+            "\n",
+            "    string greet(self, other: string) {\n",
+            "        return format!(\"{} {}\", self.name, other)\n",
+            "    }\n",
+            "}\n",
+        ));
+
+        assert!(code.contains("struct Person {\n    name: String,\n    age: i64,\n}"));
+        assert!(code.contains("pub fn new(name: String) -> Self {\n        Self {\n            name,\n            age: Default::default(),\n        }\n    }"));
+        assert!(code.contains("pub fn greet(&self, other: String) -> String {"));
+        assert!(!code.contains("fn main"));
+    }
+
+    #[test]
+    fn test_class_members() {
+        let code = emit(concat!(
+            "class Counter {\n",
+            "    const STEP: int = 2\n",
+            "    #[get, set]\n",
+            "    label: string\n",
+            "    #[get]\n",
+            "    public count: int\n",
+            "    Counter.named(label: string) {\n",
+            "        self.label = label\n",
+            "    }\n",
+            "    func void bump(mut self) {\n",
+            "        self.count += Counter::STEP\n",
+            "    }\n",
+            "    private static func int twice(value: int) {\n",
+            "        value * 2\n",
+            "    }\n",
+            "}\n",
+        ));
+
+        assert!(code.contains("struct Counter {\n    label: String,\n    pub count: i64,\n}"));
+        assert!(code.contains("impl Counter {\n    pub const STEP: i64 = 2;\n\n    pub fn named(label: String) -> Self {"));
+        assert!(code.contains("pub fn label(&self) -> &str {\n        &self.label\n    }"));
+        assert!(code.contains("pub fn set_label(&mut self, label: String) {\n        self.label = label;\n    }"));
+        assert!(code.contains("pub fn count(&self) -> i64 {\n        self.count\n    }"));
+        assert!(code.contains("pub fn bump(&mut self) {"));
+        assert!(code.contains("\n    fn twice(value: i64) -> i64 {"));
+    }
+
+    #[test]
+    fn test_generics() {
+        let code = emit(concat!(
+            "func T first<T: Clone>(items: Vec<T>) {\n",
+            "    items[0].clone()\n",
+            "}\n",
+            "func int total<I>(items: I)\n",
+            "    where I: Iterator<Item = int>\n",
+            "{\n",
+            "    items.sum()\n",
+            "}\n",
+            "class Cell<T: Into<string> = string> implements Named {\n",
+            "    value: T\n",
+            "    func string name(self) { \"cell\".to_string() }\n",
+            "    T get(self) where T: Clone {\n",
+            "        self.value.clone()\n",
+            "    }\n",
+            "}\n",
+            "func T last<T>(items: List<T>, seen: Set<T>, counts: Map<T, int>) {\n",
+            "    items[0]\n",
+            "}\n",
+        ));
+
+        assert!(code.contains("fn first<T: Clone>(items: Vec<T>) -> T {"));
+        assert!(code.contains("fn total<I>(items: I) -> i64 where I: Iterator<Item = i64> {"));
+        assert!(code.contains("struct Cell<T: Into<String> = String> {\n    value: T,\n}"));
+        assert!(code.contains("impl<T: Into<String>> Cell<T> {\n    pub fn name(&self) -> String {"));
+        assert!(code.contains("impl<T: Into<String>> Named for Cell<T> {}"));
+        assert!(code.contains("pub fn get(&self) -> T where T: Clone {"));
+        assert!(code.contains(concat!(
+            "fn last<T>(items: Vec<T>, seen: std::collections::HashSet<T>, ",
+            "counts: std::collections::HashMap<T, i64>) -> T {",
+        )));
+    }
+
+    #[test]
+    fn test_inheritance() {
+        let code = emit(concat!(
+            "class Admin extends UserAccount {\n",
+            "    level: int\n",
+            "    Admin(name: string) {\n",
+            "        super(name, 1)\n",
+            "        self.level = 1\n",
+            "    }\n",
+            "    override func string greet(self) {\n",
+            "        super.greet()\n",
+            "    }\n",
+            "}\n",
+        ));
+
+        assert!(code.contains("struct Admin {\n    user_account: UserAccount,\n    level: i64,\n}"));
+        assert!(code.contains("Self {\n            user_account: UserAccount::new(name, 1),\n            level: 1,\n        }"));
+        assert!(code.contains("pub fn greet(&self) -> String {\n        self.user_account.greet()\n    }"));
+        assert!(code.contains("impl std::ops::Deref for Admin {\n    type Target = UserAccount;\n"));
+        assert!(code.contains("fn deref_mut(&mut self) -> &mut UserAccount {\n        &mut self.user_account\n    }"));
+
+        let source = concat!(
+            "class Animal {\n",
+            "    name: string\n",
+            "    Animal.named(name: string) {\n",
+            "        self.name = name\n",
+            "    }\n",
+            "}\n",
+            "class Dog extends Animal {\n",
+            "    Dog() {}\n",
+            "}\n",
+        );
+        let mut parser = Parser::new(Tokenizer::new(source.to_string()).tokenize());
+        parser.parse();
+        let diagnostics = parser.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "Constructor of `Dog` has to call `super(...)`");
+        assert_eq!(diagnostics[0].primary.location.first_line, 8);
+
+        let source = concat!(
+            "class Plain {\n",
+            "    Plain() {\n",
+            "        super()\n",
+            "    }\n",
+            "    func void hi(self) {\n",
+            "        super.hi()\n",
+            "    }\n",
+            "}\n",
+            "class Cat extends Plain {\n",
+            "    Cat(ok: bool) {\n",
+            "        if ok {\n",
+            "            super()\n",
+            "        }\n",
+            "    }\n",
+            "}\n",
+        );
+        let mut parser = Parser::new(Tokenizer::new(source.to_string()).tokenize());
+        parser.parse();
+        let messages = parser.diagnostics().iter().map(|diagnostic| diagnostic.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(messages, [
+            "`Plain` doesn't extend a class, `super` has no parent to refer to",
+            "`Plain` doesn't extend a class, `super` has no parent to refer to",
+            "`super(...)` is only allowed at the top level of a constructor",
+        ]);
+    }
+
+    #[test]
+    fn test_tail_expression() {
+        let code = emit("func int add(a: int, b: int) {\n    a + b\n}\n");
+
+        assert!(code.contains("fn add(a: i64, b: i64) -> i64 {\n    a + b\n}"));
+    }
+
+    #[test]
+    fn test_json_literal() {
+        let code = emit("data = { \"name\": \"copper\", \"tags\": [1, 2] }\n");
+
+        assert!(code.contains("use serde_json::{json, Value as JsonValue};"));
+        assert!(code.contains("let data = json!({\"name\": \"copper\", \"tags\": [1, 2]});"));
+    }
+
+    #[test]
+    fn test_collections() {
+        let code = emit(concat!(
+            "names: list<string> = [\"ann\", \"bob\"]\n",
+            "ages: map<string, list<int>> = {\n",
+            "    \"ann\": [31],\n",
+            "    \"bob\": [],\n",
+            "}\n",
+            "tags: set<int> = []\n",
+            "pair: tuple<int, string> = (1, \"a\")\n",
+            "grid: int[2][3] = [[1, 2, 3], [4, 5, 6]]\n",
+            "data = [1, 2]\n",
+        ));
+
+        assert!(code.contains("let names: Vec<String> = vec![\"ann\".to_string(), \"bob\".to_string()];"));
+        assert!(code.contains(concat!(
+            "let ages: std::collections::HashMap<String, Vec<i64>> = ",
+            "std::collections::HashMap::from([(\"ann\".to_string(), vec![31]), (\"bob\".to_string(), vec![])]);",
+        )));
+        assert!(code.contains("let tags: std::collections::HashSet<i64> = std::collections::HashSet::new();"));
+        assert!(code.contains("let pair: (i64, String) = (1, \"a\".to_string());"));
+        assert!(code.contains("let grid: [[i64; 3]; 2] = [[1, 2, 3], [4, 5, 6]];"));
+        assert!(code.contains("let data = json!([1, 2]);"));
+    }
+
+    #[test]
+    fn test_collection_arguments() {
+        let source = concat!(
+            "func void total(items: list<int>) {}\n",
+            "func void show(ages: map<string, int>) {}\n",
+            "func void tag(names: set<string>) {}\n",
+            "total([1, 2])\n",
+            "show({\"ann\": 31})\n",
+            "tag([\"new\"])\n",
+        );
+        let program = Parser::new(Tokenizer::new(source.to_string()).tokenize()).parse();
+        let checked = crate::checker::check(&program, &HashMap::new());
+        assert!(checked.diagnostics.is_empty(), "{:?}", checked.diagnostics);
+        let code = Emitter::new().with_argument_types(checked.argument_types).emit(&program);
+
+        assert!(code.contains("total(vec![1, 2]);"));
+        assert!(code.contains("show(std::collections::HashMap::from([(\"ann\".to_string(), 31)]));"));
+        assert!(code.contains("tag(std::collections::HashSet::from([\"new\".to_string()]));"));
+    }
+
+    #[test]
+    fn test_number_literals() {
+        let code = emit("mask = 0xFF_00u16\ncount = 1_000int32\nratio = 2.5e-3float32\nlow = -128int8\n");
+
+        assert!(code.contains("let mask = 0xFF_00u16;"));
+        assert!(code.contains("let count = 1_000i32;"));
+        assert!(code.contains("let ratio = 2.5e-3f32;"));
+        assert!(code.contains("let low = -128i8;"));
+
+        let tokens = Tokenizer::new(concat!(
+            "x: int8 = 300\ny: int8 = -128\nconst Z: uint8 = 256\n",
+            "u: uint8 = -1\nzero: uint8 = -0\nv = -5uint8\nw = -255uint8\n",
+            "big = 340282366920938463463374607431768211456\n",
+        ).to_string()).tokenize();
+        let mut parser = Parser::new(tokens);
+        parser.parse();
+        let messages = parser.diagnostics().iter().map(|diagnostic| diagnostic.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(messages, [
+            "Literal out of range for `int8`",
+            "Literal out of range for `uint8`",
+            "Literal out of range for `uint8`",
+            "Literal out of range for `uint8`",
+            "Literal out of range for `uint8`",
+            "Integer literal is too large",
+        ]);
+    }
+
+    #[test]
+    fn test_regex_literals() {
+        let code = emit("path = /usr\\/local/\nname = /copper/im\n");
+
+        assert!(code.contains("let path = __regex__::Regex::new(r\"usr/local\").unwrap();"));
+        assert!(code.contains("let name = __regex__::RegexBuilder::new(r\"copper\").case_insensitive(true).multi_line(true).build().unwrap();"));
+
+        // The tokenizer reports the missing `/`, the pattern isn't checked too
+        let mut tokenizer = Tokenizer::new("p = /[/\nq = /a(/\n".to_string());
+        let mut parser = Parser::new(tokenizer.tokenize());
+        parser.parse();
+        let messages = tokenizer.diagnostics().iter().chain(parser.diagnostics()).map(|diagnostic| diagnostic.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(messages, ["Unterminated regex literal", "Invalid regex: unclosed group"]);
+    }
+
+    #[test]
+    fn test_enum() {
+        let code = emit(concat!(
+            "#[derive(Clone, Copy)]\n",
+            "enum Level { Low = 1, High = 10 }\n",
+            "enum Shape {\n",
+            "    Circle(float)\n",
+            "    Rect { width: float, height: int32 },\n",
+            "    Empty\n",
+            "}\n",
+        ));
+
+        assert!(code.contains("#[derive(Clone, Copy)]\n#[derive(Debug, PartialEq)]\nenum Level {\n    Low = 1,\n    High = 10,\n}"));
+        assert!(code.contains(concat!(
+            "#[derive(Debug, Clone, PartialEq)]\n",
+            "enum Shape {\n",
+            "    Circle(f64),\n",
+            "    Rect { width: f64, height: i32 },\n",
+            "    Empty,\n",
+            "}",
+        )));
+    }
+
+    #[test]
+    fn test_enum_derives_follow_payloads() {
+        let code = emit(concat!(
+            "class User { name: string }\n",
+            "#[derive(Debug)]\n",
+            "struct Point { x: int }\n",
+            "enum Event { Login(User), Logout }\n",
+            "enum Wrapped { Event(Event) }\n",
+            "enum Moved { At(Point), To(list<Point>) }\n",
+            "enum Tree<T> { Leaf(T), Node(Box<Tree<T>>) }\n",
+        ));
+
+        assert!(code.contains("\nenum Event {"));
+        assert!(!code.contains("]\nenum Event {"));
+        assert!(!code.contains("]\nenum Wrapped {"));
+        assert!(code.contains("#[derive(Debug)]\nenum Moved {"));
+        assert!(code.contains("#[derive(Debug, Clone, PartialEq)]\nenum Tree<T> {"));
+    }
+
+    #[test]
+    fn test_traits() {
+        let code = emit(concat!(
+            "trait Animal: Clone {\n",
+            "    func string name(self)\n",
+            "    func string speak(self) {\n",
+            "        self.name()\n",
+            "    }\n",
+            "}\n",
+            "class Dog implements Animal, Marker {\n",
+            "    func string name(self) {\n",
+            "        \"Rex\".to_string()\n",
+            "    }\n",
+            "    func void wag(self) {}\n",
+            "}\n",
+            "impl Animal for Cat {\n",
+            "    pub func string name(self) {\n",
+            "        \"Tom\".to_string()\n",
+            "    }\n",
+            "}\n",
+            "impl Cat {\n",
+            "    func void rename(mut self) {}\n",
+            "    fn into_name(self) -> String { self.name() }\n",
+            "}\n",
+        ));
+
+        assert!(code.contains("trait Animal: Clone {\n    fn name(&self) -> String;\n\n    fn speak(&self) -> String {\n        self.name()\n    }\n}"));
+        assert!(code.contains("impl Dog {\n    pub fn wag(&self) {}\n}"));
+        assert!(code.contains("impl Animal for Dog {\n    fn name(&self) -> String {\n        \"Rex\".to_string()\n    }\n}"));
+        assert!(code.contains("impl Marker for Dog {}"));
+        assert!(code.contains("impl Animal for Cat {\n    fn name(&self) -> String {"));
+        assert!(code.contains("fn rename(&mut self) {}"));
+        assert!(code.contains("fn into_name(self) -> String {"));
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let code = emit(concat!(
+            "mut total = 0\n",
+            "for (i in 0..10) {\n",
+            "    total = total + i\n",
+            "    label = \"loop\"\n",
+            "}\n",
+            "if (total > 10) { total = 10 }\n",
+            "kind = match (total) {\n",
+            "    x if x < 0 => \"negative\",\n",
+            "    1..=9 => \"few\",\n",
+            "    _ => \"many\",\n",
+            "}\n",
+            "func void reset() {\n",
+            "    total = 0\n",
+            "}\n",
+        ));
+
+        assert!(code.contains("for i in 0..10 {\n        total = total + i;\n        let label = \"loop\";\n    }"));
+        assert!(code.contains("if total > 10 {\n        total = 10;\n    }"));
+        assert!(code.contains("let kind = match total {\n        x if x < 0 => \"negative\",\n        1..=9 => \"few\","));
+        // Functions don't see the variables of `main`
+        assert!(code.contains("fn reset() {\n    let total = 0;\n}"));
+
+        // Without `mut`, the assignment would declare a variable of the loop
+        let source = "count = 0\nfor i in 0..10 {\n    count = count + i\n}\n";
+        let mut parser = Parser::new(Tokenizer::new(source.to_string()).tokenize());
+        parser.parse();
+        let diagnostic = &parser.diagnostics()[0];
+        assert_eq!((diagnostic.message.as_str(), diagnostic.primary.location.first_line), ("Cannot assign to `count` inside this block", 3));
+        assert_eq!(diagnostic.help.as_deref(), Some("declare `count` with `mut`"));
+    }
+
+    #[test]
+    fn test_continuation_lines() {
+        let corpus = [
+            ("total = items\n    .iter()\n    .map(|x| x * 2)\n    .sum::<i32>()\n", "let total = items.iter().map(|x| x * 2).sum::<i32>();"),
+            ("sum = 1 +\n    2 -\n    3\n", "let sum = 1 + 2 - 3;"),
+            ("ok = a > b\n    && b > 0\n    || a == 0\n", "let ok = a > b && b > 0 || a == 0;"),
+            ("big = count >\n    2\n", "let big = count > 2;"),
+            ("println(\"{} {}\",\n    total,\n    sum)\n", "println!(\"{} {}\", total, sum);"),
+            ("x = (1\n    + 2)\n", "let x = (1 + 2);"),
+            ("list = vec![\n    1,\n    2\n]\n", "let list = vec![1, 2];"),
+            ("name = user. // the user\n    name\n", "let name = user.name;"),
+            ("path = std::\n    env::args()\n", "let path = std::env::args();"),
+            ("r = 0..=\n    10\n", "let r = 0..=10;"),
+            ("p = a ..\n    b\n", "let p = a..b;"),
+        ];
+
+        for (source, expected) in corpus {
+            let code = emit(source);
+            assert!(code.contains(expected), "{:?} gave\n{}", source, code);
+        }
+
+        // Lines ending in a postfix operator or a generic are complete
+        let code = emit("mut count = 0\ncount++\nitems: Vec<i32>\ncount--\n");
+        assert!(code.contains("count += 1;\n"));
+        assert!(code.contains("let items: Vec<i32>;\n"));
+        assert!(code.contains("count -= 1;\n"));
+
+        let mut parser = Parser::new(Tokenizer::new("r = (0..=)\n".to_string()).tokenize());
+        parser.parse();
+        assert_eq!(parser.diagnostics()[0].message, "Inclusive range with no end");
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let code = emit(concat!(
+            "//! Geometry helpers\n",
+            "/// A point\n",
+            "#[derive(Debug)]\n",
+            "struct Point {\n",
+            "    /// Horizontal position\n",
+            "    x: i32\n",
+            "}\n",
+        ));
+
+        assert!(code.starts_with("//! Geometry helpers\n\n/// A point\n#[derive(Debug)]\nstruct Point {\n    /// Horizontal position\n    x: i32,\n}"));
+
+        // Doc comments before a statement are dropped with a warning
+        let tokens = Tokenizer::new("x = 0\n/// Only a comment\np = Point { x: 1 }\n".to_string()).tokenize();
+        let mut parser = Parser::new(tokens);
+        let code = Emitter::new().emit(&parser.parse());
+        assert!(!code.contains("Only a comment"));
+        let diagnostic = &parser.diagnostics()[0];
+        assert!(!diagnostic.is_error());
+        assert_eq!((diagnostic.message.as_str(), diagnostic.primary.location.first_line), ("Unused doc comment", 2));
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let code = emit(concat!(
+            "println(\"Hi $name, ${user.age + 1} {}\", 5)\n",
+            "label = \"$$${price} for {item}\"\n",
+            "plain = \"costs $$5\"\n",
+            "println(\"{:?} $x {name}\", 2, name = 3)\n",
+            "assert_eq!(total, 3, \"total of $name is $total\")\n",
+            "debug_assert!(ok, \"failed ${count + 1} times\")\n",
+            "unreachable!(\"state $state\")\n",
+        ));
+
+        assert!(code.contains("println!(\"Hi {1}, {2} {}\", 5, name, user.age + 1);"));
+        assert!(code.contains("let label = format!(\"${} for {{item}}\", price);"));
+        assert!(code.contains("let plain = \"costs $5\";"));
+        assert!(code.contains("println!(\"{:?} {1} {name}\", 2, x, name = 3);"));
+        assert!(code.contains("assert_eq!(total, 3, \"total of {} is {}\", name, total);"));
+        assert!(code.contains("debug_assert!(ok, \"failed {} times\", count + 1);"));
+        assert!(code.contains("unreachable!(\"state {}\", state);"));
+
+        let tokens = Tokenizer::new("s = \"a ${} b\"\n".to_string()).tokenize();
+        let mut parser = Parser::new(tokens);
+        parser.parse();
+        let diagnostic = &parser.diagnostics()[0];
+        assert_eq!(diagnostic.message, "Empty interpolation");
+        assert_eq!((diagnostic.primary.location.first_line, diagnostic.primary.location.first_column), (1, 5));
+    }
+}
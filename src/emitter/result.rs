@@ -0,0 +1,99 @@
+#[derive(Debug, Clone, Default)]
+pub struct Result {
+    pub value: String,
+    pub(crate) uses_json: bool,
+    pub(crate) uses_xml: bool,
+    pub(crate) uses_toml: bool,
+    pub(crate) uses_regex: bool,
+}
+
+impl Result {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn append(&mut self, value: &str) {
+        self.value.push_str(value);
+    }
+
+    pub fn add_required_import(&mut self, value: &str) {
+        self.value = "use ".to_owned() + value + " as " + "__" + value + "__" + "; // Imported by CForge for implementations\n" + &self.value;
+    }
+
+    pub fn add_data_type_aliases(&mut self) {
+        let mut aliases = String::new();
+        let mut imports = String::new();
+
+        if self.uses_json {
+            imports.push_str("use serde_json::{json, Value as JsonValue};\n");
+            aliases.push_str("// JsonValue type alias already imported from serde_json\n");
+        }
+
+        if self.uses_xml {
+            aliases.push_str("type XmlValue = String; // For now, XML is represented as String\n");
+        }
+
+        if self.uses_toml {
+            imports.push_str("use toml;\n");
+            aliases.push_str("type TomlValue = toml::Value;\n");
+        }
+
+        if !aliases.is_empty() {
+            let full_aliases = format!("// Native data type aliases for Copper\n{}\n{}\n", imports, aliases);
+            self.value = full_aliases + &self.value;
+        }
+    }
+
+    /// Puts the `//!` lines of the source file above everything else
+    pub fn add_module_docs(&mut self, docs: &[String]) {
+        if !docs.is_empty() {
+            self.value = format!("{}\n\n{}", docs.join("\n"), self.value);
+        }
+    }
+
+    pub fn mark_json_usage(&mut self) {
+        self.uses_json = true;
+    }
+
+    pub fn mark_xml_usage(&mut self) {
+        self.uses_xml = true;
+    }
+
+    pub fn mark_toml_usage(&mut self) {
+        self.uses_toml = true;
+    }
+
+    pub fn mark_regex_usage(&mut self) {
+        self.uses_regex = true;
+    }
+
+    pub fn get_required_dependencies(&self) -> Vec<String> {
+        let mut deps = Vec::new();
+
+        if self.uses_json {
+            deps.push("serde_json".to_string());
+        }
+
+        if self.uses_toml {
+            deps.push("toml".to_string());
+        }
+
+        if self.uses_regex {
+            deps.push("regex".to_string());
+        }
+
+        // XML não precisa de dependência externa por enquanto (usa String)
+
+        deps
+    }
+
+    pub fn has_required_import(&self, value: &str) -> bool {
+        self.value.contains(&("use ".to_owned() + value + " as " + "__" + value + "__" + ";"))
+    }
+
+    // Not run through rustfmt anymore, the source map relies on the emitted lines
+    pub fn get(&mut self) -> String {
+        self.value = self.value.trim().to_string();
+        self.value.clone() + "\n"
+    }
+}
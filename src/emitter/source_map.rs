@@ -0,0 +1,144 @@
+use std::cmp::Reverse;
+
+use serde::{Deserialize, Serialize};
+
+use crate::tokenizer::tokens::LocationData;
+
+// Markers wrapped around emitted code while the output is being built,
+// they are removed by `SourceMap::extract` once the output is complete
+pub(crate) const MARK_START: char = '\u{E000}';
+pub(crate) const MARK_ID_END: char = '\u{E001}';
+pub(crate) const MARK_END: char = '\u{E002}';
+
+/// Escapes the marker characters in Copper text copied into the output, such
+/// as string literals and comments, so `extract` keeps them as they are
+pub(crate) fn escape_markers(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, MARK_START | MARK_ID_END | MARK_END) {
+            escaped.push(MARK_ID_END);
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// A range of generated code and the Copper code it came from. Lines and
+/// columns are 1-based, columns count chars and `end` is exclusive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mapping {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub location: LocationData,
+}
+
+/// Maps positions in a generated Rust file back to its Copper source.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourceMap {
+    pub source: String,
+    pub mappings: Vec<Mapping>,
+}
+
+impl SourceMap {
+    /// Removes the markers from `code`, recording where each marked piece ended up
+    pub fn extract(code: &str, locations: &[LocationData]) -> (String, Self) {
+        let mut output = String::with_capacity(code.len());
+        let mut map = Self::default();
+        // `None` for a marker whose id can't be read, its code is kept unmapped
+        let mut open: Vec<(Option<usize>, (usize, usize))> = Vec::new();
+        let (mut line, mut column) = (1, 1);
+
+        let mut chars = code.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                MARK_START => {
+                    let id = chars.by_ref().take_while(|c| *c != MARK_ID_END).collect::<String>();
+                    let id = id.parse().ok().filter(|id| *id < locations.len());
+                    open.push((id, (line, column)));
+                },
+                MARK_END => {
+                    if let Some((Some(id), start)) = open.pop() {
+                        map.mappings.push(Mapping {
+                            start,
+                            end: (line, column),
+                            location: locations[id].clone(),
+                        });
+                    }
+                },
+                // Outside of a marker it escapes the character after it
+                MARK_ID_END => {
+                    if let Some(c) = chars.next() {
+                        output.push(c);
+                        column += 1;
+                    }
+                },
+                '\n' => {
+                    output.push(c);
+                    line += 1;
+                    column = 1;
+                },
+                _ => {
+                    output.push(c);
+                    column += 1;
+                },
+            }
+        }
+
+        (output, map)
+    }
+
+    /// Moves every mapping down, used when lines are added above the code
+    pub fn shift(&mut self, lines: usize) {
+        for mapping in &mut self.mappings {
+            mapping.start.0 += lines;
+            mapping.end.0 += lines;
+        }
+    }
+
+    /// Finds the Copper location of the innermost piece of code containing
+    /// the generated span, falling back to the closest one starting before it
+    pub fn lookup(&self, start: (usize, usize), end: (usize, usize)) -> Option<&LocationData> {
+        // Nested pieces start after or end before the ones around them
+        let innermost = |mapping: &&Mapping| (mapping.start, Reverse(mapping.end));
+
+        self.mappings
+            .iter()
+            .filter(|mapping| mapping.start <= start && start < mapping.end && end <= mapping.end)
+            .max_by_key(innermost)
+            .or_else(|| self.mappings.iter().filter(|mapping| mapping.start <= start).max_by_key(innermost))
+            .map(|mapping| &mapping.location)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{emitter::Emitter, parser::Parser, tokenizer::tokenizer::Tokenizer};
+
+    #[test]
+    fn test_lookup_points_at_copper_source() {
+        let source = "func void greet() {\n    println(\"hi\")\n}\n\ncount = 1\ntotal = count + missing\n";
+        let tokens = Tokenizer::new(source.to_string()).tokenize();
+        let program = Parser::new(tokens).parse();
+        let mut emitter = Emitter::new();
+        let code = emitter.emit(&program);
+
+        let (index, text) = code.lines().enumerate().find(|(_, line)| line.contains("missing")).unwrap();
+        let column = text.chars().count() - "missing;".len() + 1;
+        let location = emitter.source_map().lookup((index + 1, column), (index + 1, column + 7)).unwrap();
+
+        assert_eq!((location.first_line, location.first_column, location.last_column), (6, 17, 23));
+    }
+
+    #[test]
+    fn test_keeps_marker_characters_of_the_source() {
+        let source = "/// a \u{E001} doc\nfunc void greet() {}\ns = \"\u{E000}\u{E002}\"\n";
+        let tokens = Tokenizer::new(source.to_string()).tokenize();
+        let code = Emitter::new().emit(&Parser::new(tokens).parse());
+
+        assert!(code.contains("/// a \u{E001} doc"));
+        assert!(code.contains("let s = \"\u{E000}\u{E002}\";"));
+
+        let (code, map) = super::SourceMap::extract("a\u{E000}x\u{E001}b\u{E002}c\u{E002}", &[]);
+        assert_eq!((code.as_str(), map.mappings.len()), ("abc", 0));
+    }
+}
@@ -1,6 +1,9 @@
 pub mod tokenizer;
 pub mod utils;
 pub mod parser;
+pub mod emitter;
+pub mod diagnostics;
+pub mod checker;
 pub mod cforge;
 
 use std::{env, fs, path};
@@ -37,6 +40,7 @@ static BASE_CMD: Lazy<ClapCommand> = Lazy::new(|| {
             .short('r')
             .long("release")
             .action(clap::ArgAction::SetTrue)
+            .global(true)
             .help("Compile in release mode"))
         .arg(Arg::new("version")
             .short('v')
@@ -47,6 +51,7 @@ static BASE_CMD: Lazy<ClapCommand> = Lazy::new(|| {
             .long("target")
             .num_args(1)
             .value_name("TARGET")
+            .global(true)
             .help("Cross-compile target (friendly names: windows, mac, linux or full rust triple)")
         )
         .arg(Arg::new("verbose")
@@ -56,20 +61,68 @@ static BASE_CMD: Lazy<ClapCommand> = Lazy::new(|| {
             .help("Enable verbose output"))
         .subcommand(ClapCommand::new("run")
             .about("Compile and run the project")
-            .args([
-                Arg::new("input")
-                    .help("Input files to compile")
-                    .value_name("FILE")
-                    .required(false)
-                    .index(1),
-                Arg::new("output")
-                    .short('o')
-                    .long("output")
-                    .help("Output directory for compiled files"),
-            ])
+            .args(project_args())
+            .arg(Arg::new("args")
+                .index(2)
+                .num_args(0..)
+                .last(true)
+                .allow_hyphen_values(true)
+                .value_name("ARGS")
+                .help("Arguments passed to the program, after `--`"))
+        )
+        .subcommand(ClapCommand::new("check")
+            .about("Compile the project and check the generated code without building it")
+            .args(project_args())
+        )
+        .subcommand(ClapCommand::new("build")
+            .about("Compile the project and build an optimized binary into the output directory")
+            .args(project_args())
+            .arg(Arg::new("profile")
+                .long("profile")
+                .value_name("PROFILE")
+                .conflicts_with("release")
+                .help("Build with a profile declared in properties.kson"))
+        )
+        .subcommand(ClapCommand::new("syntax")
+            .about("Print the lossless syntax tree of a Copper file, for editor and formatter tooling")
+            .arg(Arg::new("file")
+                .value_name("FILE")
+                .required(true)
+                .index(1)
+                .help("Copper file to read"))
+        )
+        .subcommand(ClapCommand::new("watch")
+            .about("Compile the project again whenever a file changes")
+            .args(project_args())
+            .arg(Arg::new("run")
+                .long("run")
+                .action(clap::ArgAction::SetTrue)
+                .help("Build and restart the program after every change"))
+            .arg(Arg::new("args")
+                .index(2)
+                .num_args(0..)
+                .last(true)
+                .allow_hyphen_values(true)
+                .value_name("ARGS")
+                .help("Arguments passed to the program, after `--`"))
         )
 });
 
+// Arguments shared by the subcommands working on a whole project
+fn project_args() -> [Arg; 2] {
+    [
+        Arg::new("input")
+            .help("Input files to compile")
+            .value_name("FILE")
+            .required(false)
+            .index(1),
+        Arg::new("output")
+            .short('o')
+            .long("output")
+            .help("Output directory for compiled files"),
+    ]
+}
+
 fn is_command_available(command: &str) -> bool {
     ProcessCommand::new(command)
         .arg("--version")
@@ -157,13 +210,25 @@ fn parse_commands() -> ParsedCommands {
     parsed_args.add_command(cmd);
     
     // Handle subcommands
-    if let Some(("run", run_matches)) = matches.subcommand() {
-        // Process run subcommand
-        let mut cmd = ParsedCommand::new("run".to_string(), vec![]);
+    if let Some((name @ ("run" | "check" | "build" | "watch"), run_matches)) = matches.subcommand() {
+        // Process run, check, build and watch subcommands
+        // The profile to build with, or the arguments of the program to run
+        let args = match name {
+            "build" => run_matches.get_one::<String>("profile").cloned().into_iter().collect(),
+            "run" | "watch" => run_matches.get_many::<String>("args").unwrap_or_default().cloned().collect(),
+            _ => vec![],
+        };
+        let mut cmd = ParsedCommand::new(name.to_string(), args);
         cmd.set_valid(true);
         parsed_args.add_command(cmd);
+
+        if name == "watch" {
+            let mut cmd = ParsedCommand::new("restart".to_string(), vec![]);
+            cmd.set_valid(run_matches.get_flag("run"));
+            parsed_args.add_command(cmd);
+        }
         
-        // Override input if provided in run subcommand
+        // Override input if provided in the subcommand
         if let Some(file_path) = run_matches.get_one::<String>("input") {
             let path = path::Path::new(file_path);
             
@@ -197,7 +262,7 @@ fn parse_commands() -> ParsedCommands {
             cmd.set_valid(true);
             parsed_args.update_or_add_command(cmd);
         } else {
-            // If no file specified for run, try using main.crs as default
+            // If no file specified, try using main.crs as default
             let default_file = "main.crs";
             if path::Path::new(default_file).exists() {
                 let mut cmd = ParsedCommand::new("input".to_string(), vec![default_file.to_string()]);
@@ -211,7 +276,7 @@ fn parse_commands() -> ParsedCommands {
             }
         }
         
-        // Override output if provided in run subcommand
+        // Override output if provided in the subcommand
         if let Some(output_path) = run_matches.get_one::<String>("output") {
             let output_dir = output_path.to_string();
             
@@ -260,6 +325,26 @@ async fn main() {
         return;
     }
 
+    if let Some(("syntax", syntax)) = BASE_CMD.clone().get_matches().subcommand() {
+        let file = syntax.get_one::<String>("file").unwrap();
+        match fs::read_to_string(file) {
+            Ok(source) => {
+                let tree = tokenizer::syntax::parse(&source);
+                print!("{}", tree);
+                // Tools rely on the tree giving back the file as it is
+                if tree.text() != source {
+                    eprintln!("Error: the syntax tree of '{}' doesn't give back its source", file);
+                    std::process::exit(1);
+                }
+            },
+            Err(err) => {
+                eprintln!("Error: could not read '{}': {}", file, err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     if commands.get_command("verbose").unwrap().is_valid {
         env::set_var("CFORGE_VERBOSE", "1");
     } else {
@@ -308,8 +393,8 @@ async fn main() {
     }
 
     if commands.get_command("compile").unwrap().is_valid {
-        let detected_dependencies = cforge::compile(files.clone(), input_dir.clone(), output_dir.clone());
-        cforge::generate_toml(detected_dependencies).await;
+        let detected_dependencies = cforge::compile(files.clone(), input_dir.clone(), output_dir.clone()).await;
+        cforge::generate_toml(detected_dependencies, &output_dir).await;
     }
     // If the user provided --target, expose it to downstream build runner via environment variable
     if let Some(t) = BASE_CMD.clone().get_matches().get_one::<String>("target") {
@@ -325,12 +410,48 @@ async fn main() {
         std::env::set_var("CFORGE_TARGET", target_triple);
     }
     
+    // Handle check subcommand
+    if commands.get_command("check").is_some() && commands.get_command("check").unwrap().is_valid {
+        let detected_dependencies = cforge::compile(files.clone(), input_dir.clone(), output_dir.clone()).await;
+        cforge::generate_toml(detected_dependencies, &output_dir).await;
+        cforge::check(&output_dir);
+    }
+
+    // Handle build subcommand
+    if let Some(build) = commands.get_command("build").filter(|build| build.is_valid) {
+        let profile = match build.args.first() {
+            Some(profile) => profile.clone(),
+            None if commands.get_command("release").unwrap().is_valid => "release".to_string(),
+            None => "dev".to_string(),
+        };
+
+        let detected_dependencies = cforge::compile(files.clone(), input_dir.clone(), output_dir.clone()).await;
+        cforge::generate_toml(detected_dependencies, &output_dir).await;
+        cforge::build(&output_dir, &profile);
+    }
+
+    // Handle watch subcommand
+    if let Some(watch) = commands.get_command("watch").filter(|watch| watch.is_valid) {
+        let profile = if commands.get_command("release").unwrap().is_valid { "release" } else { "dev" };
+
+        cforge::watch::watch(cforge::watch::WatchOptions {
+            files: files.clone(),
+            input_dir: input_dir.clone(),
+            output_dir: output_dir.clone(),
+            restart: commands.get_command("restart").is_some_and(|restart| restart.is_valid),
+            profile: profile.to_string(),
+            args: watch.args.clone(),
+        }).await;
+    }
+
     // Handle run subcommand
-    if commands.get_command("run").is_some() && commands.get_command("run").unwrap().is_valid {
-        let detected_dependencies = cforge::compile(files, input_dir, output_dir.clone());
-        cforge::generate_toml(detected_dependencies).await;
-        cforge::run();
+    if let Some(run) = commands.get_command("run").filter(|run| run.is_valid) {
+        let profile = if commands.get_command("release").unwrap().is_valid { "release" } else { "dev" };
+
+        let detected_dependencies = cforge::compile(files, input_dir, output_dir.clone()).await;
+        cforge::generate_toml(detected_dependencies, &output_dir).await;
+        cforge::run(&output_dir, profile, &run.args).await;
     }
     
-    println!("");
+    println!();
 }
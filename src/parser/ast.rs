@@ -0,0 +1,620 @@
+use std::collections::HashMap;
+
+use crate::tokenizer::tokens::LocationData;
+
+/// A parsed Copper source file.
+///
+/// Top-level statements keep their source order; the emitter decides which
+/// of them become module items and which end up inside the generated `main`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub body: Vec<Stmt>,
+    /// `//!` doc comment lines of the file
+    pub docs: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub location: LocationData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    /// `x = value`, `mut x = value`, `x: int = value` or Rust's `let`
+    Let {
+        pattern: Pattern,
+        ty: Option<Type>,
+        value: Option<Expr>,
+    },
+    /// An expression statement. `semicolon` is false for the trailing
+    /// expression of a block that produces the block value.
+    Expr { expr: Expr, semicolon: bool },
+    Item(Item),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub location: LocationData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub kind: ItemKind,
+    /// `#[...]` attributes and `///` doc comment lines, as written
+    pub attributes: Vec<String>,
+    pub location: LocationData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemKind {
+    Import(Import),
+    /// Plain Rust `use` declaration, kept as written
+    Use(String),
+    Function(Function),
+    Class(Class),
+    Struct(Struct),
+    Enum(Enum),
+    Trait(Trait),
+    Impl(Impl),
+    Const(Const),
+    /// Rust items Copper has no syntax of its own for yet (`trait`, `mod`,
+    /// `type`, `macro_rules!`...), passed through as written
+    Verbatim(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    /// Module path segments, `std.io` => `["std", "io"]`. Relative imports
+    /// keep their `..` segments, `../shared/util` => `["..", "shared", "util"]`
+    pub path: Vec<String>,
+    pub kind: ImportKind,
+    /// Imports another Copper file, `from ./models/user`
+    pub relative: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportKind {
+    /// `import alias from path`
+    Alias(String),
+    /// `import { a, b } from path`
+    List(Vec<String>),
+    /// `import * from path`
+    All,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    /// `///` doc comment lines, as written
+    pub docs: Vec<String>,
+    pub name: String,
+    pub is_pub: bool,
+    pub generics: Generics,
+    pub params: Vec<Param>,
+    pub return_type: Option<Type>,
+    /// `None` for a trait method without a default body
+    pub body: Option<Block>,
+    /// Declared with `func` rather than Rust's `fn`
+    pub is_copper: bool,
+    pub location: LocationData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Param {
+    /// `self`, `&self`, `mut self`, `&mut self`
+    SelfParam { reference: bool, mutable: bool },
+    Named {
+        name: String,
+        mutable: bool,
+        ty: Type,
+        location: LocationData,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Class {
+    pub name: String,
+    pub generics: Generics,
+    /// Parent class, embedded as a field the class derefs to
+    pub extends: Option<Type>,
+    /// Traits listed after `implements`
+    pub implements: Vec<Type>,
+    pub constants: Vec<Const>,
+    pub fields: Vec<Field>,
+    pub constructors: Vec<Constructor>,
+    pub methods: Vec<Function>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constructor {
+    pub docs: Vec<String>,
+    /// `new` for `Name(...)`, `guest` for `Name.guest(...)`
+    pub name: String,
+    pub is_pub: bool,
+    pub params: Vec<Param>,
+    pub body: Block,
+    pub location: LocationData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub docs: Vec<String>,
+    pub name: String,
+    pub is_pub: bool,
+    pub ty: Type,
+    /// Class fields marked `#[get]` or `#[set]` get accessor methods
+    pub getter: bool,
+    pub setter: bool,
+    pub location: LocationData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Struct {
+    pub name: String,
+    pub is_pub: bool,
+    pub generics: Generics,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enum {
+    pub name: String,
+    pub is_pub: bool,
+    pub generics: Generics,
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub docs: Vec<String>,
+    pub name: String,
+    pub kind: VariantKind,
+    /// `Red = 1`
+    pub discriminant: Option<Expr>,
+    pub location: LocationData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariantKind {
+    Unit,
+    /// `Circle(float)`
+    Tuple(Vec<Type>),
+    /// `Rect { width: float, height: float }`
+    Struct(Vec<Field>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trait {
+    pub name: String,
+    pub is_pub: bool,
+    pub generics: Generics,
+    /// Supertraits, `Display + Clone`
+    pub bounds: Vec<Type>,
+    pub methods: Vec<Function>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Impl {
+    pub generics: Generics,
+    pub trait_name: Option<Type>,
+    pub target: Type,
+    pub methods: Vec<Function>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Const {
+    pub name: String,
+    pub is_pub: bool,
+    pub is_static: bool,
+    pub ty: Type,
+    pub value: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Type {
+    pub kind: TypeKind,
+    pub location: LocationData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeKind {
+    /// `int`, `Vec<string>`, `std::io::Result<()>`
+    Path { segments: Vec<String>, args: Vec<Type> },
+    Reference {
+        lifetime: Option<String>,
+        mutable: bool,
+        inner: Box<Type>,
+    },
+    Tuple(Vec<Type>),
+    /// `[T]` when `len` is `None`, `[T; N]` otherwise
+    Array { inner: Box<Type>, len: Option<Box<Expr>> },
+    /// Copper's `T?`
+    Optional(Box<Type>),
+    /// `_`
+    Infer,
+    /// `dyn Trait`, `impl Trait`, `fn(A) -> B`, passed through as written
+    Verbatim(String),
+    /// `Item = T` in the arguments of a trait, `Iterator<Item = T>`
+    Binding { name: String, ty: Box<Type> },
+}
+
+/// Generic parameters of an item and its where clause
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Generics {
+    pub params: Vec<GenericParam>,
+    /// `where T: Display, U: Clone`
+    pub predicates: Vec<WherePredicate>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenericParam {
+    /// `'a` or `'a: 'b`
+    Lifetime { name: String, bounds: Vec<Type> },
+    /// `T`, `T: Display + Clone`, `T = int`
+    Type { name: String, bounds: Vec<Type>, default: Option<Type> },
+    /// `const N: usize`
+    Const { name: String, ty: Type },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WherePredicate {
+    pub ty: Type,
+    pub bounds: Vec<Type>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub location: LocationData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternKind {
+    Ident { name: String, mutable: bool },
+    /// Any other pattern, passed through as written
+    Verbatim(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub location: LocationData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Literal(Literal),
+    /// String with `$name` or `${expression}` parts, `"Hi $name"`
+    Interpolated(Vec<StringPart>),
+    Path(Path),
+    Unary {
+        op: UnaryOp,
+        expr: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    /// `target = value` or a compound assignment such as `target += value`
+    Assign {
+        op: Option<BinaryOp>,
+        target: Box<Expr>,
+        value: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
+    MethodCall {
+        receiver: Box<Expr>,
+        method: String,
+        generics: Vec<Type>,
+        args: Vec<Expr>,
+    },
+    Field {
+        base: Box<Expr>,
+        name: String,
+    },
+    Index {
+        base: Box<Expr>,
+        index: Box<Expr>,
+    },
+    Macro {
+        path: Path,
+        delimiter: Delimiter,
+        args: MacroArgs,
+    },
+    Cast {
+        expr: Box<Expr>,
+        ty: Type,
+    },
+    /// `expr?`
+    Try(Box<Expr>),
+    /// `let pattern = value` in `if let` / `while let` conditions
+    Let {
+        pattern: Pattern,
+        value: Box<Expr>,
+    },
+    Range {
+        start: Option<Box<Expr>>,
+        end: Option<Box<Expr>>,
+        inclusive: bool,
+    },
+    Closure {
+        is_move: bool,
+        params: Vec<ClosureParam>,
+        return_type: Option<Type>,
+        body: Box<Expr>,
+    },
+    Block(Block),
+    Unsafe(Block),
+    If {
+        cond: Box<Expr>,
+        then: Block,
+        otherwise: Option<Box<Expr>>,
+    },
+    While {
+        label: Option<String>,
+        cond: Box<Expr>,
+        body: Block,
+    },
+    Loop {
+        label: Option<String>,
+        body: Block,
+    },
+    For {
+        label: Option<String>,
+        pattern: Pattern,
+        iter: Box<Expr>,
+        body: Block,
+    },
+    Match {
+        expr: Box<Expr>,
+        arms: Vec<MatchArm>,
+    },
+    Return(Option<Box<Expr>>),
+    Break {
+        label: Option<String>,
+        value: Option<Box<Expr>>,
+    },
+    Continue(Option<String>),
+    StructLiteral {
+        path: Path,
+        fields: Vec<FieldInit>,
+        base: Option<Box<Expr>>,
+    },
+    Tuple(Vec<Expr>),
+    Array(Vec<Expr>),
+    /// `[1, 2]` declared as a `list`
+    List(Vec<Expr>),
+    /// `[1, 2]` declared as a `set`
+    Set(Vec<Expr>),
+    /// `{ "a": 1 }` declared as a `map`
+    Map(Vec<(Expr, Expr)>),
+    /// `[value; len]`
+    ArrayRepeat {
+        value: Box<Expr>,
+        len: Box<Expr>,
+    },
+    Paren(Box<Expr>),
+    /// Copper's `x = { "key": value }` / `x = [ ... ]` literals
+    Json(JsonValue),
+    /// `/pattern/flags`, the pattern with its `\/` escapes removed
+    Regex {
+        pattern: String,
+        flags: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    /// Numeric literal as written
+    Number(String),
+    /// String literal as written, quotes and `b`/`r#` prefixes included
+    String(String),
+    /// Char literal as written, `'a'` or `b'a'`
+    Char(String),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    /// Text as written, escapes included
+    Text(String),
+    Expr(Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    pub segments: Vec<PathSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathSegment {
+    pub name: String,
+    /// Turbofish arguments, `collect::<Vec<_>>`
+    pub generics: Vec<Type>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    Deref,
+    Ref,
+    RefMut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    And,
+    Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    Paren,
+    Bracket,
+    Brace,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MacroArgs {
+    /// Comma separated expressions, `println!("{}", x)`
+    Exprs(Vec<Expr>),
+    /// Anything else, passed through as written
+    Verbatim(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosureParam {
+    pub pattern: Pattern,
+    pub ty: Option<Type>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+    pub location: LocationData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldInit {
+    pub name: String,
+    /// `None` for the shorthand `Point { x, y }`
+    pub value: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    /// Keys are kept as written, quotes included
+    Object(Vec<(String, JsonValue)>),
+    Array(Vec<JsonValue>),
+    Value(Box<Expr>),
+}
+
+impl Program {
+    /// Method names of the traits the program declares, by trait name
+    pub fn traits(&self) -> HashMap<String, Vec<String>> {
+        self.body
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                StmtKind::Item(Item { kind: ItemKind::Trait(declared), .. }) => {
+                    Some((declared.name.clone(), declared.methods.iter().map(|method| method.name.clone()).collect()))
+                },
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Path {
+    pub fn single(name: &str) -> Self {
+        Self {
+            segments: vec![PathSegment {
+                name: name.to_string(),
+                generics: vec![],
+            }],
+        }
+    }
+
+    /// Returns the identifier if the path is a single segment without generics
+    pub fn as_ident(&self) -> Option<&str> {
+        match self.segments.as_slice() {
+            [segment] if segment.generics.is_empty() => Some(&segment.name),
+            _ => None,
+        }
+    }
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, location: LocationData) -> Self {
+        Self { kind, location }
+    }
+
+    /// Block-like expressions don't need a trailing `;` when used as statements
+    pub fn is_block_like(&self) -> bool {
+        matches!(
+            self.kind,
+            ExprKind::Block(_)
+                | ExprKind::Unsafe(_)
+                | ExprKind::If { .. }
+                | ExprKind::While { .. }
+                | ExprKind::Loop { .. }
+                | ExprKind::For { .. }
+                | ExprKind::Match { .. }
+        )
+    }
+}
+
+impl BinaryOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "^",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+        }
+    }
+
+    /// Binding power, higher binds tighter
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 3,
+            BinaryOp::BitOr => 4,
+            BinaryOp::BitXor => 5,
+            BinaryOp::BitAnd => 6,
+            BinaryOp::Shl | BinaryOp::Shr => 7,
+            BinaryOp::Add | BinaryOp::Sub => 8,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 9,
+        }
+    }
+}
+
+impl UnaryOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "!",
+            UnaryOp::Deref => "*",
+            UnaryOp::Ref => "&",
+            UnaryOp::RefMut => "&mut ",
+        }
+    }
+}
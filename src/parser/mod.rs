@@ -1,74 +1,147 @@
-use std::vec;
-use crate::tokenizer::{kind::TokenKind, tokens::Token};
-pub mod result;
+use crate::{diagnostics::Diagnostic, emitter::format_args, tokenizer::{kind::TokenKind, tokens::{Data, Interpolation, LocationData, Token}}};
+pub mod ast;
 pub mod utils;
 pub mod scope;
 pub mod scope_manager;
 
-use crate::utils::Consumed;
-use crate::{ConsumeVar, ConsumedTrait};
-use utils::convert_type;
-use result::Result;
+use std::collections::HashMap;
 
-const COPPER_OPERATORS: [(&str, &str); 2] = [
-    ("++", "+= 1"),
-    ("--", "-= 1"),
+use ast::*;
+use scope::Parent;
+use scope_manager::Scopes;
+use utils::{check_regex, integer_overflows, integer_range, is_float_literal, number_suffix, split_number};
+
+// Macros that can be called without the `!` suffix
+const RUST_MACROS: [&str; 1] = [
+    "println",
+];
+
+// Macros taking a format string, and its position in their arguments
+const FORMAT_MACROS: [(&str, usize); 17] = [
+    ("format", 0),
+    ("print", 0),
+    ("println", 0),
+    ("eprint", 0),
+    ("eprintln", 0),
+    ("panic", 0),
+    ("unreachable", 0),
+    ("todo", 0),
+    ("unimplemented", 0),
+    ("write", 1),
+    ("writeln", 1),
+    ("assert", 1),
+    ("debug_assert", 1),
+    ("assert_eq", 2),
+    ("assert_ne", 2),
+    ("debug_assert_eq", 2),
+    ("debug_assert_ne", 2),
+];
+
+// Words that can never be used as identifiers. `import` and `from` are only
+// keywords in imports, so `String::from` stays a path
+const RESERVED_WORDS: &[&str] = &[
+    "if", "else", "while", "for", "loop", "match", "return", "break", "continue",
+    "let", "mut", "fn", "func", "class", "struct", "impl", "trait", "enum",
+    "use", "pub", "public", "const", "static", "type", "mod",
+    "unsafe", "async", "move", "as", "in", "where", "true", "false", "extern",
+    "dyn", "ref",
 ];
 
-const RUST_MACROS: [(&str, &str); 1] = [
-    ("println", "println!"),
+// Items Copper has no syntax of its own for, passed through to Rust as written
+const VERBATIM_ITEMS: [&str; 4] = ["mod", "type", "extern", "union"];
+
+const COMPOUND_ASSIGNMENTS: [(&str, BinaryOp); 9] = [
+    ("+=", BinaryOp::Add),
+    ("-=", BinaryOp::Sub),
+    ("*=", BinaryOp::Mul),
+    ("/=", BinaryOp::Div),
+    ("%=", BinaryOp::Rem),
+    ("&=", BinaryOp::BitAnd),
+    ("|=", BinaryOp::BitOr),
+    ("^=", BinaryOp::BitXor),
+    ("<<=", BinaryOp::Shl),
 ];
 
-#[derive(Debug)]
-pub enum AppendMode {
-    Append,
-    AppendToMainFunction,
-    ForceAppend,
-    FFAppend,
-    AppendWithSpace,
-    AppendToMainFunctionWithSpace,
-    ForceAppendWithSpace,
-    FFAppendWithSpace,
+pub type ParseResult<T> = std::result::Result<T, Box<Diagnostic>>;
+
+enum ClassMember {
+    Const(Const),
+    Field(Field),
+    Constructor(Constructor),
+    Method(Function),
 }
 
 pub struct Parser {
     tokens: Vec<Token>,
+    // Whether each token was preceded by whitespace in the source
+    spaced: Vec<bool>,
     current: usize,
-    result: Result,
-    eof: bool,
-    function_start: bool,
-    seen_import: bool,
-    current_import_vars: Vec<String>,
-    is_import_list: bool,
-    is_inside_class: bool,
-    current_class: Option<String>,
-    is_inside_struct: bool,
-    is_inside_impl: bool,
-    current_struct: Option<String>,
-    current_impl_target: Option<String>,
-    uses_data_types: bool,
+    // Struct literals are not allowed in `if`/`while`/`match`/`for` heads
+    no_struct_literal: bool,
+    // Number of open blocks, a stray `}` is only skipped at the top level
+    depth: usize,
+    // The number literal being parsed follows a unary `-`
+    negative_literal: bool,
+    // `///` lines and where they are, by the index of the token after them
+    docs: HashMap<usize, (Vec<String>, LocationData)>,
+    // `//!` lines, they document the whole file
+    module_docs: Vec<String>,
+    // Variables declared so far, to tell declarations from assignments
+    scopes: Scopes,
+    // Bare `super` expressions in the class member being parsed, and whether
+    // each one is called
+    super_uses: Option<Vec<(LocationData, bool)>>,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
+        let mut filtered = Vec::with_capacity(tokens.len());
+        let mut spaced = Vec::with_capacity(tokens.len());
+        let mut whitespace = false;
+        let mut docs = HashMap::new();
+        let mut module_docs = Vec::new();
+        let mut pending = Vec::new();
+        let mut pending_location: Option<LocationData> = None;
+
+        for token in tokens {
+            match token.kind {
+                TokenKind::Whitespace => whitespace = true,
+                TokenKind::Unknown if token.value.trim().is_empty() => whitespace = true,
+                TokenKind::Comment => {},
+                TokenKind::DocComment if token.value.starts_with("//!") => module_docs.push(token.value.trim_end().to_string()),
+                TokenKind::DocComment => {
+                    pending.push(token.value.trim_end().to_string());
+                    let location = token.location_data.clone().unwrap_or_default();
+                    pending_location = Some(match pending_location {
+                        Some(first) => first.to(&location),
+                        None => location,
+                    });
+                },
+                _ => {
+                    if !pending.is_empty() && token.kind != TokenKind::Newline {
+                        let location = pending_location.take().unwrap_or_default();
+                        docs.insert(filtered.len(), (std::mem::take(&mut pending), location));
+                    }
+                    filtered.push(token);
+                    spaced.push(whitespace);
+                    whitespace = false;
+                }
+            }
+        }
+
         Self {
-            tokens: tokens.into_iter()
-                .filter(|t| t.kind != TokenKind::Whitespace && t.kind != TokenKind::Comment)
-                .collect(),
+            tokens: filtered,
+            spaced,
             current: 0,
-            result: Result::new(),
-            eof: false,
-            function_start: false,
-            seen_import: false,
-            current_import_vars: vec![],
-            is_import_list: false,
-            is_inside_class: false,
-            current_class: None,
-            is_inside_struct: false,
-            is_inside_impl: false,
-            current_struct: None,
-            current_impl_target: None,
-            uses_data_types: false,
+            no_struct_literal: false,
+            depth: 0,
+            negative_literal: false,
+            docs,
+            module_docs,
+            scopes: Scopes::new(),
+            super_uses: None,
+            diagnostics: Vec::new(),
         }
     }
 
@@ -81,7 +154,7 @@ impl Parser {
     }
 
     pub fn peek_kind(&self) -> Option<TokenKind> {
-        self.peek().map(|t| t.kind.clone())
+        self.peek().map(|t| t.kind)
     }
 
     pub fn peek_value(&self) -> Option<String> {
@@ -93,1443 +166,2977 @@ impl Parser {
     }
 
     pub fn next(&mut self) {
-        self.current += 1;
-        self.check_eof();
+        if self.current < self.tokens.len() {
+            self.current += 1;
+        }
+    }
+
+    pub fn value(&self) -> String {
+        self.current().map_or(String::new(), |t| t.value.clone())
+    }
+
+    pub fn kind(&self) -> TokenKind {
+        self.current().map_or(TokenKind::Eof, |t| t.kind)
+    }
+
+    pub fn is_eof(&self) -> bool {
+        self.kind() == TokenKind::Eof
+    }
+
+    fn is(&self, value: &str) -> bool {
+        self.is_at(self.current, value)
+    }
+
+    fn is_at(&self, index: usize, value: &str) -> bool {
+        self.select(index).is_some_and(|t| !is_literal(t.kind) && t.value == value)
+    }
+
+    fn peek_is(&self, value: &str) -> bool {
+        self.is_at(self.current + 1, value)
+    }
+
+    fn is_newline(&self) -> bool {
+        self.kind() == TokenKind::Newline
+    }
+
+    fn is_ident(&self) -> bool {
+        self.current().is_some_and(is_ident)
+    }
+
+    // Current token is the end of a statement
+    fn is_terminator(&self) -> bool {
+        matches!(self.kind(), TokenKind::Newline | TokenKind::Semicolon | TokenKind::Eof) || self.is("}")
     }
 
-    pub fn check_eof(&mut self) {
-        if self.current >= self.tokens.len() {
-            self.eof = true;
+    fn skip_newlines(&mut self) {
+        while self.is_newline() {
+            self.next();
         }
     }
 
-    pub fn append(&mut self, value: &str, mode: AppendMode) {
-        match mode {
-            AppendMode::Append => self.result.append(value, false),
-            AppendMode::AppendToMainFunction => self.result.append_to_main_function(value, false),
-            AppendMode::ForceAppend => self.result.force_append(value, false),
-            AppendMode::FFAppend => self.result.ff_append(value, false),
-            AppendMode::AppendWithSpace => self.result.append(value, true),
-            AppendMode::AppendToMainFunctionWithSpace => self.result.append_to_main_function(value, true),
-            AppendMode::ForceAppendWithSpace => self.result.force_append(value, true),
-            AppendMode::FFAppendWithSpace => self.result.ff_append(value, true),
+    fn skip_separators(&mut self) {
+        while self.is_newline() || self.kind() == TokenKind::Semicolon {
+            self.next();
         }
     }
 
-    pub fn value(&self) -> String {
-        self.current().map_or(String::new(), |t| t.value.clone())
+    // Index of the first token at or after `index` that isn't a newline
+    fn skip_newlines_from(&self, mut index: usize) -> usize {
+        while self.select(index).is_some_and(|t| t.kind == TokenKind::Newline) {
+            index += 1;
+        }
+        index
     }
 
-    pub fn kind(&self) -> TokenKind {
-        self.current().map_or(TokenKind::Eof, |t| t.kind.clone())
+    pub fn location(&self) -> LocationData {
+        self.current()
+            .or(self.tokens.last())
+            .and_then(|t| t.location_data.clone())
+            .unwrap_or_default()
     }
 
-    pub fn parse_mut(&mut self) -> Consumed {
-        let mut consumed = 0;
-        if self.value() == "mut" && self.peek_kind() == Some(TokenKind::Identifier) {
-            if let Some(next) = self.select(self.current + 3) {
-                if next.value != "=" {
-                    consumed += 2;
-                    self.append(&format!("let mut {}", self.peek_value().unwrap_or_default()), AppendMode::AppendWithSpace);
-                }
-            }
+    fn previous_location(&self) -> LocationData {
+        self.current
+            .checked_sub(1)
+            .and_then(|index| self.select(index))
+            .and_then(|t| t.location_data.clone())
+            .unwrap_or_else(|| self.location())
+    }
+
+    fn span(&self, start: &LocationData) -> LocationData {
+        start.to(&self.previous_location())
+    }
+
+    fn describe_current(&self) -> String {
+        match self.kind() {
+            TokenKind::Eof => "end of file".to_string(),
+            TokenKind::Newline => "end of line".to_string(),
+            _ => format!("`{}`", self.value()),
         }
-        Consumed::consume(consumed)
-    }
-
-    pub fn parse_var(&mut self) -> Consumed {
-        let mut consumed = 0;
-        if self.kind() == TokenKind::Identifier && self.peek_value() == Some("=".to_string()) {
-            if let Some(var_value) = self.select(self.current + 2) {
-                if var_value.value != "=" {
-                    let var_name = self.value();
-                    
-                    // Check if next token (after =) is { or [
-                if var_value.kind == TokenKind::BraceStart || var_value.kind == TokenKind::BracketStart {
-                        // Debug: show what type of token was detected
-                        // JSON detected var_value.kind, var_value.value);
-                        
-                        // This is a JSON object or array!
-                        consumed += 3; // identifier + = + { or [
-                        
-                        let is_array = var_value.kind == TokenKind::BracketStart;
-                        
-                        // Collect all tokens until matching } or ]
-                        let mut json_tokens = Vec::new();
-                        let mut brace_count = if is_array { 0 } else { 1 };
-                        let mut bracket_count = if is_array { 1 } else { 0 };
-                        let mut current_idx = self.current + 3;
-                        
-                        while (brace_count > 0 || bracket_count > 0) && current_idx < self.tokens.len() {
-                            if let Some(token) = self.select(current_idx) {
-                                // Process JSON token
-                                match token.kind {
-                                    TokenKind::BraceStart => {
-                                        brace_count += 1;
-                                        json_tokens.push(token.clone());
-                                    },
-                                    TokenKind::BraceEnd => {
-                                        json_tokens.push(token.clone());
-                                        brace_count -= 1;
-                                    },
-                                    TokenKind::BracketStart => {
-                                        bracket_count += 1;
-                                        json_tokens.push(token.clone());
-                                    },
-                                    TokenKind::BracketEnd => {
-                                        json_tokens.push(token.clone());
-                                        bracket_count -= 1;
-                                    },
-                                    TokenKind::Symbol => {
-                                        // Special case: split symbols containing brackets/braces
-                                        if token.value.contains(']') || token.value.contains('}') {
-                                            for ch in token.value.chars() {
-                                                match ch {
-                                                    ']' => {
-                                                        let mut bracket_token = token.clone();
-                                                        bracket_token.kind = TokenKind::BracketEnd;
-                                                        bracket_token.value = "]".to_string();
-                                                        json_tokens.push(bracket_token);
-                                                        bracket_count -= 1;
-                                                    },
-                                                    '}' => {
-                                                        let mut brace_token = token.clone();
-                                                        brace_token.kind = TokenKind::BraceEnd;
-                                                        brace_token.value = "}".to_string();
-                                                        json_tokens.push(brace_token);
-                                                        brace_count -= 1;
-                                                    },
-                                                    _ => {
-                                                        let mut symbol_token = token.clone();
-                                                        symbol_token.kind = TokenKind::Symbol;
-                                                        symbol_token.value = ch.to_string();
-                                                        json_tokens.push(symbol_token);
-                                                    }
-                                                }
-                                            }
-                                        } else if brace_count > 0 || bracket_count > 0 {
-                                            json_tokens.push(token.clone());
-                                        }
-                                    },
-                                    _ => {
-                                        if brace_count > 0 || bracket_count > 0 {
-                                            json_tokens.push(token.clone());
-                                        }
-                                    }
-                                }
-                                
-                                current_idx += 1;
-                                consumed += 1;
-                            } else {
-                                break;
-                            }
-                        }
-                        
-                        // Build JSON content
-                        let mut json_content = String::new();
-                        
-                        for (i, token) in json_tokens.iter().enumerate() {
-                            // Skip newline tokens that were converted to ";\n"
-                            if token.kind == TokenKind::Newline || token.value.contains(";\n") {
-                                continue;
-                            }
-                            
-                            let token_value = &token.value;
-                            
-                            // Add appropriate spacing
-                            if i > 0 && !json_content.is_empty() {
-                                let last_char = json_content.chars().last().unwrap_or(' ');
-                                let first_char = token_value.chars().next().unwrap_or(' ');
-                                
-                                // JSON spacing rules
-                                let needs_space = match (last_char, first_char) {
-                                    // After comma or colon, always space
-                                    (',', _) | (':', _) => true,
-                                    // Before closing delimiters, no space
-                                    (_, ',') | (_, ':') | (_, '}') | (_, ']') => false,
-                                    // After opening delimiters, no space
-                                    ('{', _) | ('[', _) => false,
-                                    // Between values, add space
-                                    _ if !",:]{}[]".contains(first_char) && !",:]{}[]".contains(last_char) => true,
-                                    _ => false
-                                };
-                                
-                                if needs_space {
-                                    json_content.push(' ');
-                                }
-                            }
-                            
-                            json_content.push_str(token_value);
-                        }
-                        
-                        // Mark that we're using JSON
-                        self.uses_data_types = true;
-                        self.result.mark_json_usage();
-                        
-                        // Complete JSON detection
-                        
-                        // Generate Rust code with json! macro
-                        if is_array {
-                            // Remove brackets from content for arrays
-                            let mut clean_content = json_content.trim();
-                            if clean_content.starts_with('[') {
-                                clean_content = &clean_content[1..];
-                            }
-                            if clean_content.ends_with(']') {
-                                clean_content = &clean_content[..clean_content.len()-1];
-                            }
-                            self.append(&format!("let {} = json!([{}]);", var_name, clean_content.trim()), AppendMode::AppendWithSpace);
-                        } else {
-                            // Remove braces from content for objects
-                            let mut clean_content = json_content.trim();
-                            if clean_content.starts_with('{') {
-                                clean_content = &clean_content[1..];
-                            }
-                            if clean_content.ends_with('}') {
-                                clean_content = &clean_content[..clean_content.len()-1];
-                            }
-                            self.append(&format!("let {} = json!({{{}}});", var_name, clean_content.trim()), AppendMode::AppendWithSpace);
-                        }
-                        self.append("\n", AppendMode::Append);
-                        
-                    } else {
-                        // Normal variable
-                        consumed += 2;
-                        self.append(&format!("let {} = ", var_name), AppendMode::AppendWithSpace);
-                    }
+    }
+
+    pub fn error(&self, message: &str) -> Diagnostic {
+        Diagnostic::error(message, self.location()).with_code("E0100")
+    }
+
+    /// Problems found while parsing
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    // Records the error and skips to the start of the next statement
+    fn recover(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+
+        loop {
+            if self.is_eof() {
+                return;
+            }
+            if self.is_newline() || self.kind() == TokenKind::Semicolon {
+                self.next();
+                return;
+            }
+            if self.is("}") || self.is(")") || self.is("]") {
+                // Closing the enclosing block, which will consume it
+                if self.depth > 0 && self.is("}") {
+                    return;
                 }
+                self.next();
+                continue;
             }
-        }
-        Consumed::consume(consumed)
-    }
-
-    pub fn parse_type_declaration(&mut self) -> Consumed {
-        let mut consumed = 0;
-        if self.kind() == TokenKind::Identifier && self.peek_value() == Some(":".to_string()) {
-            if let Some(type_token) = self.select(self.current + 2) {
-                // Check if it's a type declaration (identifier : type)
-                if type_token.kind == TokenKind::Json || type_token.kind == TokenKind::Xml || 
-                   type_token.kind == TokenKind::Toml || type_token.kind == TokenKind::Identifier ||
-                   type_token.kind == TokenKind::ParamType || type_token.kind == TokenKind::Keyword {
-                    
-                    let var_name = self.value();
-                    let (type_name, data_type) = utils::convert_type_with_marking(&type_token.value);
-                    
-                    // Mark data type usage when types are used
-                    if let Some(dt) = data_type {
-                        self.uses_data_types = true;
-                        match dt.as_str() {
-                            "json" => self.result.mark_json_usage(),
-                            "xml" => self.result.mark_xml_usage(),
-                            "toml" => self.result.mark_toml_usage(),
-                            _ => {}
-                        }
-                    }
-                    
-                    self.append(&format!("let {}: {};", var_name, type_name), AppendMode::AppendWithSpace);
-                    consumed += 3; // identifier + : + type
+            if self.is("(") || self.is("[") || self.is("{") {
+                if self.skip_group().is_err() {
+                    return;
                 }
+            } else {
+                self.next();
             }
         }
-        Consumed::consume(consumed)
     }
 
-    pub fn parse_json_object(&mut self) -> Consumed {
-        let mut consumed = 0;
-        if self.kind() == TokenKind::JsonObject {
+    fn unclosed(&self, open: &str, location: LocationData) -> Diagnostic {
+        Diagnostic::error(&format!("Unclosed delimiter `{}`", open), location)
+            .with_code("E0001")
+            .with_label("unclosed delimiter")
+    }
+
+    fn expect(&mut self, value: &str) -> ParseResult<Token> {
+        if self.is(value) {
+            let token = self.current().cloned().unwrap();
+            self.next();
+            Ok(token)
+        } else {
+            Err(Box::new(self.error(&format!("Expected `{}`, found {}", value, self.describe_current()))
+                .with_label(&format!("expected `{}`", value))))
+        }
+    }
+
+    fn expect_ident(&mut self) -> ParseResult<String> {
+        if self.is_ident() {
             let value = self.value();
-            
-            // Extract variable name and JSON content
-            if let Some(eq_pos) = value.find('=') {
-                let var_name = value[..eq_pos].trim();
-                let json_content = value[eq_pos + 1..].trim();
-                
-                // Mark that we're using JSON
-                self.uses_data_types = true;
-                self.result.mark_json_usage();
-                
-                // Generate Rust code with json! macro
-                self.append(&format!("let {} = json!({});", var_name, json_content), AppendMode::AppendWithSpace);
-                consumed += 1;
-            }
-        }
-        Consumed::consume(consumed)
-    }
-
-    pub fn parse_any(&mut self) -> Consumed {
-        let token_value = self.value();
-        
-        // Skip invalid tokens or tokens that shouldn't be in output
-        if token_value.is_empty() || 
-           token_value.contains("Como parâmetros de função") ||
-           token_value.contains("rocessaJSON") ||
-           token_value.starts_with("//") ||
-           self.kind() == TokenKind::Comment ||
-           self.kind() == TokenKind::Unknown {
-            return Consumed::consume(1);
-        }
-        
-        for (copper, rust) in RUST_MACROS.iter() {
-            if token_value == *copper {
-                // Check if next token is already !, if so, don't add another one
-                if let Some(next_token) = self.peek() {
-                    if next_token.value == "!" {
-                        // Next token is already !, just output the macro name without !
-                        self.append(copper, AppendMode::Append);
-                        return Consumed::consume(1);
-                    }
+            self.next();
+            Ok(value)
+        } else {
+            Err(Box::new(self.error(&format!("Expected identifier, found {}", self.describe_current()))))
+        }
+    }
+
+    // Consumes the end of a statement. Returns false when the statement is the
+    // last one of a block and wasn't terminated with `;`, so it can be the
+    // block value.
+    fn end_stmt(&mut self) -> ParseResult<bool> {
+        match self.kind() {
+            TokenKind::Semicolon => {
+                self.next();
+                Ok(true)
+            },
+            TokenKind::Newline => {
+                self.next();
+                let next = self.skip_newlines_from(self.current);
+                Ok(!self.is_at(next, "}"))
+            },
+            TokenKind::Eof => Ok(false),
+            _ if self.is("}") => Ok(false),
+            _ => Err(Box::new(self.error(&format!("Expected end of statement, found {}", self.describe_current()))
+                .with_help("put each statement on its own line or separate them with `;`"))),
+        }
+    }
+
+    // Joins the tokens in `start..end` back into source text
+    fn verbatim(&self, start: usize, end: usize) -> String {
+        let mut text = String::new();
+
+        for index in start..end {
+            let token = &self.tokens[index];
+
+            if token.kind == TokenKind::Newline {
+                let previous = text.trim_end().chars().last();
+                let next = self.select(self.skip_newlines_from(index + 1)).map(|t| t.value.as_str());
+                let needs_semicolon = token.value.starts_with(';')
+                    && !matches!(previous, None | Some(',' | '{' | '(' | '[' | ';'))
+                    && !matches!(next, None | Some("}" | ")" | "]" | "," | "{" | "=>" | "." | "->" | "where"));
+
+                if needs_semicolon {
+                    text.push(';');
                 }
-                // Next token is not !, so add the ! suffix
-                self.append(rust, AppendMode::Append);
-                return Consumed::consume(1);
-            }
-        }
-        
-        self.append(&token_value, AppendMode::Append);
-        Consumed::consume(1)
-    }
-
-    pub fn parse_function(&mut self) -> Consumed {
-        if self.value() == "func" {
-            self.function_start = true;
-            self.result.enter_function();
-            // Mark that we're using Copper syntax
-            self.result.is_copper_function = true;
-            return Consumed::consume(1);
-        } else if self.value() == "fn" {
-            // For pure Rust syntax, just mark as function but don't add "fn"
-            // because it's already present
-            self.function_start = true;
-            self.result.is_function = true;
-            self.result.is_copper_function = false;
-            self.append(&self.value(), AppendMode::AppendWithSpace);
-            return Consumed::consume(1);
-        }
-        Consumed::consume(0)
-    }
-
-    pub fn parse_function_params(&mut self) -> Consumed {
-        let mut consumed = 0;
-        if self.value() == "(" && self.kind() == TokenKind::ParametersStart {
-            self.append(&self.value(), AppendMode::Append);
-            consumed += 1;
-        }
-        if self.value() == ")" && self.kind() == TokenKind::ParametersEnd {
-            if self.result.is_function {
-                self.append(&self.value(), AppendMode::Append);
-                // Only add return type for Copper syntax
-                if self.result.is_copper_function {
-                    let return_type = if self.result.return_type.is_empty() { "()" } else { &self.result.return_type };
-                    self.append(&format!(" -> {}", return_type), AppendMode::Append);
+                if !text.is_empty() && !text.ends_with(' ') {
+                    text.push(' ');
                 }
-                self.result.exit_function();
-                self.result.is_inside_function = true;
-            } else {
-                self.append(&self.value(), AppendMode::Append);
+                continue;
+            }
+
+            if index > start && self.spaced[index] && !text.ends_with(' ') {
+                text.push(' ');
+            }
+            text.push_str(&token.value);
+        }
+
+        text.trim().to_string()
+    }
+
+    // Skips a balanced `(...)`, `[...]` or `{...}` group starting at the current token
+    fn skip_group(&mut self) -> ParseResult<()> {
+        let open = self.location();
+        let mut depth = 0;
+
+        loop {
+            if self.is_eof() {
+                let value = self.tokens.iter().find(|t| t.location_data.as_ref() == Some(&open)).map_or(String::new(), |t| t.value.clone());
+                return Err(Box::new(self.unclosed(&value, open)));
+            }
+            if self.is("(") || self.is("[") || self.is("{") {
+                depth += 1;
+            } else if self.is(")") || self.is("]") || self.is("}") {
+                depth -= 1;
+            }
+            self.next();
+            if depth == 0 {
+                return Ok(());
             }
-            consumed += 1;
         }
-        Consumed::consume(consumed)
     }
 
-    pub fn parse_function_body(&mut self) -> Consumed {
-        let mut consumed = 0;
-        if self.value() == "{" && self.kind() == TokenKind::BraceStart {
-            self.append(&self.value(), AppendMode::Append);
-            consumed += 1;
+    // Whether the group starting at the current token is followed by a block
+    fn group_before_block(&mut self) -> bool {
+        let start = self.current;
+        let closed = self.skip_group().is_ok();
+        let next = self.skip_newlines_from(self.current);
+        self.current = start;
+        closed && self.is_at(next, "{")
+    }
+
+    // Collects tokens until one of `stops` is found outside of any delimiter
+    fn skip_until(&mut self, stops: &[&str], stop_at_newline: bool) -> ParseResult<()> {
+        loop {
+            if self.is_eof() || stops.iter().any(|stop| self.is(stop)) {
+                return Ok(());
+            }
+            if stop_at_newline && self.is_newline() {
+                return Ok(());
+            }
+            if self.is("(") || self.is("[") || self.is("{") {
+                self.skip_group()?;
+            } else if self.is(")") || self.is("]") || self.is("}") {
+                return Ok(());
+            } else {
+                self.next();
+            }
         }
-        if self.value() == "}" && self.kind() == TokenKind::BraceEnd {
-            if !self.seen_import && !self.is_inside_class {
-                self.append(&self.value(), AppendMode::Append);
-                self.append("\n", AppendMode::AppendWithSpace);
-                self.result.is_inside_function = false;
-                consumed += 1;
+    }
+
+    /// Parses the whole file. Statements with errors are left out of the
+    /// program and reported in [`Parser::diagnostics`].
+    pub fn parse(&mut self) -> Program {
+        let mut body = Vec::new();
+
+        loop {
+            self.skip_separators();
+            if self.is_eof() {
+                break;
+            }
+            match self.parse_stmt() {
+                Ok(stmt) => body.push(stmt),
+                Err(diagnostic) => self.recover(*diagnostic),
             }
         }
-        Consumed::consume(consumed)
+        self.check_parent_constructors(&body);
+
+        Program {
+            body,
+            docs: self.module_docs.clone(),
+        }
     }
 
-    pub fn parse_operator(&mut self) -> Consumed {
-        let mut consumed = 0;
-        for (copper, rust) in COPPER_OPERATORS.iter() {
-            if let Some(next_token) = self.peek() {
-                if (next_token.value.clone() + &self.value()) == *copper {
-                    self.append(rust, AppendMode::Append);
-                    consumed += 2;
-                    break;
-                }
+    // Runs `parse` in a new scope with `vars` declared in it
+    fn in_scope<T>(
+        &mut self,
+        parent: Parent,
+        vars: Vec<(String, bool)>,
+        parse: impl FnOnce(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<T> {
+        self.scopes.add_scope(parent);
+        for (var, assignable) in vars {
+            self.scopes.get_current_scope_mut().add_var(var, assignable);
+        }
+        let result = parse(self);
+        self.scopes.end_scope();
+        result
+    }
+
+    fn declare(&mut self, pattern: &Pattern, assignable: bool) {
+        for (name, mutable) in pattern_vars(pattern) {
+            self.scopes.get_current_scope_mut().add_var(name, mutable || assignable);
+        }
+    }
+
+    pub fn parse_block(&mut self) -> ParseResult<Block> {
+        self.in_scope(Parent::Block, Vec::new(), Self::parse_block_stmts)
+    }
+
+    fn parse_block_stmts(&mut self) -> ParseResult<Block> {
+        let start = self.location();
+        self.expect("{")?;
+
+        let mut stmts = Vec::new();
+        self.depth += 1;
+        loop {
+            self.skip_separators();
+            if self.is("}") {
+                self.next();
+                break;
+            }
+            if self.is_eof() {
+                self.depth -= 1;
+                return Err(Box::new(self.unclosed("{", start)));
+            }
+            match self.parse_stmt() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(diagnostic) => self.recover(*diagnostic),
             }
         }
-        Consumed::consume(consumed)
+        self.depth -= 1;
+
+        Ok(Block {
+            stmts,
+            location: self.span(&start),
+        })
     }
 
-    pub fn parse_import(&mut self) -> Consumed {
-        let mut consumed = 0;
-        if self.value() == "from" && self.kind() == TokenKind::From {
-            consumed += 1;
+    pub fn parse_stmt(&mut self) -> ParseResult<Stmt> {
+        let start = self.location();
+
+        if let Some(item) = self.parse_item()? {
+            return Ok(Stmt {
+                kind: StmtKind::Item(item),
+                location: self.span(&start),
+            });
         }
-        if self.value() == "import" && self.kind() == TokenKind::Import {
-            self.seen_import = true;
-            self.append("use", AppendMode::AppendWithSpace);
-            consumed += 1;
+
+        let kind = if self.is("let") {
+            self.parse_let()?
+        } else if self.is("mut") && self.peek().is_some_and(is_ident) {
+            self.next();
+            self.parse_var(true)?
+        } else if self.is_ident() && (self.peek_is("=") || self.peek_is(":")) {
+            self.parse_var(false)?
+        } else {
+            self.parse_expr_stmt()?
+        };
+
+        Ok(Stmt {
+            kind,
+            location: self.span(&start),
+        })
+    }
+
+    // Copper variables: `x = value`, `mut x = value`, `x: type` and `x: type = value`.
+    // `x = value` assigns `x` instead when it was declared `mut` or without a value.
+    pub fn parse_var(&mut self, mutable: bool) -> ParseResult<StmtKind> {
+        let start = self.location();
+        let name = self.expect_ident()?;
+        // A new variable in the block would leave the outer one unchanged
+        if !mutable && self.is("=") && self.scopes.is_immutable_outside(&name) {
+            let diagnostic = Diagnostic::error(&format!("Cannot assign to `{}` inside this block", name), self.span(&start))
+                .with_label(&format!("`{}` is declared outside of the block without `mut`", name))
+                .with_help(&format!("declare `{}` with `mut`", name));
+            self.diagnostics.push(diagnostic);
+            self.current -= 1;
+            return self.parse_expr_stmt();
         }
-        if self.value() == "{" && self.kind() == TokenKind::BraceStart && self.seen_import {
-            self.is_import_list = true;
-            consumed += 1;
+        if !mutable && self.is("=") && self.scopes.find_var(&name) == Some(true) {
+            self.current -= 1;
+            return self.parse_expr_stmt();
         }
-        if self.value() == "}" && self.kind() == TokenKind::BraceEnd && self.seen_import {
-            consumed += 1;
+        let pattern = Pattern {
+            kind: PatternKind::Ident { name, mutable },
+            location: self.span(&start),
+        };
+
+        let ty = if self.is(":") {
+            self.next();
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+
+        let value = if self.is("=") {
+            self.next();
+            self.skip_newlines();
+            Some(self.parse_var_value(ty.as_ref())?)
+        } else {
+            None
+        };
+
+        if ty.is_none() && value.is_none() {
+            return Err(Box::new(self.error(&format!("Expected `=` or `:`, found {}", self.describe_current()))));
         }
-        if self.kind() == TokenKind::ModuleVar {
-            self.current_import_vars.push(self.value());
-            consumed += 1;
+
+        self.end_stmt()?;
+        self.check_declared_literal(ty.as_ref(), value.as_ref());
+        self.declare(&pattern, value.is_none());
+        Ok(StmtKind::Let { pattern, ty, value })
+    }
+
+    // Variables initialized with `{` or `[` hold JSON values unless annotated
+    // with another type, `list`, `set` and `map` have literals of their own
+    fn parse_var_value(&mut self, ty: Option<&Type>) -> ParseResult<Expr> {
+        let (name, args) = match ty.map(|ty| &ty.kind) {
+            None => ("json", &[][..]),
+            Some(TypeKind::Path { segments, args }) if segments.len() == 1 => (segments[0].as_str(), args.as_slice()),
+            Some(_) => ("", &[][..]),
+        };
+
+        let start = self.location();
+        match name {
+            "list" | "set" if self.is("[") => {
+                let items = self.parse_list_literal(args.first())?;
+                let kind = if name == "list" { ExprKind::List(items) } else { ExprKind::Set(items) };
+                return Ok(Expr::new(kind, self.span(&start)));
+            },
+            "map" if self.is("{") => {
+                let entries = self.parse_map_literal(args.first(), args.get(1))?;
+                return Ok(Expr::new(ExprKind::Map(entries), self.span(&start)));
+            },
+            _ => {},
         }
-        if self.kind() == TokenKind::ModulePath {
-            if self.seen_import {
-                self.append(&self.value(), AppendMode::Append);
-                if !self.current_import_vars.is_empty() {
-                    if self.is_import_list {
-                        self.append(&"::".to_string(), AppendMode::Append);
-                        self.append(&"{".to_string(), AppendMode::Append);
-                        self.append(&self.current_import_vars.join(", "), AppendMode::Append);
-                        self.append(&"}".to_string(), AppendMode::Append);
-                        self.is_import_list = false;
-                    } else {
-                        let var = self.current_import_vars[0].clone();
-                        self.append(&" as".to_string(), AppendMode::AppendWithSpace);
-                        self.append(&var, AppendMode::Append);
-                    }
-                    self.current_import_vars.clear();
-                }
-                self.seen_import = false;
-                consumed += 1;
-            } else {
-                self.append(&self.value(), AppendMode::Append);
-                consumed += 1;
-            }
+
+        if name == "json" && (self.is("{") || self.is("[")) {
+            let value = self.parse_json_value()?;
+            return Ok(Expr::new(ExprKind::Json(value), self.span(&start)));
         }
-        Consumed::consume(consumed)
+
+        self.parse_expr()
     }
 
-    pub fn parse_regex(&mut self) -> Consumed {
-        let mut consumed = 0;
-        if self.kind() == TokenKind::Regex {
-            if !self.result.has_required_import("regex") {
-                self.result.add_required_import("regex");
-            }
-            let value = self.value();
-            let var = "__regex__";
-            let resolved_regex = value.trim_start_matches('/').trim_end_matches('/');
-            self.append(&format!("{}::Regex::new(r\"{}\").unwrap()", var, resolved_regex), AppendMode::Append);
-            consumed += 1;
+    // An item of a collection literal, itself a literal of the item type
+    fn parse_item_value(&mut self, ty: Option<&Type>) -> ParseResult<Expr> {
+        match ty {
+            Some(ty) => self.parse_var_value(Some(ty)),
+            None => self.parse_expr(),
         }
-        Consumed::consume(consumed)
     }
 
-    pub fn parse_class_definition(&mut self) -> Consumed {
-        // Detects "class Name { ... }"
-        if self.value() == "class" && self.kind() == TokenKind::Keyword {
-            let mut consumed = 1; 
+    // `[item, ...]` of a list or a set
+    fn parse_list_literal(&mut self, item: Option<&Type>) -> ParseResult<Vec<Expr>> {
+        let open = self.location();
+        self.expect("[")?;
 
-            let class_name = if let Some(tok) = self.select(self.current + consumed) {
-                if tok.kind == TokenKind::Identifier {
-                    consumed += 1;
-                    tok.value.clone()
-                } else {
-                    return Consumed::consume(0);
-                }
-            } else {
-                return Consumed::consume(0);
-            };
-            self.current_class = Some(class_name.clone());
-            self.is_inside_class = true;
-    
-            let mut brace_count = 0;
-            let mut class_tokens = Vec::new();
-            // first, advances until it finds the first '{'
-            while let Some(tok) = self.select(self.current + consumed) {
-                consumed += 1;
-                if tok.kind == TokenKind::BraceStart {
-                    brace_count = 1;
-                    class_tokens.push(tok.clone());
-                    break;
-                }
+        let mut items = Vec::new();
+        loop {
+            self.skip_newlines();
+            if self.is("]") {
+                self.next();
+                break;
             }
-            // Now collects until all braces are closed
-            while brace_count > 0 {
-                if let Some(tok) = self.select(self.current + consumed) {
-                    consumed += 1;
-                    match tok.kind {
-                        TokenKind::BraceStart => brace_count += 1,
-                        TokenKind::BraceEnd   => brace_count -= 1,
-                        _ => {}
-                    }
-                    class_tokens.push(tok.clone());
-                } else {
-                    break;
-                }
+            if self.is_eof() {
+                return Err(Box::new(self.unclosed("[", open)));
             }
 
-            // Processes members and appends the generated Rust code
-            let parsed = self.process_class_members(&class_tokens, &class_name);
-            self.append(&parsed, AppendMode::AppendWithSpace);
-    
-            // reset
-            self.is_inside_class = false;
-            self.current_class = None;
-            return Consumed::consume(consumed.try_into().unwrap());
+            items.push(self.parse_item_value(item)?);
+
+            self.skip_newlines();
+            if self.is(",") {
+                self.next();
+            } else if !self.is("]") {
+                return Err(Box::new(self.error(&format!("Expected `,` or `]`, found {}", self.describe_current()))
+                    .with_secondary(open, "`[` opened here")));
+            }
         }
-    
-        Consumed::consume(0)
+
+        Ok(items)
     }
-    
-    fn process_class_members(&mut self, class_tokens: &[Token], class_name: &str) -> String {
-        let tokens: Vec<&Token> = class_tokens
-            .iter()
-            .filter(|t| t.kind != TokenKind::Newline)
-            .collect();
-        if tokens.len() < 2 {
-            return String::new();
-        }
-        let inner = &tokens[1..tokens.len()-1];
-    
-        let mut output = String::new();
-        let mut fields = Vec::new();
-    
-        // Collect the fields
-        let mut i = 0;
-        while i + 2 < inner.len() {
-            let a = &inner[i];
-            let b = &inner[i+1];
-            let c = &inner[i+2];
-            let is_colon = (b.kind == TokenKind::Operator || b.kind == TokenKind::Colon) && b.value == ":";
-            let is_type = c.kind == TokenKind::Identifier
-                || c.kind == TokenKind::ParamType
-                || c.kind == TokenKind::Keyword;
-    
-            if a.kind == TokenKind::Identifier && is_colon && is_type {
-                fields.push((a.value.clone(), c.value.clone()));
-                i += 3;
-            } else {
+
+    // `{ key: value, ... }` of a map, the keys are expressions
+    fn parse_map_literal(&mut self, key: Option<&Type>, value: Option<&Type>) -> ParseResult<Vec<(Expr, Expr)>> {
+        let open = self.location();
+        self.expect("{")?;
+
+        let mut entries = Vec::new();
+        loop {
+            self.skip_newlines();
+            if self.is("}") {
+                self.next();
                 break;
             }
+            if self.is_eof() {
+                return Err(Box::new(self.unclosed("{", open)));
+            }
+
+            let entry_key = self.parse_item_value(key)?;
+            self.expect(":")?;
+            self.skip_newlines();
+            entries.push((entry_key, self.parse_item_value(value)?));
+
+            self.skip_newlines();
+            if self.is(",") {
+                self.next();
+            } else if !self.is("}") {
+                return Err(Box::new(self.error(&format!("Expected `,` or `}}`, found {}", self.describe_current()))
+                    .with_secondary(open, "`{` opened here")));
+            }
         }
-    
-        // Build the struct
-        output.push_str(&format!("struct {} {{\n", class_name));
-        for (n, t) in &fields {
-            output.push_str(&format!("    {}: {},\n", n, t));
+
+        Ok(entries)
+    }
+
+    // Rust's `let [mut] pattern[: type] [= value]`
+    pub fn parse_let(&mut self) -> ParseResult<StmtKind> {
+        self.expect("let")?;
+        let pattern = self.parse_pattern(&["=", ":"])?;
+
+        let ty = if self.is(":") {
+            self.next();
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+
+        let value = if self.is("=") {
+            self.next();
+            self.skip_newlines();
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+
+        self.end_stmt()?;
+        self.check_declared_literal(ty.as_ref(), value.as_ref());
+        self.declare(&pattern, value.is_none());
+        Ok(StmtKind::Let { pattern, ty, value })
+    }
+
+    pub fn parse_expr_stmt(&mut self) -> ParseResult<StmtKind> {
+        let start = self.location();
+        let mut expr = self.parse_expr()?;
+
+        // Copper's `x++` and `x--`
+        if let Some(op) = self.increment_operator() {
+            self.next();
+            self.next();
+            let one = Expr::new(ExprKind::Literal(Literal::Number("1".to_string())), self.previous_location());
+            expr = Expr::new(ExprKind::Assign {
+                op: Some(op),
+                target: Box::new(expr),
+                value: Box::new(one),
+            }, self.span(&start));
         }
-        output.push_str("}\n\n");
-    
-        // Build the constructor
-        output.push_str(&format!("impl {} {{\n", class_name));
-        if let Some(pos) = inner
-            .iter()
-            .position(|t| t.kind == TokenKind::Identifier && t.value == class_name)
-        {
-            if pos + 1 < inner.len() && inner[pos + 1].kind == TokenKind::ParenthesesStart {
-                // coleta params
-                let mut params = Vec::new();
-                let mut j = pos + 2;
-                while j + 2 < inner.len() && inner[j].kind == TokenKind::Param {
-                    let pname = inner[j].value.clone();
-                    let sep = &inner[j + 1];
-                    let ptyp = &inner[j + 2];
-                    let ok_sep = (sep.kind == TokenKind::Operator || sep.kind == TokenKind::Colon) && sep.value == ":";
-                    if ok_sep && ptyp.kind == TokenKind::ParamType {
-                        params.push((pname.clone(), ptyp.value.clone()));
-                        j += 3;
-                        if j < inner.len() && inner[j].value == "," {
-                            j += 1;
-                        }
-                    } else {
-                        break;
-                    }
-                }
-    
-                // Jump to the first '{'
-                while j < inner.len() && inner[j].kind != TokenKind::BraceStart {
-                    j += 1;
-                }
-                if j >= inner.len() {
-                    return output;
-                }
-    
-                // Extract the assignments
-                let mut assigns = Vec::new();
-                
-                // Initialize for the class body analysis
-                let mut bi: usize = j + 1;
-                while bi < inner.len() {
-                    let t0 = &inner[bi];
-                    if t0.kind == TokenKind::BraceEnd {
-                        break;
-                    }
-                    // detecta "self . campo ="
-                    if t0.kind == TokenKind::Keyword
-                        && t0.value == "self"
-                        && bi + 3 < inner.len()
-                        && inner[bi + 1].kind == TokenKind::Dot
-                        && inner[bi + 2].kind == TokenKind::Identifier
-                        && inner[bi + 3].value == "="
-                    {
-                        let field = inner[bi + 2].value.clone();
-                        // Collect all tokens until the end of the statement or block
-                        let mut expr_tokens = Vec::new();
-                        let mut xi = bi + 4;
-    
-                        while xi < inner.len() {
-                            let tt = &inner[xi];
-                            // Found statement end or block end
-                            if tt.value == ";" || tt.kind == TokenKind::BraceEnd {
-                                break;
-                            }
-                            // Found new assignment
-                            if tt.kind == TokenKind::Keyword
-                                && tt.value == "self"
-                                && xi + 3 < inner.len()
-                                && inner[xi + 1].kind == TokenKind::Dot
-                                && inner[xi + 2].kind == TokenKind::Identifier
-                                && inner[xi + 3].value == "="
-                            {
-                                break;
-                            }
-    
-                            expr_tokens.push(tt.value.clone());
-                            xi += 1;
-                        }
-    
-                        let expr = expr_tokens.join(" ").trim().to_string();
-                        assigns.push((field, expr));
-                        bi = xi;
-                        continue;
-                    }
-    
-                    bi += 1;
-                }
-    
-                // Generate the constructor signature
-                let sig = params
-                    .iter()
-                    .map(|(n, t)| format!("{}: {}", n, t))
-                    .collect::<Vec<_>>()
-                    .join(", ");
-    
-                // Initialize the fields
-                let init = fields
-                    .iter()
-                    .map(|(field_name, _)| {
-                        // Searches in assigns if this field has an explicit initialization
-                        if let Some((_, expr)) = assigns.iter().find(|(f, _)| f == field_name) {
-                            format!("{}: {}", field_name, expr)
-                        } 
-                        else if let Some((param_name, _)) = params.iter().find(|(n, _)| n == field_name) {
-                            format!("{}: {}", field_name, param_name)
-                        } 
-                        else {
-                            // Default if not found in assigns or params
-                            format!("{}: Default::default()", field_name)
-                        }
-                    })
-                    .collect::<Vec<_>>()
-                    .join(", ");
-    
-                output.push_str(&format!("    pub fn new({}) -> Self {{\n", sig));
-                output.push_str(&format!("        Self {{ {} }}\n", init));
-                output.push_str("    }\n\n");
-            }
-        }
-
-        // Methods
-        let mut k = 0;
-        while k < inner.len() {
-            if k + 3 < inner.len() {
-                let return_type = &inner[k];
-                let name_t = &inner[k + 1];
-                let pstart = &inner[k + 2];
-    
-                let valid_return_type = return_type.kind == TokenKind::Identifier
-                    || return_type.kind == TokenKind::ParamType
-                    || return_type.kind == TokenKind::Keyword;
-    
-                if valid_return_type
-                    && name_t.kind == TokenKind::Identifier
-                    && pstart.kind == TokenKind::ParenthesesStart
-                {
-                    let mut found_self = false;
-                    let mut idx = k + 3;
-                    while idx < inner.len() {
-                        let param = &inner[idx];
-                        if param.value == "self" {
-                            found_self = true;
-                        }
-                        if param.kind == TokenKind::ParametersEnd {
-                            break;
-                        }
-                        idx += 1;
-                    }
-    
-                    if found_self && idx < inner.len() && inner[idx].kind == TokenKind::ParametersEnd {
-                        let mut body = Vec::new();
-                        idx += 1;
-                        while idx < inner.len() && inner[idx].kind != TokenKind::BraceStart {
-                            idx += 1;
-                        }
 
-                        if idx < inner.len() {
-                            let mut depth = 1;
-                            idx += 1;
-                            while idx < inner.len() && depth > 0 {
-                                let tt = &inner[idx];
-                                if tt.kind == TokenKind::BraceStart {
-                                    depth += 1;
-                                } else if tt.kind == TokenKind::BraceEnd {
-                                    depth -= 1;
-                                }
-                                if depth > 0 {
-                                    body.push(tt.value.clone());
-                                }
-                                idx += 1;
-                            }
-    
-                            let body_str = body.join(" ");
-                            let (rust_type, data_type) = utils::convert_type_with_marking(&return_type.value);
-                            
-                            // Mark data type usage for function return types  
-                            if let Some(dt) = data_type {
-                                self.uses_data_types = true;
-                                match dt.as_str() {
-                                    "json" => self.result.mark_json_usage(),
-                                    "xml" => self.result.mark_xml_usage(),
-                                    "toml" => self.result.mark_toml_usage(),
-                                    _ => {}
-                                }
-                            }
-                            
-                            if rust_type == "()" {
-                                output.push_str(&format!("    pub fn {}(&self) {{\n", name_t.value));
-                            } else {
-                                output.push_str(&format!("    pub fn {}(&self) -> {} {{\n", name_t.value, rust_type));
-                            }
-                            output.push_str(&format!("        {}\n", body_str));
-                            output.push_str("    }\n\n");
-                        }
-                        k = idx;
-                        continue;
-                    }
-                }
-            }
-            k += 1;
+        let semicolon = if expr.is_block_like() && !self.is_terminator() {
+            true
+        } else {
+            self.end_stmt()?
+        };
+
+        Ok(StmtKind::Expr { expr, semicolon })
+    }
+
+    // `++` or `--` right before the end of the statement
+    fn increment_operator(&self) -> Option<BinaryOp> {
+        let op = match self.value().as_str() {
+            "+" => BinaryOp::Add,
+            "-" => BinaryOp::Sub,
+            _ => return None,
+        };
+
+        let ends = self.select(self.current + 2).is_none_or(|t| {
+            matches!(t.kind, TokenKind::Newline | TokenKind::Semicolon | TokenKind::Eof) || t.value == "}"
+        });
+
+        if self.peek_value() == Some(self.value()) && ends {
+            Some(op)
+        } else {
+            None
         }
-    
-        output.push_str("}\n");
-        output
     }
 
-    // Parses native struct definition
-    pub fn parse_struct_definition(&mut self) -> Consumed {
-        if self.value() == "struct" && self.kind() == TokenKind::Struct {
-            let mut consumed = 1;
-            
-            let struct_name = if let Some(tok) = self.select(self.current + consumed) {
-                if tok.kind == TokenKind::Identifier {
-                    consumed += 1;
-                    tok.value.clone()
-                } else {
-                    return Consumed::consume(0);
-                }
-            } else {
-                return Consumed::consume(0);
-            };
+    // The `///` lines right before the current token
+    fn doc_comments(&self) -> Vec<String> {
+        self.docs.get(&self.current).map(|(docs, _)| docs.clone()).unwrap_or_default()
+    }
 
-            self.current_struct = Some(struct_name.clone());
-            self.is_inside_struct = true;
-
-            let mut generics = String::new();
-            if let Some(tok) = self.select(self.current + consumed) {
-                if tok.kind == TokenKind::AngleStart {
-                    consumed += 1;
-                    generics.push('<');
-                    
-                    while let Some(tok) = self.select(self.current + consumed) {
-                        consumed += 1;
-                        if tok.kind == TokenKind::AngleEnd {
-                            generics.push('>');
-                            break;
-                        }
-                        generics.push_str(&tok.value);
-                        if tok.value == "," {
-                            generics.push(' ');
-                        }
-                    }
-                }
+    // Doc comments are kept as attributes, `#[...]` and `///` lines can be mixed
+    fn parse_attributes(&mut self) -> ParseResult<Vec<String>> {
+        let mut attributes = self.doc_comments();
+
+        while self.is("#") {
+            let start = self.current;
+            self.next();
+            if self.is("!") {
+                self.next();
+            }
+            if !self.is("[") {
+                return Err(Box::new(self.error(&format!("Expected `[`, found {}", self.describe_current()))));
             }
+            self.skip_group()?;
+            attributes.push(self.verbatim(start, self.current).replace("# [", "#["));
+            self.skip_newlines();
+            attributes.extend(self.doc_comments());
+        }
 
-            self.append(&format!("struct {}{} {{", struct_name, generics), AppendMode::ForceAppendWithSpace);
+        Ok(attributes)
+    }
 
-            // Processes struct fields
-            while let Some(tok) = self.select(self.current + consumed) {
-                consumed += 1;
-                if tok.kind == TokenKind::BraceStart {
-                    break;
-                }
+    pub fn parse_item(&mut self) -> ParseResult<Option<Item>> {
+        let save = self.current;
+        let start = self.location();
+        let attributes = self.parse_attributes()?;
+
+        let is_pub = self.is("pub") || self.is("public");
+        if is_pub {
+            self.next();
+            // pub(crate), pub(super)...
+            if self.is("(") {
+                self.skip_group()?;
             }
+        }
 
-            // Collect fields until the closing brace
-            let mut brace_count = 1;
-            let mut current_field = String::new();
-            let mut in_field_name = true;
-            
-            while brace_count > 0 && consumed < self.tokens.len() - self.current {
-                if let Some(tok) = self.select(self.current + consumed) {
-                    consumed += 1;
-                    match tok.kind {
-                        TokenKind::BraceStart => brace_count += 1,
-                        TokenKind::BraceEnd => {
-                            brace_count -= 1;
-                            if brace_count == 0 {
-                                if !current_field.trim().is_empty() {
-                                    self.append(&format!("    {},", current_field.trim()), AppendMode::ForceAppendWithSpace);
-                                }
-                                break;
-                            }
-                        },
-                        TokenKind::Identifier => {
-                            if in_field_name {
-                                current_field = tok.value.clone();
-                                in_field_name = false;
-                            } else {
-                                // This is a type
-                                let (converted_type, data_type) = utils::convert_type_with_marking(&tok.value);
-                                
-                                // Mark data type usage for struct fields
-                                if let Some(dt) = data_type {
-                                    self.uses_data_types = true;
-                                    match dt.as_str() {
-                                        "json" => self.result.mark_json_usage(),
-                                        "xml" => self.result.mark_xml_usage(),
-                                        "toml" => self.result.mark_toml_usage(),
-                                        _ => {}
-                                    }
-                                }
-                                
-                                current_field.push_str(&converted_type);
-                            }
-                        },
-                        TokenKind::Colon => {
-                            current_field.push_str(": ");
-                        },
-                        TokenKind::ParamType | TokenKind::Type | TokenKind::Json | TokenKind::Xml | TokenKind::Toml => {
-                            let (converted_type, data_type) = utils::convert_type_with_marking(&tok.value);
-                            
-                            // Mark data type usage for struct/param types
-                            if let Some(dt) = data_type {
-                                self.uses_data_types = true;
-                                match dt.as_str() {
-                                    "json" => self.result.mark_json_usage(),
-                                    "xml" => self.result.mark_xml_usage(),
-                                    "toml" => self.result.mark_toml_usage(),
-                                    _ => {}
-                                }
-                            }
-                            
-                            current_field.push_str(&converted_type);
-                            
-                            // Note: We don't mark data types usage just by declaring them in structs
-                            // We'll only mark when actually using the types in operations
-                            // match tok.kind {
-                            //     TokenKind::Json => {
-                            //         self.uses_data_types = true;
-                            //         self.result.mark_json_usage();
-                            //     },
-                            //     TokenKind::Xml => {
-                            //         self.uses_data_types = true;
-                            //         self.result.mark_xml_usage();
-                            //     },
-                            //     TokenKind::Toml => {
-                            //         self.uses_data_types = true;
-                            //         self.result.mark_toml_usage();
-                            //     },
-                            //     _ => {}
-                            // }
-                        },
-                        TokenKind::Comma => {
-                            if !current_field.trim().is_empty() {
-                                self.append(&format!("    {},", current_field.trim()), AppendMode::ForceAppendWithSpace);
-                                current_field.clear();
-                                in_field_name = true;
-                            }
-                        },
-                        TokenKind::Newline => {
-                            // Ignores newlines
-                        },
-                        _ => {
-                            if !tok.value.trim().is_empty() && tok.value != " " {
-                                current_field.push_str(&tok.value);
-                            }
-                        }
-                    }
+        let kind = match self.value().as_str() {
+            _ if is_literal(self.kind()) => None,
+            "import" if self.kind() == TokenKind::Import => Some(ItemKind::Import(self.parse_import()?)),
+            "use" => Some(self.parse_use()?),
+            "func" | "fn" => Some(ItemKind::Function(self.parse_function(is_pub)?)),
+            "class" => Some(ItemKind::Class(self.parse_class_definition()?)),
+            "struct" => Some(self.parse_struct_definition(is_pub, save)?),
+            "enum" => Some(ItemKind::Enum(self.parse_enum_definition(is_pub)?)),
+            "trait" => Some(ItemKind::Trait(self.parse_trait_definition(is_pub)?)),
+            "impl" => Some(ItemKind::Impl(self.parse_impl_block()?)),
+            "const" | "static" => Some(ItemKind::Const(self.parse_const(is_pub)?)),
+            "macro_rules" if self.peek_is("!") => Some(self.parse_verbatim_item(save)?),
+            value if VERBATIM_ITEMS.contains(&value) => Some(self.parse_verbatim_item(save)?),
+            _ => None,
+        };
+
+        match kind {
+            Some(kind) => Ok(Some(Item {
+                kind,
+                attributes,
+                location: self.span(&start),
+            })),
+            None if is_pub || attributes.iter().any(|attribute| attribute.starts_with('#')) => {
+                Err(Box::new(self.error(&format!("Expected item, found {}", self.describe_current()))))
+            },
+            // Doc comments before a statement document nothing, rustc warns about them too
+            None => {
+                if let Some((_, location)) = self.docs.get(&save) {
+                    let diagnostic = Diagnostic::warning("Unused doc comment", location.clone())
+                        .with_label("doc comments only document items, fields, variants and methods")
+                        .with_secondary(self.location(), "this statement is not documented")
+                        .with_help("use `//` for a regular comment");
+                    self.diagnostics.push(diagnostic);
                 }
+                self.current = save;
+                Ok(None)
             }
+        }
+    }
 
-            self.append("}", AppendMode::ForceAppendWithSpace);
-            self.append("\n", AppendMode::ForceAppend);
-            self.is_inside_struct = false;
-            self.current_struct = None;
-            
-            return Consumed::consume(consumed.try_into().unwrap());
-        }
-        
-        Consumed::consume(0)
-    }
-
-    pub fn parse_impl_block(&mut self) -> Consumed {
-        if self.value() == "impl" && self.kind() == TokenKind::Impl {
-            let mut consumed = 1; // count the 'impl'
-            
-            // Generics for impl (optional)
-            let mut impl_generics = String::new();
-            if let Some(tok) = self.select(self.current + consumed) {
-                if tok.kind == TokenKind::AngleStart {
-                    consumed += 1;
-                    impl_generics.push('<');
-                    
-                    while let Some(tok) = self.select(self.current + consumed) {
-                        consumed += 1;
-                        if tok.kind == TokenKind::AngleEnd {
-                            impl_generics.push('>');
+    // Passes an item through as written, from `start` up to its closing brace
+    // or the end of the line
+    fn parse_verbatim_item(&mut self, start: usize) -> ParseResult<ItemKind> {
+        // Skip attributes, they are kept separately
+        let mut text_start = start;
+        while self.is_at(text_start, "#") {
+            let mut depth = 0;
+            text_start += 1;
+            while let Some(token) = self.select(text_start) {
+                text_start += 1;
+                match token.value.as_str() {
+                    "[" => depth += 1,
+                    "]" => {
+                        depth -= 1;
+                        if depth == 0 {
                             break;
                         }
-                        impl_generics.push_str(&tok.value);
-                        if tok.value == "," {
-                            impl_generics.push(' ');
-                        }
-                    }
+                    },
+                    _ => {}
                 }
             }
+            text_start = self.skip_newlines_from(text_start);
+        }
 
-            // Name of the type being implemented
-            let target_type = if let Some(tok) = self.select(self.current + consumed) {
-                if tok.kind == TokenKind::Identifier {
-                    consumed += 1;
-                    tok.value.clone()
-                } else {
-                    return Consumed::consume(0);
-                }
+        loop {
+            if self.is_eof() || self.is_newline() || self.kind() == TokenKind::Semicolon {
+                break;
+            }
+            if self.is("{") {
+                self.skip_group()?;
+                break;
+            }
+            if self.is("(") || self.is("[") {
+                self.skip_group()?;
             } else {
-                return Consumed::consume(0);
-            };
-
-            // Type generics (optional)
-            let mut type_generics = String::new();
-            if let Some(tok) = self.select(self.current + consumed) {
-                if tok.kind == TokenKind::AngleStart {
-                    consumed += 1;
-                    type_generics.push('<');
-                    
-                    while let Some(tok) = self.select(self.current + consumed) {
-                        consumed += 1;
-                        if tok.kind == TokenKind::AngleEnd {
-                            type_generics.push('>');
-                            break;
-                        }
-                        type_generics.push_str(&tok.value);
-                        if tok.value == "," {
-                            type_generics.push(' ');
-                        }
-                    }
-                }
+                self.next();
             }
+        }
 
-            // Verifies if is impl or for 
-            if let Some(tok) = self.select(self.current + consumed) {
-                if tok.value == "for" {
-                    consumed += 1;
-                    
-                    // The trait name must be before the "for"
-                    let trait_name = target_type.clone();
-                    
-                    if let Some(tok) = self.select(self.current + consumed) {
-                        if tok.kind == TokenKind::Identifier {
-                            consumed += 1;
-                            let actual_target = tok.value.clone();
-                            self.current_impl_target = Some(actual_target.clone());
-                            self.append(&format!("impl{} {} for {}{} {{", impl_generics, trait_name, actual_target, type_generics), AppendMode::ForceAppendWithSpace);
-                        }
+        let mut text = self.verbatim(text_start, self.current);
+        if !text.ends_with('}') && !text.ends_with(';') {
+            text.push(';');
+        }
+        Ok(ItemKind::Verbatim(text))
+    }
+
+    // import alias from a.b | import { a, b } from a.b | import * from a.b
+    pub fn parse_import(&mut self) -> ParseResult<Import> {
+        self.expect("import")?;
+
+        let kind = if self.is("{") {
+            self.next();
+            let mut names = Vec::new();
+            loop {
+                self.skip_newlines();
+                if self.is("}") {
+                    self.next();
+                    break;
+                }
+                names.push(self.expect_ident()?);
+                self.skip_newlines();
+                if self.is(",") {
+                    self.next();
+                } else if !self.is("}") {
+                    return Err(Box::new(self.error(&format!("Expected `,` or `}}`, found {}", self.describe_current()))));
+                }
+            }
+            ImportKind::List(names)
+        } else if self.is("*") {
+            self.next();
+            ImportKind::All
+        } else {
+            ImportKind::Alias(self.expect_ident()?)
+        };
+
+        self.expect("from")?;
+
+        let mut module = String::new();
+        while !self.is_terminator() {
+            module.push_str(&self.value());
+            self.next();
+        }
+        if module.is_empty() {
+            return Err(Box::new(self.error("Expected module path after `from`")));
+        }
+        self.end_stmt()?;
+
+        let relative = module.starts_with('.') || module.starts_with('/');
+        let path = if relative {
+            module
+                .split('/')
+                .filter(|segment| !segment.is_empty() && *segment != ".")
+                .map(|segment| segment.to_string())
+                .collect()
+        } else {
+            module
+                .replace("::", ".")
+                .split('.')
+                .filter(|segment| !segment.is_empty())
+                .map(|segment| segment.to_string())
+                .collect()
+        };
+
+        Ok(Import { path, kind, relative })
+    }
+
+    fn parse_use(&mut self) -> ParseResult<ItemKind> {
+        let start = self.current;
+        self.expect("use")?;
+        self.skip_until(&[";"], true)?;
+        let text = self.verbatim(start + 1, self.current).replace(" :: ", "::");
+        self.end_stmt()?;
+        Ok(ItemKind::Use(text))
+    }
+
+    fn parse_const(&mut self, is_pub: bool) -> ParseResult<Const> {
+        let is_static = self.is("static");
+        self.next();
+        if self.is("mut") {
+            return Err(Box::new(self.error("`static mut` is not supported")));
+        }
+
+        let name = self.expect_ident()?;
+        self.expect(":")?;
+        let ty = self.parse_type()?;
+        self.expect("=")?;
+        self.skip_newlines();
+        let value = self.parse_expr()?;
+        self.end_stmt()?;
+        self.check_declared_literal(Some(&ty), Some(&value));
+
+        Ok(Const {
+            name,
+            is_pub,
+            is_static,
+            ty,
+            value,
+        })
+    }
+
+    // `<T: Display + Clone, 'a, const N: usize>`, the where clause comes later
+    fn parse_generics(&mut self) -> ParseResult<Generics> {
+        let mut generics = Generics::default();
+        if !self.is("<") {
+            return Ok(generics);
+        }
+
+        let open = self.location();
+        self.next();
+        loop {
+            self.skip_newlines();
+            if self.is(">") {
+                self.next();
+                break;
+            }
+
+            let param = if self.kind() == TokenKind::Lifetime {
+                let name = self.value();
+                self.next();
+                GenericParam::Lifetime { name, bounds: self.parse_optional_bounds()? }
+            } else if self.is("const") {
+                self.next();
+                let name = self.expect_ident()?;
+                self.expect(":")?;
+                GenericParam::Const { name, ty: self.parse_type()? }
+            } else {
+                let name = self.expect_ident()?;
+                let bounds = self.parse_optional_bounds()?;
+                let default = if self.is("=") {
+                    self.next();
+                    Some(self.parse_type()?)
+                } else {
+                    None
+                };
+                GenericParam::Type { name, bounds, default }
+            };
+            generics.params.push(param);
+
+            self.skip_newlines();
+            if self.is(",") {
+                self.next();
+            } else if !self.is(">") {
+                return Err(Box::new(self.error(&format!("Expected `,` or `>`, found {}", self.describe_current()))
+                    .with_secondary(open, "`<` opened here")));
+            }
+        }
+
+        Ok(generics)
+    }
+
+    // `: Display + Clone + 'a` after a generic parameter, empty without the `:`
+    fn parse_optional_bounds(&mut self) -> ParseResult<Vec<Type>> {
+        if !self.is(":") {
+            return Ok(Vec::new());
+        }
+        self.next();
+
+        let mut bounds = Vec::new();
+        loop {
+            if self.is("?") {
+                // `?Sized`
+                let start = self.location();
+                self.next();
+                let name = self.expect_ident()?;
+                bounds.push(Type { kind: TypeKind::Verbatim(format!("?{}", name)), location: self.span(&start) });
+            } else {
+                bounds.push(self.parse_type()?);
+            }
+
+            if !self.is("+") {
+                return Ok(bounds);
+            }
+            self.next();
+        }
+    }
+
+    // `where T: Display, U: Clone` before a body, it can start on the next line
+    fn parse_where_clause(&mut self, generics: &mut Generics) -> ParseResult<()> {
+        let index = self.skip_newlines_from(self.current);
+        if !self.is_at(index, "where") {
+            return Ok(());
+        }
+        self.current = index + 1;
+
+        loop {
+            self.skip_newlines();
+            let ty = self.parse_type()?;
+            if !self.is(":") {
+                return Err(Box::new(self.error(&format!("Expected `:` and bounds, found {}", self.describe_current()))));
+            }
+            let bounds = self.parse_optional_bounds()?;
+            generics.predicates.push(WherePredicate { ty, bounds });
+
+            if !self.is(",") {
+                return Ok(());
+            }
+            self.next();
+            // Trailing comma before the body
+            if self.is_at(self.skip_newlines_from(self.current), "{") {
+                return Ok(());
+            }
+        }
+    }
+
+    // `name<...>(` of a Copper function without a return type
+    fn is_generic_function_name(&self) -> bool {
+        if !self.is_ident() || !self.peek_is("<") {
+            return false;
+        }
+
+        let mut depth = 0;
+        for index in self.current + 1..self.tokens.len() {
+            if self.is_at(index, "<") {
+                depth += 1;
+            } else if self.is_at(index, ">") {
+                depth -= 1;
+                if depth == 0 {
+                    return self.is_at(index + 1, "(");
+                }
+            } else if self.select(index).is_some_and(|t| t.kind == TokenKind::Newline) || self.is_at(index, "{") {
+                return false;
+            }
+        }
+        false
+    }
+
+    // Copper: `func type name(params) { }` or `func name(params) { }`
+    // Rust: `fn name(params) -> type { }`
+    pub fn parse_function(&mut self, is_pub: bool) -> ParseResult<Function> {
+        self.parse_function_or_signature(is_pub, false)
+    }
+
+    // Trait methods can end after their signature, the body is a default
+    fn parse_function_or_signature(&mut self, is_pub: bool, in_trait: bool) -> ParseResult<Function> {
+        let start = self.location();
+        let is_copper = self.is("func");
+        self.next();
+
+        // No return type when the name comes first, `func name<T>(params)`
+        let return_type = if is_copper && !(self.is_ident() && self.peek_is("(")) && !self.is_generic_function_name() {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        let name = self.expect_ident()?;
+        let mut generics = self.parse_generics()?;
+
+        let params = self.parse_params()?;
+
+        let return_type = if !is_copper && self.is("->") {
+            self.next();
+            Some(self.parse_type()?)
+        } else {
+            return_type
+        };
+        self.parse_where_clause(&mut generics)?;
+
+        let body = if in_trait && !self.is_at(self.skip_newlines_from(self.current), "{") {
+            None
+        } else {
+            self.skip_newlines();
+            Some(self.in_scope(Parent::Function(name.clone()), param_vars(&params), Self::parse_block)?)
+        };
+
+        Ok(Function {
+            docs: Vec::new(),
+            name,
+            is_pub,
+            generics,
+            params,
+            return_type,
+            body,
+            is_copper,
+            location: self.span(&start),
+        })
+    }
+
+    pub fn parse_params(&mut self) -> ParseResult<Vec<Param>> {
+        let open = self.location();
+        self.expect("(")?;
+        let mut params = Vec::new();
+
+        loop {
+            self.skip_newlines();
+            if self.is(")") {
+                self.next();
+                break;
+            }
+
+            params.push(self.parse_param()?);
+
+            self.skip_newlines();
+            if self.is(",") {
+                self.next();
+            } else if !self.is(")") {
+                return Err(Box::new(self.error(&format!("Expected `,` or `)`, found {}", self.describe_current()))
+                    .with_secondary(open, "`(` opened here")));
+            }
+        }
+
+        Ok(params)
+    }
+
+    fn parse_param(&mut self) -> ParseResult<Param> {
+        let start = self.location();
+
+        // &self, &'a self, &mut self
+        if self.is("&") {
+            let mut index = self.current + 1;
+            if self.select(index).is_some_and(|t| t.kind == TokenKind::Lifetime) {
+                index += 1;
+            }
+            let mutable = self.is_at(index, "mut");
+            if mutable {
+                index += 1;
+            }
+            if self.is_at(index, "self") {
+                self.current = index + 1;
+                return Ok(Param::SelfParam { reference: true, mutable });
+            }
+        }
+
+        let mutable = self.is("mut");
+        if mutable {
+            self.next();
+        }
+
+        if self.is("self") {
+            self.next();
+            return Ok(Param::SelfParam { reference: false, mutable });
+        }
+
+        let name = self.expect_ident()?;
+        if !self.is(":") {
+            return Err(Box::new(self.error(&format!("Expected `:` and a type for parameter `{}`", name))
+                .with_help(&format!("add a type, e.g. `{}: int`", name))));
+        }
+        self.next();
+        let ty = self.parse_type()?;
+
+        Ok(Param::Named {
+            name,
+            mutable,
+            ty,
+            location: self.span(&start),
+        })
+    }
+
+    pub fn parse_class_definition(&mut self) -> ParseResult<Class> {
+        self.expect("class")?;
+        let name = self.expect_ident()?;
+        let mut generics = self.parse_generics()?;
+
+        let extends = if self.is("extends") {
+            self.next();
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+
+        let mut implements = Vec::new();
+        if self.is("implements") {
+            self.next();
+            implements.push(self.parse_type()?);
+            while self.is(",") {
+                self.next();
+                implements.push(self.parse_type()?);
+            }
+        }
+        self.parse_where_clause(&mut generics)?;
+
+        self.skip_newlines();
+        let open = self.location();
+        self.expect("{")?;
+
+        let mut constants = Vec::new();
+        let mut fields = Vec::new();
+        let mut constructors: Vec<Constructor> = Vec::new();
+        let mut methods = Vec::new();
+
+        self.depth += 1;
+        loop {
+            self.skip_separators();
+            if self.is("}") {
+                self.next();
+                break;
+            }
+            if self.is_eof() {
+                self.depth -= 1;
+                return Err(Box::new(self.unclosed("{", open)));
+            }
+
+            let outer = self.super_uses.replace(Vec::new());
+            let member = self.parse_class_member(&name, extends.is_some());
+            let uses = std::mem::replace(&mut self.super_uses, outer).unwrap_or_default();
+            if let Ok(member) = &member {
+                self.check_super_uses(&name, extends.is_some(), member, uses);
+            }
+
+            match member {
+                Ok(ClassMember::Field(field)) => fields.push(field),
+                Ok(ClassMember::Const(constant)) => constants.push(constant),
+                Ok(ClassMember::Method(method)) => methods.push(method),
+                Ok(ClassMember::Constructor(second)) => match constructors.iter().find(|first| first.name == second.name) {
+                    Some(first) => {
+                        let diagnostic = match second.name.as_str() {
+                            "new" => Diagnostic::error(&format!("Class `{}` already has a constructor", name), second.location)
+                                .with_help(&format!("constructors can't be overloaded, give this one a name: `{}.withName(...)`", name)),
+                            other => Diagnostic::error(&format!("Class `{}` already has a constructor named `{}`", name, other), second.location),
+                        };
+                        let diagnostic = diagnostic
+                            .with_code("E0101")
+                            .with_label("second constructor")
+                            .with_secondary(first.location.clone(), "first constructor defined here");
+                        self.diagnostics.push(diagnostic);
+                    },
+                    None => constructors.push(second),
+                },
+                Err(diagnostic) => self.recover(*diagnostic),
+            }
+        }
+        self.depth -= 1;
+
+        Ok(Class {
+            name,
+            generics,
+            extends,
+            implements,
+            constants,
+            fields,
+            constructors,
+            methods,
+        })
+    }
+
+    // A constructor without `super(...)` builds the parent with `Parent::new()`,
+    // which needs a `Parent()` constructor when the parent is in this file
+    fn check_parent_constructors(&mut self, body: &[Stmt]) {
+        let items = body
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                StmtKind::Item(item) => Some(&item.kind),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let classes = items
+            .iter()
+            .filter_map(|kind| match kind {
+                ItemKind::Class(class) => Some(class),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for class in &classes {
+            let Some(TypeKind::Path { segments, .. }) = class.extends.as_ref().map(|ty| &ty.kind) else {
+                continue;
+            };
+            let Some(parent) = classes.iter().find(|parent| segments.last() == Some(&parent.name)) else {
+                continue;
+            };
+
+            let constructor = parent.constructors.iter().find(|constructor| constructor.name == "new");
+            // `fn new()` written in an `impl` of the parent works too
+            let implemented = items.iter().any(|kind| match kind {
+                ItemKind::Impl(implementation) => {
+                    matches!(&implementation.target.kind, TypeKind::Path { segments, .. } if segments.last() == Some(&parent.name))
+                        && implementation.methods.iter().any(|method| method.name == "new" && method.params.is_empty())
+                },
+                _ => false,
+            });
+            if implemented || constructor.is_some_and(|constructor| constructor.params.is_empty()) {
+                continue;
+            }
+
+            for child in class.constructors.iter().filter(|child| !calls_super(&child.body)) {
+                let diagnostic = Diagnostic::error(&format!("Constructor of `{}` has to call `super(...)`", class.name), child.location.clone())
+                    .with_code("E0104")
+                    .with_label(&format!("`{}` has no constructor without arguments", parent.name));
+                let diagnostic = match constructor {
+                    Some(constructor) => diagnostic
+                        .with_secondary(constructor.location.clone(), &format!("`{}(...)` takes arguments", parent.name))
+                        .with_help(&format!("start the constructor with `super(...)`, passing the arguments of `{}(...)`", parent.name)),
+                    None => diagnostic.with_help(&format!("add a constructor `{}(...)` to `{}`, `super(...)` calls it", parent.name, parent.name)),
+                };
+                self.diagnostics.push(diagnostic);
+            }
+        }
+    }
+
+    // `super` refers to the parent, and `super(...)` builds it in a statement
+    // of its own at the top level of a constructor
+    fn check_super_uses(&mut self, class_name: &str, has_parent: bool, member: &ClassMember, uses: Vec<(LocationData, bool)>) {
+        let top_level = match member {
+            ClassMember::Constructor(constructor) => constructor.body.stmts
+                .iter()
+                .filter_map(|stmt| match &stmt.kind {
+                    StmtKind::Expr { expr: Expr { kind: ExprKind::Call { callee, .. }, .. }, .. } => Some(&callee.location),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            _ => Vec::new(),
+        };
+
+        for (location, called) in uses {
+            if !has_parent {
+                let diagnostic = Diagnostic::error(&format!("`{}` doesn't extend a class, `super` has no parent to refer to", class_name), location)
+                    .with_help("add `extends` and a parent class");
+                self.diagnostics.push(diagnostic);
+            } else if called && !top_level.contains(&&location) {
+                let diagnostic = Diagnostic::error("`super(...)` is only allowed at the top level of a constructor", location)
+                    .with_label("the parent can't be built here")
+                    .with_help("call `super(...)` once, as a statement of the constructor body");
+                self.diagnostics.push(diagnostic);
+            }
+        }
+    }
+
+    fn parse_class_member(&mut self, class_name: &str, has_parent: bool) -> ParseResult<ClassMember> {
+        let start = self.location();
+        let mut docs = self.parse_attributes()?;
+
+        // `#[get]` and `#[set]` are for Copper, other attributes go to the member
+        let (mut getter, mut setter) = (false, false);
+        docs.retain(|line| match line.replace(' ', "").as_str() {
+            "#[get]" => { getter = true; false },
+            "#[set]" => { setter = true; false },
+            "#[get,set]" | "#[set,get]" => { getter = true; setter = true; false },
+            _ => true,
+        });
+
+        // Fields are private unless told otherwise, everything else is public
+        let visibility = if self.is("pub") || self.is("public") {
+            Some(true)
+        } else if self.is("private") {
+            Some(false)
+        } else {
+            None
+        };
+        if visibility.is_some() {
+            self.next();
+        }
+
+        let is_static = self.is("static");
+        if is_static {
+            if self.is_at(self.current + 2, ":") {
+                return Err(Box::new(self.error("Classes can't have static fields")
+                    .with_help("use a constant, `const NAME: type = value`, or a `static` outside of the class")));
+            }
+            self.next();
+        }
+
+        // Methods override the parent's by name, `override` only documents it
+        if self.is("override") {
+            if !has_parent {
+                let diagnostic = self.error(&format!("`{}` doesn't extend a class, there is nothing to override", class_name))
+                    .with_help("remove `override` or add `extends` and a parent class");
+                self.diagnostics.push(diagnostic);
+            }
+            self.next();
+        }
+
+        let is_constructor = self.is(class_name)
+            && (self.peek_is("(") || (self.peek_is(".") && self.is_at(self.current + 3, "(")));
+        let is_field = self.is_ident() && self.peek_is(":");
+        if (getter || setter) && !is_field {
+            return Err(Box::new(self.error("`#[get]` and `#[set]` can only be used on fields")));
+        }
+
+        let member = if self.is("const") {
+            ClassMember::Const(self.parse_const(visibility.unwrap_or(true))?)
+        } else if is_constructor {
+            // Constructor: `Name(params) { ... }` or named, `Name.guest(params) { ... }`
+            self.next();
+            let name = if self.is(".") {
+                self.next();
+                self.expect_ident()?
+            } else {
+                "new".to_string()
+            };
+            let params = self.parse_params()?;
+            self.skip_newlines();
+            let body = self.in_scope(Parent::Method(class_name.to_string()), param_vars(&params), Self::parse_block)?;
+            ClassMember::Constructor(Constructor {
+                docs,
+                name,
+                is_pub: visibility.unwrap_or(true),
+                params,
+                body,
+                location: self.span(&start),
+            })
+        } else if self.is("func") || self.is("fn") {
+            let mut method = self.parse_function(visibility.unwrap_or(true))?;
+            method.docs = docs;
+            ClassMember::Method(method)
+        } else if is_field {
+            // Field: `name: type`
+            let name = self.expect_ident()?;
+            self.next();
+            let ty = self.parse_type()?;
+            let field = Field {
+                docs,
+                name,
+                is_pub: visibility.unwrap_or(false),
+                ty,
+                getter,
+                setter,
+                location: self.span(&start),
+            };
+            if self.is(",") {
+                self.next();
+            }
+            self.end_stmt()?;
+            ClassMember::Field(field)
+        } else {
+            // Method: `type name(params) { ... }`
+            let return_type = self.parse_type()?;
+            let name = self.expect_ident()?;
+            let mut generics = self.parse_generics()?;
+            let params = self.parse_params()?;
+            self.parse_where_clause(&mut generics)?;
+            self.skip_newlines();
+            let body = self.in_scope(Parent::Method(name.clone()), param_vars(&params), Self::parse_block)?;
+            ClassMember::Method(Function {
+                docs,
+                name,
+                is_pub: visibility.unwrap_or(true),
+                generics,
+                params,
+                return_type: Some(return_type),
+                body: Some(body),
+                is_copper: true,
+                location: self.span(&start),
+            })
+        };
+
+        // Static methods are called on the class, `Name::method()`
+        if let ClassMember::Method(method) = &member {
+            if is_static && method.params.iter().any(|param| matches!(param, Param::SelfParam { .. })) {
+                let diagnostic = Diagnostic::error(&format!("Static method `{}` can't take `self`", method.name), method.location.clone())
+                    .with_code("E0100")
+                    .with_help("remove `static`, or `self` from the parameters");
+                self.diagnostics.push(diagnostic);
+            }
+        } else if is_static {
+            let diagnostic = Diagnostic::error("Only methods can be `static`", self.span(&start))
+                .with_code("E0100")
+                .with_help("remove `static`");
+            self.diagnostics.push(diagnostic);
+        }
+
+        Ok(member)
+    }
+
+    // Parses native struct definition
+    pub fn parse_struct_definition(&mut self, is_pub: bool, item_start: usize) -> ParseResult<ItemKind> {
+        self.expect("struct")?;
+        let name = self.expect_ident()?;
+        let mut generics = self.parse_generics()?;
+
+        // Tuple structs are passed through
+        if self.is("(") {
+            return self.parse_verbatim_item(item_start);
+        }
+        self.parse_where_clause(&mut generics)?;
+
+        let fields = if self.is("{") {
+            self.parse_fields()?
+        } else if self.is_terminator() {
+            Vec::new()
+        } else {
+            return Err(Box::new(self.error(&format!("Expected `{{`, found {}", self.describe_current()))));
+        };
+
+        Ok(ItemKind::Struct(Struct {
+            name,
+            is_pub,
+            generics,
+            fields,
+        }))
+    }
+
+    // `{ name: type, ... }` of structs and struct variants, the fields are
+    // separated by `,` or line breaks
+    fn parse_fields(&mut self) -> ParseResult<Vec<Field>> {
+        let open = self.location();
+        self.expect("{")?;
+
+        let mut fields = Vec::new();
+        loop {
+            self.skip_separators();
+            if self.is("}") {
+                self.next();
+                break;
+            }
+            if self.is_eof() {
+                return Err(Box::new(self.unclosed("{", open)));
+            }
+
+            let start = self.location();
+            let docs = self.doc_comments();
+            let is_pub = self.is("pub") || self.is("public");
+            if is_pub {
+                self.next();
+            }
+            let name = self.expect_ident()?;
+            self.expect(":")?;
+            let ty = self.parse_type()?;
+            fields.push(Field {
+                docs,
+                name,
+                is_pub,
+                ty,
+                getter: false,
+                setter: false,
+                location: self.span(&start),
+            });
+
+            if self.is(",") {
+                self.next();
+            } else if !self.is_newline() && !self.is("}") {
+                return Err(Box::new(self.error(&format!("Expected `,` or `}}`, found {}", self.describe_current()))));
+            }
+        }
+
+        Ok(fields)
+    }
+
+    // enum Name { Unit, Tuple(type, ...), Struct { field: type }, Value = 1 }
+    pub fn parse_enum_definition(&mut self, is_pub: bool) -> ParseResult<Enum> {
+        self.expect("enum")?;
+        let name = self.expect_ident()?;
+        let mut generics = self.parse_generics()?;
+        self.parse_where_clause(&mut generics)?;
+        self.skip_newlines();
+        let open = self.location();
+        self.expect("{")?;
+
+        let mut variants = Vec::new();
+        loop {
+            self.skip_separators();
+            if self.is("}") {
+                self.next();
+                break;
+            }
+            if self.is_eof() {
+                return Err(Box::new(self.unclosed("{", open)));
+            }
+
+            let start = self.location();
+            let docs = self.doc_comments();
+            let variant = self.expect_ident()?;
+            let kind = if self.is("(") {
+                VariantKind::Tuple(self.parse_tuple_types()?)
+            } else if self.is("{") {
+                VariantKind::Struct(self.parse_fields()?)
+            } else {
+                VariantKind::Unit
+            };
+            let discriminant = if self.is("=") {
+                self.next();
+                Some(self.parse_expr()?)
+            } else {
+                None
+            };
+            variants.push(Variant {
+                docs,
+                name: variant,
+                kind,
+                discriminant,
+                location: self.span(&start),
+            });
+
+            if self.is(",") {
+                self.next();
+            } else if !self.is_newline() && !self.is("}") {
+                return Err(Box::new(self.error(&format!("Expected `,` or `}}`, found {}", self.describe_current()))));
+            }
+        }
+
+        Ok(Enum {
+            name,
+            is_pub,
+            generics,
+            variants,
+        })
+    }
+
+    // `(type, ...)` of a tuple variant
+    fn parse_tuple_types(&mut self) -> ParseResult<Vec<Type>> {
+        self.expect("(")?;
+        let mut types = Vec::new();
+        loop {
+            if self.is(")") {
+                self.next();
+                break;
+            }
+            types.push(self.parse_type()?);
+            if self.is(",") {
+                self.next();
+            } else if !self.is(")") {
+                return Err(Box::new(self.error(&format!("Expected `,` or `)`, found {}", self.describe_current()))));
+            }
+        }
+        Ok(types)
+    }
+
+    // trait Name<T>: Bounds { func type name(params) [{ default body }] }
+    pub fn parse_trait_definition(&mut self, is_pub: bool) -> ParseResult<Trait> {
+        self.expect("trait")?;
+        let name = self.expect_ident()?;
+        let mut generics = self.parse_generics()?;
+        let bounds = self.parse_optional_bounds()?;
+        self.parse_where_clause(&mut generics)?;
+
+        self.skip_newlines();
+        let open = self.location();
+        self.expect("{")?;
+
+        let mut methods = Vec::new();
+        self.depth += 1;
+        loop {
+            self.skip_separators();
+            if self.is("}") {
+                self.next();
+                break;
+            }
+            if self.is_eof() {
+                self.depth -= 1;
+                return Err(Box::new(self.unclosed("{", open)));
+            }
+
+            let docs = self.doc_comments();
+            if self.is("pub") || self.is("public") {
+                let diagnostic = self.error(&format!("Trait methods can't be `{}`", self.value()))
+                    .with_help("remove it, trait methods are as visible as their trait");
+                self.diagnostics.push(diagnostic);
+                self.next();
+            }
+            if !self.is("func") && !self.is("fn") {
+                let diagnostic = self.error(&format!("Expected method, found {}", self.describe_current()))
+                    .with_note("traits can only contain `func` or `fn` methods");
+                self.recover(diagnostic);
+                continue;
+            }
+            match self.parse_function_or_signature(false, true) {
+                Ok(mut method) => {
+                    method.docs = docs;
+                    methods.push(method);
+                },
+                Err(diagnostic) => self.recover(*diagnostic),
+            }
+        }
+        self.depth -= 1;
+
+        Ok(Trait {
+            name,
+            is_pub,
+            generics,
+            bounds,
+            methods,
+        })
+    }
+
+    // impl Type { } | impl Trait for Type { }
+    pub fn parse_impl_block(&mut self) -> ParseResult<Impl> {
+        self.expect("impl")?;
+        let mut generics = self.parse_generics()?;
+        let first = self.parse_type()?;
+
+        let (trait_name, target) = if self.is("for") {
+            self.next();
+            (Some(first), self.parse_type()?)
+        } else {
+            (None, first)
+        };
+        self.parse_where_clause(&mut generics)?;
+
+        self.skip_newlines();
+        let open = self.location();
+        self.expect("{")?;
+
+        let mut methods = Vec::new();
+        self.depth += 1;
+        loop {
+            self.skip_separators();
+            if self.is("}") {
+                self.next();
+                break;
+            }
+            if self.is_eof() {
+                self.depth -= 1;
+                return Err(Box::new(self.unclosed("{", open)));
+            }
+
+            let docs = self.doc_comments();
+            let is_pub = self.is("pub") || self.is("public");
+            if is_pub {
+                self.next();
+            }
+            if !self.is("func") && !self.is("fn") {
+                let diagnostic = self.error(&format!("Expected method, found {}", self.describe_current()))
+                    .with_note("impl blocks can only contain `func` or `fn` methods");
+                self.recover(diagnostic);
+                continue;
+            }
+            match self.parse_function(is_pub) {
+                Ok(mut method) => {
+                    method.docs = docs;
+                    methods.push(method);
+                },
+                Err(diagnostic) => self.recover(*diagnostic),
+            }
+        }
+        self.depth -= 1;
+
+        Ok(Impl {
+            generics,
+            trait_name,
+            target,
+            methods,
+        })
+    }
+
+    pub fn parse_type(&mut self) -> ParseResult<Type> {
+        let start = self.location();
+
+        let kind = if self.is("&") || self.is("&&") {
+            let double = self.is("&&");
+            self.next();
+            let lifetime = if self.kind() == TokenKind::Lifetime {
+                let lifetime = self.value();
+                self.next();
+                Some(lifetime)
+            } else {
+                None
+            };
+            let mutable = self.is("mut");
+            if mutable {
+                self.next();
+            }
+            let inner = self.parse_type()?;
+            let reference = TypeKind::Reference {
+                lifetime,
+                mutable,
+                inner: Box::new(inner),
+            };
+            if double {
+                TypeKind::Reference {
+                    lifetime: None,
+                    mutable: false,
+                    inner: Box::new(Type {
+                        kind: reference,
+                        location: self.span(&start),
+                    }),
+                }
+            } else {
+                reference
+            }
+        } else if self.is("(") {
+            self.next();
+            let mut types = Vec::new();
+            loop {
+                self.skip_newlines();
+                if self.is(")") {
+                    self.next();
+                    break;
+                }
+                types.push(self.parse_type()?);
+                self.skip_newlines();
+                if self.is(",") {
+                    self.next();
+                } else if !self.is(")") {
+                    return Err(Box::new(self.error(&format!("Expected `,` or `)`, found {}", self.describe_current()))));
+                }
+            }
+            TypeKind::Tuple(types)
+        } else if self.is("[") {
+            self.next();
+            let inner = self.parse_type()?;
+            let len = if self.kind() == TokenKind::Semicolon || self.is(";") {
+                self.next();
+                Some(Box::new(self.parse_expr()?))
+            } else {
+                None
+            };
+            self.expect("]")?;
+            TypeKind::Array {
+                inner: Box::new(inner),
+                len,
+            }
+        } else if self.is("_") {
+            self.next();
+            TypeKind::Infer
+        } else if self.kind() == TokenKind::Lifetime {
+            let lifetime = self.value();
+            self.next();
+            TypeKind::Verbatim(lifetime)
+        } else if self.is("dyn") || self.is("impl") || self.is("fn") {
+            self.parse_verbatim_type()?
+        } else if self.is_ident() || self.is("self") || self.is("Self") || self.is("crate") || self.is("super") {
+            let mut segments = vec![self.value()];
+            self.next();
+            while self.is("::") {
+                self.next();
+                if !self.is_ident() && !self.is("Self") {
+                    return Err(Box::new(self.error(&format!("Expected identifier, found {}", self.describe_current()))));
+                }
+                segments.push(self.value());
+                self.next();
+            }
+
+            if matches!(segments.last().map(String::as_str), Some("Fn" | "FnMut" | "FnOnce")) && self.is("(") {
+                // Closure trait sugar, `Fn(i32) -> i32`
+                self.current -= segments.len() * 2 - 1;
+                self.parse_verbatim_type()?
+            } else {
+                let args = self.parse_type_args()?;
+                match segments.as_slice() {
+                    // `tuple<int, string>`
+                    [name] if name == "tuple" && !args.is_empty() => TypeKind::Tuple(args),
+                    _ => TypeKind::Path { segments, args },
+                }
+            }
+        } else {
+            return Err(Box::new(self.error(&format!("Expected type, found {}", self.describe_current()))));
+        };
+
+        let mut ty = Type {
+            kind,
+            location: self.span(&start),
+        };
+
+        // Fixed size arrays, `int[2][3]` is two arrays of three
+        let mut lens = Vec::new();
+        while self.is("[") {
+            self.next();
+            lens.push(self.parse_expr()?);
+            self.expect("]")?;
+        }
+        for len in lens.into_iter().rev() {
+            ty = Type {
+                kind: TypeKind::Array { inner: Box::new(ty), len: Some(Box::new(len)) },
+                location: self.span(&start),
+            };
+        }
+
+        while self.is("?") {
+            self.next();
+            ty = Type {
+                kind: TypeKind::Optional(Box::new(ty)),
+                location: self.span(&start),
+            };
+        }
+
+        Ok(ty)
+    }
+
+    fn parse_type_args(&mut self) -> ParseResult<Vec<Type>> {
+        let mut args = Vec::new();
+        if !self.is("<") {
+            return Ok(args);
+        }
+
+        self.next();
+        loop {
+            self.skip_newlines();
+            if self.is(">") {
+                self.next();
+                break;
+            }
+            if self.is_ident() && self.peek_is("=") {
+                // `Item = T`
+                let start = self.location();
+                let name = self.expect_ident()?;
+                self.next();
+                let ty = Box::new(self.parse_type()?);
+                args.push(Type { kind: TypeKind::Binding { name, ty }, location: self.span(&start) });
+            } else {
+                args.push(self.parse_type()?);
+            }
+            self.skip_newlines();
+            if self.is(",") {
+                self.next();
+            } else if !self.is(">") {
+                return Err(Box::new(self.error(&format!("Expected `,` or `>`, found {}", self.describe_current()))));
+            }
+        }
+
+        Ok(args)
+    }
+
+    // `dyn A + B`, `impl Fn(i32) -> i32`, `fn(i32) -> i32` are kept as written
+    fn parse_verbatim_type(&mut self) -> ParseResult<TypeKind> {
+        let start = self.current;
+        let mut angle_depth = 0;
+
+        loop {
+            if self.is_eof() || self.is_newline() || self.kind() == TokenKind::Semicolon {
+                break;
+            }
+            if self.is("(") || self.is("[") {
+                self.skip_group()?;
+                continue;
+            }
+            if self.is("<") {
+                angle_depth += 1;
+            } else if self.is(">") {
+                if angle_depth == 0 {
+                    break;
+                }
+                angle_depth -= 1;
+            } else if angle_depth == 0
+                && (self.is(",") || self.is(")") || self.is("]") || self.is("=") || self.is("{") || self.is("}") || self.is("?"))
+            {
+                break;
+            }
+            self.next();
+        }
+
+        let text = self.verbatim(start, self.current)
+            .replace("< ", "<")
+            .replace(" >", ">")
+            .replace(" (", "(");
+        Ok(TypeKind::Verbatim(text))
+    }
+
+    // Patterns are kept as written, except for plain `[mut] name` bindings
+    pub fn parse_pattern(&mut self, stops: &[&str]) -> ParseResult<Pattern> {
+        let start_location = self.location();
+        let start = self.current;
+
+        if (self.is_ident() || self.is("mut")) && !self.is("_") {
+            let mutable = self.is("mut");
+            let index = if mutable { self.current + 1 } else { self.current };
+            let after = self.select(index + 1);
+            let is_binding = self.select(index).is_some_and(is_ident)
+                && after.is_none_or(|t| t.kind == TokenKind::Newline || stops.contains(&t.value.as_str()));
+
+            if is_binding {
+                let name = self.select(index).unwrap().value.clone();
+                self.current = index + 1;
+                return Ok(Pattern {
+                    kind: PatternKind::Ident { name, mutable },
+                    location: self.span(&start_location),
+                });
+            }
+        }
+
+        self.skip_until(stops, true)?;
+        if self.current == start {
+            return Err(Box::new(self.error(&format!("Expected pattern, found {}", self.describe_current()))));
+        }
+
+        Ok(Pattern {
+            kind: PatternKind::Verbatim(self.verbatim(start, self.current)),
+            location: self.span(&start_location),
+        })
+    }
+
+    pub fn parse_expr(&mut self) -> ParseResult<Expr> {
+        self.parse_assign()
+    }
+
+    // Expression in the head of `if`/`while`/`match`/`for`
+    fn parse_expr_no_struct(&mut self) -> ParseResult<Expr> {
+        let previous = self.no_struct_literal;
+        self.no_struct_literal = true;
+        let expr = self.parse_expr();
+        self.no_struct_literal = previous;
+        expr
+    }
+
+    // Expression inside delimiters, where struct literals are allowed again
+    fn parse_expr_nested(&mut self) -> ParseResult<Expr> {
+        let previous = self.no_struct_literal;
+        self.no_struct_literal = false;
+        let expr = self.parse_expr();
+        self.no_struct_literal = previous;
+        expr
+    }
+
+    fn parse_assign(&mut self) -> ParseResult<Expr> {
+        let start = self.location();
+        let target = self.parse_range()?;
+
+        let op = if self.is("=") {
+            None
+        } else if let Some((_, op)) = COMPOUND_ASSIGNMENTS.iter().find(|(sign, _)| self.is(sign)) {
+            Some(*op)
+        } else if self.is(">") && self.peek_is(">=") {
+            self.next();
+            Some(BinaryOp::Shr)
+        } else {
+            return Ok(target);
+        };
+
+        self.next();
+        self.skip_newlines();
+        let value = self.parse_assign()?;
+
+        Ok(Expr::new(ExprKind::Assign {
+            op,
+            target: Box::new(target),
+            value: Box::new(value),
+        }, self.span(&start)))
+    }
+
+    fn parse_range(&mut self) -> ParseResult<Expr> {
+        let start_location = self.location();
+
+        let start = if self.is("..") || self.is("..=") {
+            None
+        } else {
+            let lhs = self.parse_binary(1)?;
+            if !self.is("..") && !self.is("..=") {
+                return Ok(lhs);
+            }
+            Some(Box::new(lhs))
+        };
+
+        let inclusive = self.is("..=");
+        self.next();
+
+        let end = if self.can_begin_expr() {
+            Some(Box::new(self.parse_binary(1)?))
+        } else {
+            None
+        };
+
+        let location = self.span(&start_location);
+        if inclusive && end.is_none() {
+            let diagnostic = Diagnostic::error("Inclusive range with no end", location.clone())
+                .with_label("`..=` needs an end")
+                .with_help("add the end of the range, or use `..` for a range without one");
+            self.diagnostics.push(diagnostic);
+        }
+
+        Ok(Expr::new(ExprKind::Range { start, end, inclusive }, location))
+    }
+
+    fn can_begin_expr(&self) -> bool {
+        if self.is_terminator() || self.is_eof() {
+            return false;
+        }
+        if self.no_struct_literal && self.is("{") {
+            return false;
+        }
+        !matches!(self.value().as_str(), ")" | "]" | "," | "=>" | "=" | "else") || is_literal(self.kind())
+    }
+
+    fn binary_operator(&self) -> Option<(BinaryOp, usize)> {
+        if is_literal(self.kind()) {
+            return None;
+        }
+
+        let op = match self.value().as_str() {
+            "+" => BinaryOp::Add,
+            "-" => BinaryOp::Sub,
+            "*" => BinaryOp::Mul,
+            "/" => BinaryOp::Div,
+            "%" => BinaryOp::Rem,
+            "&&" => BinaryOp::And,
+            "||" => BinaryOp::Or,
+            "&" => BinaryOp::BitAnd,
+            "|" => BinaryOp::BitOr,
+            "^" => BinaryOp::BitXor,
+            "<<" => BinaryOp::Shl,
+            "==" => BinaryOp::Eq,
+            "!=" => BinaryOp::Ne,
+            "<" => BinaryOp::Lt,
+            "<=" => BinaryOp::Le,
+            ">=" => BinaryOp::Ge,
+            ">" if self.peek_is(">=") => return None,
+            ">" if self.peek_is(">") => return Some((BinaryOp::Shr, 2)),
+            ">" => BinaryOp::Gt,
+            _ => return None,
+        };
+
+        if self.increment_operator().is_some() {
+            return None;
+        }
+
+        Some((op, 1))
+    }
+
+    // Precedence climbing over binary operators
+    fn parse_binary(&mut self, min_precedence: u8) -> ParseResult<Expr> {
+        let start = self.location();
+        let mut lhs = self.parse_cast()?;
+
+        while let Some((op, width)) = self.binary_operator() {
+            let precedence = op.precedence();
+            if precedence < min_precedence {
+                break;
+            }
+
+            for _ in 0..width {
+                self.next();
+            }
+            self.skip_newlines();
+
+            let rhs = self.parse_binary(precedence + 1)?;
+            lhs = Expr::new(ExprKind::Binary {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            }, self.span(&start));
+        }
+
+        Ok(lhs)
+    }
+
+    fn parse_cast(&mut self) -> ParseResult<Expr> {
+        let start = self.location();
+        let mut expr = self.parse_unary()?;
+
+        while self.is("as") {
+            self.next();
+            let ty = self.parse_type()?;
+            expr = Expr::new(ExprKind::Cast {
+                expr: Box::new(expr),
+                ty,
+            }, self.span(&start));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> ParseResult<Expr> {
+        let start = self.location();
+
+        let op = match self.value().as_str() {
+            _ if is_literal(self.kind()) => None,
+            "-" => Some(UnaryOp::Neg),
+            "!" => Some(UnaryOp::Not),
+            "*" => Some(UnaryOp::Deref),
+            "&" | "&&" if self.peek_is("mut") => Some(UnaryOp::RefMut),
+            "&" | "&&" => Some(UnaryOp::Ref),
+            _ => None,
+        };
+
+        let Some(op) = op else {
+            return self.parse_postfix();
+        };
+
+        let double = self.is("&&");
+        self.next();
+        if op == UnaryOp::RefMut {
+            self.next();
+        }
+        self.negative_literal = op == UnaryOp::Neg && self.kind() == TokenKind::Number;
+
+        let mut expr = Expr::new(ExprKind::Unary {
+            op,
+            expr: Box::new(self.parse_unary()?),
+        }, self.span(&start));
+
+        if double {
+            expr = Expr::new(ExprKind::Unary {
+                op: UnaryOp::Ref,
+                expr: Box::new(expr),
+            }, self.span(&start));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_postfix(&mut self) -> ParseResult<Expr> {
+        let start = self.location();
+        let mut expr = self.parse_primary()?;
+
+        loop {
+            if self.is("(") {
+                let args = self.parse_args("(", ")")?;
+                expr = Expr::new(ExprKind::Call {
+                    callee: Box::new(expr),
+                    args,
+                }, self.span(&start));
+            } else if self.is("[") {
+                self.next();
+                self.skip_newlines();
+                let index = self.parse_expr_nested()?;
+                self.skip_newlines();
+                self.expect("]")?;
+                expr = Expr::new(ExprKind::Index {
+                    base: Box::new(expr),
+                    index: Box::new(index),
+                }, self.span(&start));
+            } else if self.is("?") {
+                self.next();
+                expr = Expr::new(ExprKind::Try(Box::new(expr)), self.span(&start));
+            } else if self.is(".") {
+                self.next();
+
+                if self.kind() == TokenKind::Number {
+                    // Tuple fields, `pair.0` or `nested.0.1`
+                    for index in self.value().split('.') {
+                        expr = Expr::new(ExprKind::Field {
+                            base: Box::new(expr),
+                            name: index.to_string(),
+                        }, self.span(&start));
                     }
+                    self.next();
+                    continue;
+                }
+
+                let name = if self.is_ident() || self.is("await") {
+                    self.value()
+                } else {
+                    return Err(Box::new(self.error(&format!("Expected field or method name, found {}", self.describe_current()))));
+                };
+                self.next();
+
+                let generics = if self.is("::") && self.peek_is("<") {
+                    self.next();
+                    self.parse_type_args()?
+                } else {
+                    vec![]
+                };
+
+                if self.is("(") {
+                    let args = self.parse_args("(", ")")?;
+                    expr = Expr::new(ExprKind::MethodCall {
+                        receiver: Box::new(expr),
+                        method: name,
+                        generics,
+                        args,
+                    }, self.span(&start));
                 } else {
-                    self.current_impl_target = Some(target_type.clone());
-                    self.append(&format!("impl{} {}{} {{", impl_generics, target_type, type_generics), AppendMode::ForceAppendWithSpace);
+                    expr = Expr::new(ExprKind::Field {
+                        base: Box::new(expr),
+                        name,
+                    }, self.span(&start));
                 }
             } else {
-                self.current_impl_target = Some(target_type.clone());
-                self.append(&format!("impl{} {}{} {{", impl_generics, target_type, type_generics), AppendMode::ForceAppendWithSpace);
+                break;
             }
+        }
 
-            self.is_inside_impl = true;
+        Ok(expr)
+    }
 
-            // Process impl block
-            while let Some(tok) = self.select(self.current + consumed) {
-                consumed += 1;
-                if tok.kind == TokenKind::BraceStart {
-                    break;
-                }
-            }
+    // Comma separated expressions between `open` and `close`
+    fn parse_args(&mut self, open: &str, close: &str) -> ParseResult<Vec<Expr>> {
+        let open_location = self.location();
+        self.expect(open)?;
+        let mut args = Vec::new();
 
-            // Collects methods until the closing brace
-            let mut brace_count = 1;
-            let mut method_tokens = Vec::new();
-            
-            while brace_count > 0 && consumed < self.tokens.len() - self.current {
-                if let Some(tok) = self.select(self.current + consumed) {
-                    consumed += 1;
-                    match tok.kind {
-                        TokenKind::BraceStart => brace_count += 1,
-                        TokenKind::BraceEnd => {
-                            brace_count -= 1;
-                            if brace_count == 0 {
-                                break;
-                            }
-                        },
-                        _ => {}
-                    }
-                    method_tokens.push(tok.clone());
-                }
+        loop {
+            self.skip_newlines();
+            if self.is(close) {
+                self.next();
+                break;
             }
+            // `{key: value}` is a map, typed by the parameter it's passed to
+            if self.is("{") && self.is_map_literal_start() {
+                let start = self.location();
+                let entries = self.parse_map_literal(None, None)?;
+                args.push(Expr::new(ExprKind::Map(entries), self.span(&start)));
+            } else {
+                args.push(self.parse_expr_nested()?);
+            }
+            self.skip_newlines();
+            if self.is(",") {
+                self.next();
+            } else if !self.is(close) {
+                return Err(Box::new(self.error(&format!("Expected `,` or `{}`, found {}", close, self.describe_current()))
+                    .with_secondary(open_location, &format!("`{}` opened here", open))));
+            }
+        }
 
-            self.process_impl_methods(&method_tokens);
-            
-            self.append("}", AppendMode::ForceAppendWithSpace);
-            self.append("\n", AppendMode::ForceAppend);
-            self.is_inside_impl = false;
-            self.current_impl_target = None;
-            
-            return Consumed::consume(consumed.try_into().unwrap());
-        }
-        
-        Consumed::consume(0)
-    }
-
-    fn process_impl_methods(&mut self, tokens: &[Token]) {
-        let mut i = 0;
-        while i < tokens.len() {
-            // Looks for function definitions: [pub] func type name(params) or [pub] fn name(params) -> type
-            if (tokens[i].value == "pub" && i + 1 < tokens.len() && (tokens[i + 1].value == "func" || tokens[i + 1].value == "fn")) ||
-               tokens[i].value == "func" ||
-               tokens[i].value == "fn" {
-                
-                let start_idx = if tokens[i].value == "pub" { i } else { i };
-                let is_copper_func = tokens[start_idx].value == "func" || 
-                    (tokens[start_idx].value == "pub" && start_idx + 1 < tokens.len() && tokens[start_idx + 1].value == "func");
-                let fn_idx = if tokens[i].value == "pub" { 
-                    i + 1
-                } else { 
-                    i 
-                };
-                
-                let is_pub = tokens[start_idx].value == "pub";
-                
-                if is_copper_func {
-                    // Copper Syntax: [pub] func type name(params)
-                    if fn_idx + 2 >= tokens.len() {
-                        i += 1;
-                        continue;
-                    }
-                    
-                    let return_type_token = &tokens[fn_idx + 1];
-                    let method_name_token = &tokens[fn_idx + 2];
-                    
-                    if method_name_token.kind != TokenKind::Identifier {
-                        i += 1;
-                        continue;
-                    }
-                    
-                    let (return_type, data_type) = utils::convert_type_with_marking(&return_type_token.value);
-                    
-                    // Mark data type usage for return types
-                    if let Some(dt) = data_type {
-                        self.uses_data_types = true;
-                        match dt.as_str() {
-                            "json" => self.result.mark_json_usage(),
-                            "xml" => self.result.mark_xml_usage(),
-                            "toml" => self.result.mark_toml_usage(),
-                            _ => {}
-                        }
-                    }
-                    
-                    let method_name = &method_name_token.value;
-                    
-                    // Find parameters 
-                    let mut param_start = fn_idx + 3;
-                    while param_start < tokens.len() && tokens[param_start].kind != TokenKind::ParenthesesStart {
-                        param_start += 1;
-                    }
-                    
-                    if param_start >= tokens.len() {
-                        i += 1;
-                        continue;
-                    }
+        Ok(args)
+    }
 
-                    let mut param_end = param_start + 1;
-                    let mut paren_count = 1;
-                    while param_end < tokens.len() && paren_count > 0 {
-                        match tokens[param_end].kind {
-                            TokenKind::ParenthesesStart => paren_count += 1,
-                            TokenKind::ParametersEnd => paren_count -= 1,
-                            _ => {}
-                        }
-                        param_end += 1;
-                    }
+    // Parses the code of `$name` and `${expression}`. A string whose code has
+    // errors is kept as written once they are reported.
+    fn parse_interpolation(&mut self, parts: Vec<Interpolation>, written: String, location: LocationData) -> ExprKind {
+        let mut string = Vec::new();
+
+        for part in parts {
+            let tokens = match part {
+                Interpolation::Text(text) => {
+                    string.push(StringPart::Text(text));
+                    continue;
+                },
+                Interpolation::Code(tokens) => tokens,
+            };
 
-                    // Finds body start
-                    let mut body_start = param_end;
-                    while body_start < tokens.len() && tokens[body_start].kind != TokenKind::BraceStart {
-                        body_start += 1;
-                    }
+            let mut parser = Parser::new(tokens);
+            parser.skip_newlines();
+            if parser.is_eof() {
+                let diagnostic = Diagnostic::error("Empty interpolation", location)
+                    .with_code("E0100")
+                    .with_label("this string has a `${}` without an expression")
+                    .with_help("write an expression between the braces, or `$$` for a `$`");
+                self.diagnostics.push(diagnostic);
+                return ExprKind::Literal(Literal::String(written));
+            }
 
-                    if body_start >= tokens.len() {
-                        i += 1;
-                        continue;
-                    }
+            let expr = parser.parse_expr().and_then(|expr| {
+                parser.skip_newlines();
+                if parser.is_eof() {
+                    Ok(expr)
+                } else {
+                    Err(Box::new(parser.error(&format!("Expected end of interpolation, found {}", parser.describe_current()))))
+                }
+            });
+            self.diagnostics.append(&mut parser.diagnostics);
+
+            match expr {
+                Ok(expr) => string.push(StringPart::Expr(Box::new(expr))),
+                Err(diagnostic) => {
+                    self.diagnostics.push(*diagnostic);
+                    return ExprKind::Literal(Literal::String(written));
+                },
+            }
+        }
 
-                    let mut body_end = body_start + 1;
-                    let mut brace_count = 1;
-                    while body_end < tokens.len() && brace_count > 0 {
-                        match tokens[body_end].kind {
-                            TokenKind::BraceStart => brace_count += 1,
-                            TokenKind::BraceEnd => brace_count -= 1,
-                            _ => {}
-                        }
-                        body_end += 1;
-                    }
+        // Only `$$` escapes, a plain string
+        if string.iter().all(|part| matches!(part, StringPart::Text(_))) {
+            let (format, _) = format_args(&string, Some(0));
+            return ExprKind::Literal(Literal::String(format));
+        }
 
-                    // Process parameters
-                    let param_tokens: Vec<&Token> = tokens[param_start + 1..param_end - 1]
-                        .iter()
-                        .filter(|t| t.kind != TokenKind::Newline)
-                        .collect();
-
-                    let mut params = Vec::new();
-                    let mut i_param = 0;
-                    
-                    while i_param < param_tokens.len() {
-                        // To copper: name: type
-                        if param_tokens[i_param].kind == TokenKind::Param || 
-                           param_tokens[i_param].kind == TokenKind::Identifier ||
-                           param_tokens[i_param].value == "self" {
-                            
-                            let param_name = param_tokens[i_param].value.clone();
-                            
-                            // Check if it has a type
-                            if i_param + 2 < param_tokens.len() && 
-                               param_tokens[i_param + 1].kind == TokenKind::Colon {
-                                let (param_type, data_type) = utils::convert_type_with_marking(&param_tokens[i_param + 2].value);
-                                
-                                // Mark data type usage when types are used in parameters
-                                if let Some(dt) = data_type {
-                                    self.uses_data_types = true;
-                                    match dt.as_str() {
-                                        "json" => self.result.mark_json_usage(),
-                                        "xml" => self.result.mark_xml_usage(),
-                                        "toml" => self.result.mark_toml_usage(),
-                                        _ => {}
-                                    }
-                                }
-                                
-                                params.push(format!("{}: {}", param_name, param_type));
-                                i_param += 3;
-                            } else {
-                                // Just name (self)
-                                params.push(param_name);
-                                i_param += 1;
-                            }
-                            
-                            // Skip comma if present
-                            if i_param < param_tokens.len() && param_tokens[i_param].kind == TokenKind::Comma {
-                                i_param += 1;
-                            }
-                        } else {
-                            i_param += 1;
-                        }
-                    }
+        ExprKind::Interpolated(string)
+    }
 
-                    // Extract body
-                    let body_tokens: Vec<&Token> = tokens[body_start + 1..body_end - 1]
-                        .iter()
-                        .filter(|t| t.kind != TokenKind::Newline)
-                        .collect();
-
-                    let mut body_str = String::new();
-                    for (idx, token) in body_tokens.iter().enumerate() {
-                        let token_value = &token.value;
-                        
-                        // Add space before token if needed
-                        if idx > 0 && !body_str.ends_with(' ') && !body_str.ends_with('{') && 
-                           !token_value.starts_with(',') && !token_value.starts_with('}') &&
-                           !token_value.starts_with('.') && !token_value.starts_with(';') {
-                            body_str.push(' ');
-                        }
-                        
-                        body_str.push_str(token_value);
-                    }
+    // Rust form of a number literal, `10int32` => `10i32`. Literals that don't
+    // fit in their type are reported.
+    fn number_literal(&mut self, value: &str, negative: bool, location: LocationData) -> String {
+        let (digits, suffix) = split_number(value);
+
+        // Without a suffix the literal has to fit in the widest type
+        if suffix.is_empty() {
+            if !is_float_literal(digits, None) && integer_overflows(digits, negative, (i128::MIN, u128::MAX)) {
+                let diagnostic = Diagnostic::error("Integer literal is too large", location)
+                    .with_code("E0102")
+                    .with_label("does not fit in 128 bits");
+                self.diagnostics.push(diagnostic);
+            }
+            return value.to_string();
+        }
 
-                    // Adjust syntax for Rust
-                    if !body_str.is_empty() && !body_str.ends_with(';') && !body_str.ends_with('}') {
-                        body_str.push(';');
-                    }
+        // Invalid suffixes were reported by the tokenizer
+        let Some(rust_suffix) = number_suffix(suffix) else {
+            return value.to_string();
+        };
+        let ty = suffix;
+
+        if is_float_literal(digits, Some(&rust_suffix)) {
+            if !rust_suffix.starts_with('f') {
+                let diagnostic = Diagnostic::error(&format!("Float literal with integer type `{}`", ty), location)
+                    .with_code("E0102")
+                    .with_label("this is a float")
+                    .with_help("use a float type such as `float32`, or remove the fraction");
+                self.diagnostics.push(diagnostic);
+            } else {
+                let value = digits.replace('_', "").parse::<f64>().unwrap_or(f64::INFINITY);
+                let finite = if rust_suffix == "f32" { (value as f32).is_finite() } else { value.is_finite() };
+                if !finite {
+                    let diagnostic = Diagnostic::error(&format!("Literal out of range for `{}`", ty), location)
+                        .with_code("E0102")
+                        .with_label("this number is too large");
+                    self.diagnostics.push(diagnostic);
+                }
+            }
+        } else {
+            self.check_integer_range(digits, negative, &rust_suffix, ty, location);
+        }
 
-                    // Generate method using Rust syntax
-                    let visibility = if is_pub { "pub " } else { "" };
-                    let param_str = params.join(", ");
-                    
-                    if return_type == "()" {
-                        self.append(&format!("    {}fn {}({}) {{", visibility, method_name, param_str), AppendMode::ForceAppendWithSpace);
-                        self.append(&format!("        {}", body_str), AppendMode::ForceAppendWithSpace);
-                        self.append("    }", AppendMode::ForceAppendWithSpace);
-                    } else {
-                        self.append(&format!("    {}fn {}({}) -> {} {{", visibility, method_name, param_str, return_type), AppendMode::ForceAppendWithSpace);
-                        self.append(&format!("        {}", body_str), AppendMode::ForceAppendWithSpace);
-                        self.append("    }", AppendMode::ForceAppendWithSpace);
-                    }
+        format!("{}{}", digits, rust_suffix)
+    }
 
-                    i = body_end;
-                } else {
-                    // Sintaxe Rust: [pub] fn nome(params) -> tipo
-                    if fn_idx + 1 >= tokens.len() || tokens[fn_idx + 1].kind != TokenKind::Identifier {
-                        i += 1;
-                        continue;
-                    }
+    // Reports integer digits outside the range of `rust_type`, written `ty` in Copper
+    fn check_integer_range(&mut self, digits: &str, negative: bool, rust_type: &str, ty: &str, location: LocationData) {
+        let Some((min, max)) = integer_range(rust_type) else {
+            return;
+        };
+
+        if integer_overflows(digits, negative, (min, max)) {
+            let diagnostic = Diagnostic::error(&format!("Literal out of range for `{}`", ty), location)
+                .with_code("E0102")
+                .with_label(&format!("`{}` goes from {} to {}", ty, min, max))
+                .with_help("use a larger type");
+            self.diagnostics.push(diagnostic);
+        }
+    }
 
-                    let method_name = &tokens[fn_idx + 1].value;
-                    
-                    // Find parameters
-                    let mut param_start = fn_idx + 2;
-                    while param_start < tokens.len() && tokens[param_start].kind != TokenKind::ParenthesesStart {
-                        param_start += 1;
-                    }
-                    
-                    if param_start >= tokens.len() {
-                        i += 1;
-                        continue;
-                    }
+    // An unsuffixed integer literal gets the declared type, `x: int8 = 300`
+    // is out of its range
+    fn check_declared_literal(&mut self, ty: Option<&Type>, value: Option<&Expr>) {
+        let (Some(ty), Some(value)) = (ty, value) else {
+            return;
+        };
+        let TypeKind::Path { segments, args } = &ty.kind else {
+            return;
+        };
+        let ([name], true) = (segments.as_slice(), args.is_empty()) else {
+            return;
+        };
+        let Some(rust_type) = number_suffix(name) else {
+            return;
+        };
+
+        let (literal, negative) = match &value.kind {
+            ExprKind::Unary { op: UnaryOp::Neg, expr } => (&**expr, true),
+            _ => (value, false),
+        };
+        let ExprKind::Literal(Literal::Number(text)) = &literal.kind else {
+            return;
+        };
+
+        let (digits, suffix) = split_number(text);
+        if suffix.is_empty() && !is_float_literal(digits, None) {
+            self.check_integer_range(digits, negative, &rust_type, name, value.location.clone());
+        }
+    }
 
-                    let mut param_end = param_start + 1;
-                    let mut paren_count = 1;
-                    while param_end < tokens.len() && paren_count > 0 {
-                        match tokens[param_end].kind {
-                            TokenKind::ParenthesesStart => paren_count += 1,
-                            TokenKind::ParametersEnd => paren_count -= 1,
-                            _ => {}
-                        }
-                        param_end += 1;
-                    }
+    // Splits `/pattern/flags`, reporting patterns the regex crate would reject at
+    // runtime. Unterminated literals were reported by the tokenizer already.
+    fn regex_literal(&mut self, value: &str, terminated: bool, location: LocationData) -> ExprKind {
+        let body = &value[1..];
+        let (written, flags) = body.rsplit_once('/').unwrap_or((body, ""));
+
+        let mut pattern = String::new();
+        let mut chars = written.chars();
+        while let Some(c) = chars.next() {
+            match (c, chars.clone().next()) {
+                ('\\', Some('/')) => {},
+                ('\\', Some(next)) => {
+                    pattern.push(c);
+                    pattern.push(next);
+                    chars.next();
+                },
+                _ => pattern.push(c),
+            }
+        }
 
-                    // Find return type
-                    let mut return_type = "()".to_string();
-                    let mut body_start = param_end;
-                    
-                    if body_start < tokens.len() && tokens[body_start].value == "->" {
-                        body_start += 1;
-                        if body_start < tokens.len() {
-                            return_type = convert_type(&tokens[body_start].value);
-                            body_start += 1;
-                        }
-                    }
+        let checked = if terminated { check_regex(&pattern, flags) } else { Ok(()) };
+        if let Err(error) = checked {
+            let error = error.to_string();
+            let reason = error.lines().last().unwrap_or_default().trim_start_matches("error: ");
+            let diagnostic = Diagnostic::error(&format!("Invalid regex: {}", reason), location)
+                .with_code("E0103")
+                .with_label("invalid pattern");
+            self.diagnostics.push(diagnostic);
+        }
 
-                    // Find function body
-                    while body_start < tokens.len() && tokens[body_start].kind != TokenKind::BraceStart {
-                        body_start += 1;
-                    }
+        ExprKind::Regex { pattern, flags: flags.to_string() }
+    }
 
-                    if body_start >= tokens.len() {
-                        i += 1;
-                        continue;
-                    }
+    fn parse_label(&mut self) -> Option<String> {
+        if self.kind() == TokenKind::Lifetime {
+            let label = self.value();
+            self.next();
+            Some(label)
+        } else {
+            None
+        }
+    }
 
-                    let mut body_end = body_start + 1;
-                    let mut brace_count = 1;
-                    while body_end < tokens.len() && brace_count > 0 {
-                        match tokens[body_end].kind {
-                            TokenKind::BraceStart => brace_count += 1,
-                            TokenKind::BraceEnd => brace_count -= 1,
-                            _ => {}
+    fn parse_primary(&mut self) -> ParseResult<Expr> {
+        let start = self.location();
+
+        let kind = match self.kind() {
+            TokenKind::Number => {
+                let value = self.value();
+                self.next();
+                let negative = std::mem::take(&mut self.negative_literal);
+                ExprKind::Literal(Literal::Number(self.number_literal(&value, negative, start.clone())))
+            },
+            TokenKind::String => {
+                let token = self.current().cloned().unwrap();
+                self.next();
+                match token.data {
+                    Data::Interpolation(parts) => self.parse_interpolation(parts, token.value, self.previous_location()),
+                    _ => ExprKind::Literal(Literal::String(token.value)),
+                }
+            },
+            TokenKind::Char => {
+                let value = self.value();
+                self.next();
+                ExprKind::Literal(Literal::Char(value))
+            },
+            TokenKind::Regex => {
+                let token = self.current().cloned().unwrap();
+                self.next();
+                self.regex_literal(&token.value, token.data != Data::Unterminated, start.clone())
+            },
+            TokenKind::Lifetime if self.peek_is(":") => {
+                let label = self.parse_label();
+                self.next();
+                match self.value().as_str() {
+                    "loop" | "while" | "for" => return self.parse_loop(label, start),
+                    _ => return Err(Box::new(self.error(&format!("Expected loop after label, found {}", self.describe_current())))),
+                }
+            },
+            _ => match self.value().as_str() {
+                "true" | "false" => {
+                    let value = self.is("true");
+                    self.next();
+                    ExprKind::Literal(Literal::Bool(value))
+                },
+                "(" => {
+                    self.next();
+                    self.skip_newlines();
+                    if self.is(")") {
+                        self.next();
+                        ExprKind::Tuple(vec![])
+                    } else {
+                        let first = self.parse_expr_nested()?;
+                        self.skip_newlines();
+                        if self.is(",") {
+                            let mut items = vec![first];
+                            while self.is(",") {
+                                self.next();
+                                self.skip_newlines();
+                                if self.is(")") {
+                                    break;
+                                }
+                                items.push(self.parse_expr_nested()?);
+                                self.skip_newlines();
+                            }
+                            self.expect(")")?;
+                            ExprKind::Tuple(items)
+                        } else {
+                            self.expect(")")?;
+                            ExprKind::Paren(Box::new(first))
                         }
-                        body_end += 1;
                     }
-
-                    // Extract parameters
-                    let param_tokens: Vec<&Token> = tokens[param_start + 1..param_end - 1]
-                        .iter()
-                        .filter(|t| t.kind != TokenKind::Newline)
-                        .collect();
-
-                    let mut params = Vec::new();
-                    let mut current_param = String::new();
-                    
-                    for token in param_tokens {
-                        match token.kind {
-                            TokenKind::Comma => {
-                                if !current_param.trim().is_empty() {
-                                    params.push(current_param.trim().to_string());
-                                    current_param.clear();
+                },
+                "[" => {
+                    self.next();
+                    self.skip_newlines();
+                    if self.is("]") {
+                        self.next();
+                        ExprKind::Array(vec![])
+                    } else {
+                        let first = self.parse_expr_nested()?;
+                        if self.kind() == TokenKind::Semicolon {
+                            self.next();
+                            let len = self.parse_expr_nested()?;
+                            self.skip_newlines();
+                            self.expect("]")?;
+                            ExprKind::ArrayRepeat {
+                                value: Box::new(first),
+                                len: Box::new(len),
+                            }
+                        } else {
+                            let mut items = vec![first];
+                            loop {
+                                self.skip_newlines();
+                                if self.is("]") {
+                                    self.next();
+                                    break;
                                 }
-                            },
-                            TokenKind::ParamType => {
-                                current_param.push_str(&convert_type(&token.value));
-                            },
-                            _ => {
-                                if !token.value.trim().is_empty() {
-                                    if !current_param.is_empty() && !current_param.ends_with(' ') && !token.value.starts_with(':') {
-                                        current_param.push(' ');
-                                    }
-                                    current_param.push_str(&token.value);
+                                self.expect(",")?;
+                                self.skip_newlines();
+                                if self.is("]") {
+                                    self.next();
+                                    break;
                                 }
+                                items.push(self.parse_expr_nested()?);
                             }
+                            ExprKind::Array(items)
                         }
                     }
-                    
-                    if !current_param.trim().is_empty() {
-                        params.push(current_param.trim().to_string());
+                },
+                "{" => {
+                    let previous = self.no_struct_literal;
+                    self.no_struct_literal = false;
+                    let block = self.parse_block();
+                    self.no_struct_literal = previous;
+                    ExprKind::Block(block?)
+                },
+                "unsafe" if self.peek_is("{") => {
+                    self.next();
+                    ExprKind::Unsafe(self.parse_block()?)
+                },
+                "|" | "||" | "move" => return self.parse_closure(),
+                "if" => return self.parse_if(),
+                "let" => {
+                    self.next();
+                    let pattern = self.parse_pattern(&["="])?;
+                    self.expect("=")?;
+                    let value = self.parse_binary(1)?;
+                    ExprKind::Let {
+                        pattern,
+                        value: Box::new(value),
                     }
+                },
+                "while" | "loop" | "for" => return self.parse_loop(None, start),
+                "match" => return self.parse_match(),
+                "return" => {
+                    self.next();
+                    let value = if self.can_begin_expr() {
+                        Some(Box::new(self.parse_expr()?))
+                    } else {
+                        None
+                    };
+                    ExprKind::Return(value)
+                },
+                "break" => {
+                    self.next();
+                    let label = self.parse_label();
+                    let value = if self.can_begin_expr() {
+                        Some(Box::new(self.parse_expr()?))
+                    } else {
+                        None
+                    };
+                    ExprKind::Break { label, value }
+                },
+                "continue" => {
+                    self.next();
+                    ExprKind::Continue(self.parse_label())
+                },
+                _ if self.is_ident() || matches!(self.value().as_str(), "self" | "Self" | "crate" | "super") => {
+                    return self.parse_path_expr();
+                },
+                _ => return Err(Box::new(self.error(&format!("Expected expression, found {}", self.describe_current())))),
+            },
+        };
+
+        Ok(Expr::new(kind, self.span(&start)))
+    }
 
-                    // Extract body
-                    let body_tokens: Vec<&Token> = tokens[body_start + 1..body_end - 1]
-                        .iter()
-                        .filter(|t| t.kind != TokenKind::Newline || !t.value.trim().is_empty())
-                        .collect();
+    fn parse_path(&mut self) -> ParseResult<Path> {
+        let mut segments = Vec::new();
 
-                    let mut body_str = String::new();
-                    for (idx, token) in body_tokens.iter().enumerate() {
-                        if idx > 0 && !body_str.ends_with(' ') && !token.value.starts_with(';') {
-                            body_str.push(' ');
-                        }
-                        body_str.push_str(&token.value);
-                    }
+        loop {
+            let name = self.value();
+            self.next();
 
-                    // Generate method
-                    let visibility = if is_pub { "pub " } else { "" };
-                    let param_str = params.join(", ");
-                    
-                    if return_type == "()" {
-                        self.append(&format!("    {}fn {}({}) {{", visibility, method_name, param_str), AppendMode::ForceAppendWithSpace);
-                        self.append(&format!("        {}", body_str), AppendMode::ForceAppendWithSpace);
-                        self.append("    }", AppendMode::ForceAppendWithSpace);
-                    } else {
-                        self.append(&format!("    {}fn {}({}) -> {} {{", visibility, method_name, param_str, return_type), AppendMode::ForceAppendWithSpace);
-                        self.append(&format!("        {}", body_str), AppendMode::ForceAppendWithSpace);
-                        self.append("    }", AppendMode::ForceAppendWithSpace);
-                    }
+            let mut generics = Vec::new();
+            if self.is("::") && self.peek_is("<") {
+                self.next();
+                generics = self.parse_type_args()?;
+            }
+
+            segments.push(PathSegment { name, generics });
 
-                    i = body_end;
+            if self.is("::") {
+                self.next();
+                if !self.is_ident() && !matches!(self.value().as_str(), "self" | "Self" | "super") {
+                    return Err(Box::new(self.error(&format!("Expected identifier, found {}", self.describe_current()))));
                 }
             } else {
-                i += 1;
+                break;
+            }
+        }
+
+        Ok(Path { segments })
+    }
+
+    fn parse_path_expr(&mut self) -> ParseResult<Expr> {
+        let start = self.location();
+        let path = self.parse_path()?;
+
+        // Macro invocation, `name!(...)`
+        let explicit_macro = self.is("!") && (self.peek_is("(") || self.peek_is("[") || self.peek_is("{"));
+        let implicit_macro = self.is("(") && path.as_ident().is_some_and(|name| RUST_MACROS.contains(&name));
+
+        if explicit_macro || implicit_macro {
+            if explicit_macro {
+                self.next();
+            }
+            let (delimiter, close) = match self.value().as_str() {
+                "(" => (Delimiter::Paren, ")"),
+                "[" => (Delimiter::Bracket, "]"),
+                _ => (Delimiter::Brace, "}"),
+            };
+            let mut args = self.parse_macro_args(close)?;
+            let format = FORMAT_MACROS.iter().find(|(name, _)| path.as_ident() == Some(*name));
+            if let (Some((_, index)), MacroArgs::Exprs(exprs)) = (format, &mut args) {
+                lower_format_string(exprs, *index);
             }
+            return Ok(Expr::new(ExprKind::Macro { path, delimiter, args }, self.span(&start)));
         }
+
+        if self.is("{") && !self.no_struct_literal && self.is_struct_literal_start() {
+            return self.parse_struct_literal(path, start);
+        }
+
+        let location = self.span(&start);
+        let called = self.is("(");
+        if let (Some(uses), Some("super")) = (&mut self.super_uses, path.as_ident()) {
+            uses.push((location.clone(), called));
+        }
+
+        Ok(Expr::new(ExprKind::Path(path), location))
+    }
+
+    // Macro arguments are parsed as expressions when possible, and kept as
+    // written otherwise
+    fn parse_macro_args(&mut self, close: &str) -> ParseResult<MacroArgs> {
+        let start = self.current;
+        let open = self.value();
+
+        if let Ok(args) = self.parse_args(&open, close) {
+            return Ok(MacroArgs::Exprs(args));
+        }
+
+        self.current = start;
+        self.skip_group()?;
+        let text = self.verbatim(start + 1, self.current - 1);
+        Ok(MacroArgs::Verbatim(text))
+    }
+
+    fn is_struct_literal_start(&self) -> bool {
+        let index = self.skip_newlines_from(self.current + 1);
+
+        if self.is_at(index, "}") || self.is_at(index, "..") {
+            return true;
+        }
+
+        let field = self.select(index).is_some_and(is_ident);
+        let next = self.skip_newlines_from(index + 1);
+        field && (self.is_at(index + 1, ":") || self.is_at(next, ",") || self.is_at(next, "}"))
     }
 
-    pub fn get_required_dependencies(&self) -> Vec<String> {
-        self.result.get_required_dependencies()
+    // `{}` or `{key: ...`, the key being a single token
+    fn is_map_literal_start(&self) -> bool {
+        let index = self.skip_newlines_from(self.current + 1);
+        self.is_at(index, "}") || self.is_at(index + 1, ":")
     }
 
-    pub fn parse(&mut self) -> String {
+    fn parse_struct_literal(&mut self, path: Path, start: LocationData) -> ParseResult<Expr> {
+        self.expect("{")?;
+        let mut fields = Vec::new();
+        let mut base = None;
+
         loop {
-            if self.eof {
-                // Only add aliases if actually using data types
-                if self.uses_data_types {
-                    self.result.add_data_type_aliases();
+            self.skip_newlines();
+            if self.is("}") {
+                self.next();
+                break;
+            }
+
+            if self.is("..") {
+                self.next();
+                base = Some(Box::new(self.parse_expr_nested()?));
+                self.skip_newlines();
+                self.expect("}")?;
+                break;
+            }
+
+            let name = self.expect_ident()?;
+            let value = if self.is(":") {
+                self.next();
+                self.skip_newlines();
+                Some(self.parse_expr_nested()?)
+            } else {
+                None
+            };
+            fields.push(FieldInit { name, value });
+
+            self.skip_newlines();
+            if self.is(",") {
+                self.next();
+            } else if !self.is("}") {
+                return Err(Box::new(self.error(&format!("Expected `,` or `}}`, found {}", self.describe_current()))));
+            }
+        }
+
+        Ok(Expr::new(ExprKind::StructLiteral { path, fields, base }, self.span(&start)))
+    }
+
+    fn parse_closure(&mut self) -> ParseResult<Expr> {
+        let start = self.location();
+        let is_move = self.is("move");
+        if is_move {
+            self.next();
+        }
+
+        let mut params = Vec::new();
+        if self.is("||") {
+            self.next();
+        } else {
+            self.expect("|")?;
+            loop {
+                if self.is("|") {
+                    self.next();
+                    break;
                 }
-                self.result.write_main_function();
-                break self.result.get().expect("Format Error");
-            }
-    
-            if let Some(token) = self.current() {
-                match token.kind {
-                    TokenKind::Eof => {
-                        self.eof = true;
-                    },
-                    TokenKind::Identifier | TokenKind::Keyword => {
-                        self.parse_mut()
-                            .or(|| self.parse_var())
-                            .or(|| self.parse_type_declaration())
-                            .or(|| self.parse_class_definition())
-                            .or(|| self.parse_struct_definition())
-                            .or(|| self.parse_impl_block())
-                            .or(|| self.parse_function())
-                            .or(|| self.parse_any())
-                            .consume_var(&mut self.current);
-                    },
-                    TokenKind::ParametersEnd | TokenKind::ParametersStart => {
-                        self.parse_function_params()
-                            .consume_var(&mut self.current);
-                    },
-                    TokenKind::ParamType => {
-                        self.append(&convert_type(&self.value()), AppendMode::Append);
-                        self.next();
-                    },
-                    TokenKind::Json | TokenKind::Xml | TokenKind::Toml => {
-                        // Note: We only convert the type, but don't mark as used yet
-                        // Will be marked when actually used in operations
-                        self.append(&convert_type(&self.value()), AppendMode::Append);
-                        self.next();
-                    },
-                    TokenKind::ReturnType => {
-                        self.result.return_type(convert_type(&self.value()));
-                        self.next();
-                    },
-                    TokenKind::BraceStart | TokenKind::BraceEnd => {
-                        self.parse_import()
-                            .or(|| self.parse_function_body())
-                            .or(|| self.parse_any())
-                            .consume_var(&mut self.current);
-                    },
-                    TokenKind::From | TokenKind::ModuleVar | TokenKind::ModulePath | TokenKind::Import => {
-                        self.parse_import()
-                            .or(|| self.parse_any())
-                            .consume_var(&mut self.current);
-                    },
-                    TokenKind::Operator => {
-                        self.parse_operator()
-                            .or(|| self.parse_any())
-                            .consume_var(&mut self.current);
-                    },
-                    TokenKind::Regex => {
-                        self.parse_regex()
-                            .or(|| self.parse_any())
-                            .consume_var(&mut self.current);
-                    },
-                    TokenKind::Struct => {
-                        self.parse_struct_definition()
-                            .or(|| self.parse_any())
-                            .consume_var(&mut self.current);
-                    },
-                    TokenKind::Impl => {
-                        self.parse_impl_block()
-                            .or(|| self.parse_any())
-                            .consume_var(&mut self.current);
-                    },
-                    TokenKind::Trait => {
-                        // For trait, just pass token directly for now
-                        self.parse_any()
-                            .consume_var(&mut self.current);
-                    },
-                    _ => {
-                        self.append(&self.value(), AppendMode::Append);
-                        self.next();
-                    }
+                let pattern = self.parse_pattern(&[",", "|", ":"])?;
+                let ty = if self.is(":") {
+                    self.next();
+                    Some(self.parse_type()?)
+                } else {
+                    None
+                };
+                params.push(ClosureParam { pattern, ty });
+                if self.is(",") {
+                    self.next();
+                } else if !self.is("|") {
+                    return Err(Box::new(self.error(&format!("Expected `,` or `|`, found {}", self.describe_current()))));
                 }
+            }
+        }
+
+        let return_type = if self.is("->") {
+            self.next();
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+
+        let body = if return_type.is_some() {
+            let block_start = self.location();
+            let block = self.parse_block()?;
+            Expr::new(ExprKind::Block(block), self.span(&block_start))
+        } else {
+            self.parse_expr()?
+        };
+
+        Ok(Expr::new(ExprKind::Closure {
+            is_move,
+            params,
+            return_type,
+            body: Box::new(body),
+        }, self.span(&start)))
+    }
+
+    fn parse_if(&mut self) -> ParseResult<Expr> {
+        let start = self.location();
+        self.expect("if")?;
+        let cond = self.parse_expr_no_struct()?;
+        self.skip_newlines();
+        let then = self.parse_block()?;
+
+        // `else` may start on the next line
+        let else_index = self.skip_newlines_from(self.current);
+        let otherwise = if self.is_at(else_index, "else") {
+            self.current = else_index + 1;
+            if self.is("if") {
+                Some(Box::new(self.parse_if()?))
+            } else {
+                self.skip_newlines();
+                let block_start = self.location();
+                let block = self.parse_block()?;
+                Some(Box::new(Expr::new(ExprKind::Block(block), self.span(&block_start))))
+            }
+        } else {
+            None
+        };
+
+        Ok(Expr::new(ExprKind::If {
+            cond: Box::new(cond),
+            then,
+            otherwise,
+        }, self.span(&start)))
+    }
+
+    fn parse_loop(&mut self, label: Option<String>, start: LocationData) -> ParseResult<Expr> {
+        let kind = if self.is("loop") {
+            self.next();
+            self.skip_newlines();
+            ExprKind::Loop {
+                label,
+                body: self.parse_block()?,
+            }
+        } else if self.is("while") {
+            self.next();
+            let cond = self.parse_expr_no_struct()?;
+            self.skip_newlines();
+            ExprKind::While {
+                label,
+                cond: Box::new(cond),
+                body: self.parse_block()?,
+            }
+        } else {
+            self.expect("for")?;
+            // `for (item in items)`, unlike `for (a, b) in pairs`
+            let parenthesized = self.is("(") && self.group_before_block();
+            if parenthesized {
+                self.next();
+            }
+            let pattern = self.parse_pattern(&["in"])?;
+            self.expect("in")?;
+            let iter = if parenthesized {
+                let iter = self.parse_expr_nested()?;
+                self.expect(")")?;
+                iter
+            } else {
+                self.parse_expr_no_struct()?
+            };
+            self.skip_newlines();
+            let body = self.in_scope(Parent::Block, pattern_vars(&pattern), Self::parse_block)?;
+            ExprKind::For {
+                label,
+                pattern,
+                iter: Box::new(iter),
+                body,
+            }
+        };
+
+        Ok(Expr::new(kind, self.span(&start)))
+    }
+
+    fn parse_match(&mut self) -> ParseResult<Expr> {
+        let start = self.location();
+        self.expect("match")?;
+        let expr = self.parse_expr_no_struct()?;
+        self.skip_newlines();
+        let open = self.location();
+        self.expect("{")?;
+
+        let mut arms = Vec::new();
+        loop {
+            self.skip_separators();
+            if self.is("}") {
+                self.next();
+                break;
+            }
+            if self.is_eof() {
+                return Err(Box::new(self.unclosed("{", open)));
+            }
+
+            let arm_start = self.location();
+            let pattern = self.parse_pattern(&["=>", "if"])?;
+            let guard = if self.is("if") {
+                self.next();
+                Some(self.parse_expr()?)
             } else {
-                self.result.write_main_function();
-                break self.result.get().expect("Format Error");
+                None
+            };
+            self.expect("=>")?;
+            self.skip_newlines();
+            let body = self.parse_expr_nested()?;
+
+            if self.is(",") {
+                self.next();
+            } else if !self.is_terminator() && !body.is_block_like() {
+                return Err(Box::new(self.error(&format!("Expected `,` or `}}`, found {}", self.describe_current()))));
+            }
+
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+                location: self.span(&arm_start),
+            });
+        }
+
+        Ok(Expr::new(ExprKind::Match {
+            expr: Box::new(expr),
+            arms,
+        }, self.span(&start)))
+    }
+
+    // `{ "key": value }` and `[value, ...]` literals assigned to variables
+    pub fn parse_json_value(&mut self) -> ParseResult<JsonValue> {
+        self.skip_newlines();
+
+        if self.is("{") {
+            self.next();
+            let mut entries = Vec::new();
+            loop {
+                self.skip_newlines();
+                if self.is("}") {
+                    self.next();
+                    break;
+                }
+
+                let key = match self.kind() {
+                    TokenKind::String => self.value(),
+                    _ if self.is_ident() => format!("\"{}\"", self.value()),
+                    _ => return Err(Box::new(self.error(&format!("Expected JSON key, found {}", self.describe_current())))),
+                };
+                self.next();
+                self.expect(":")?;
+                let value = self.parse_json_value()?;
+                entries.push((key, value));
+
+                self.skip_newlines();
+                if self.is(",") {
+                    self.next();
+                } else if !self.is("}") {
+                    return Err(Box::new(self.error(&format!("Expected `,` or `}}`, found {}", self.describe_current()))));
+                }
+            }
+            return Ok(JsonValue::Object(entries));
+        }
+
+        if self.is("[") {
+            self.next();
+            let mut items = Vec::new();
+            loop {
+                self.skip_newlines();
+                if self.is("]") {
+                    self.next();
+                    break;
+                }
+                items.push(self.parse_json_value()?);
+                self.skip_newlines();
+                if self.is(",") {
+                    self.next();
+                } else if !self.is("]") {
+                    return Err(Box::new(self.error(&format!("Expected `,` or `]`, found {}", self.describe_current()))));
+                }
             }
-    
-            self.check_eof();
+            return Ok(JsonValue::Array(items));
         }
+
+        Ok(JsonValue::Value(Box::new(self.parse_expr_nested()?)))
+    }
+}
+
+// Turns an interpolated format string of a macro into a plain one, its parts
+// becoming arguments after the positional ones
+fn lower_format_string(args: &mut Vec<Expr>, index: usize) {
+    let Some(ExprKind::Interpolated(parts)) = args.get(index).map(|arg| &arg.kind) else {
+        return;
+    };
+
+    // Named arguments, `name = value`, have to stay last
+    let named = args[index + 1..].iter().position(is_named_arg).map_or(args.len(), |position| index + 1 + position);
+    let (format, parts) = format_args(parts, Some(named - index - 1));
+    args[index].kind = ExprKind::Literal(Literal::String(format));
+    args.splice(named..named, parts);
+}
+
+// Whether a constructor calls `super(...)` to build the parent
+fn calls_super(body: &Block) -> bool {
+    body.stmts.iter().any(|stmt| match &stmt.kind {
+        StmtKind::Expr { expr: Expr { kind: ExprKind::Call { callee, .. }, .. }, .. } => {
+            matches!(&callee.kind, ExprKind::Path(path) if path.as_ident() == Some("super"))
+        },
+        _ => false,
+    })
+}
+
+fn is_named_arg(arg: &Expr) -> bool {
+    match &arg.kind {
+        ExprKind::Assign { op: None, target, .. } => matches!(&target.kind, ExprKind::Path(path) if path.as_ident().is_some()),
+        _ => false,
     }
 }
 
-pub fn parse(tokens: Vec<Token>) -> String {
-    let mut parser = Parser::new(tokens);
-    parser.parse()
-}
\ No newline at end of file
+// Literal tokens whose value could be mistaken for punctuation or keywords
+fn is_literal(kind: TokenKind) -> bool {
+    matches!(kind, TokenKind::String | TokenKind::Char | TokenKind::Number | TokenKind::Regex | TokenKind::Comment)
+}
+
+// Names bound by a pattern and whether they are `mut`, only plain bindings are known
+fn pattern_vars(pattern: &Pattern) -> Vec<(String, bool)> {
+    match &pattern.kind {
+        PatternKind::Ident { name, mutable } => vec![(name.clone(), *mutable)],
+        PatternKind::Verbatim(_) => Vec::new(),
+    }
+}
+
+fn param_vars(params: &[Param]) -> Vec<(String, bool)> {
+    params
+        .iter()
+        .filter_map(|param| match param {
+            Param::Named { name, mutable, .. } => Some((name.clone(), *mutable)),
+            Param::SelfParam { .. } => None,
+        })
+        .collect()
+}
+
+fn is_ident(token: &Token) -> bool {
+    !is_literal(token.kind)
+        && !matches!(token.kind, TokenKind::Lifetime | TokenKind::Eof)
+        && token.value.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && token.value.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !RESERVED_WORDS.contains(&token.value.as_str())
+        && !matches!(token.value.as_str(), "self" | "Self" | "crate" | "super")
+}
@@ -8,7 +8,8 @@ pub enum Parent {
 }
 
 pub struct Scope {
-    vars: Vec<String>,
+    // Names declared in the scope, and whether they can be assigned
+    vars: Vec<(String, bool)>,
     moved_vars: Vec<(String, Parent)>,
     parent: Parent,
 }
@@ -22,18 +23,23 @@ impl Scope {
         }
     }
 
-    pub fn add_var(&mut self, var: String) {
-        self.vars.push(var);
+    pub fn add_var(&mut self, var: String, assignable: bool) {
+        self.vars.push((var, assignable));
     }
 
     pub fn add_moved_var(&mut self, var: String, parent: Parent) {
         self.moved_vars.push((var, parent));
     }
 
-    pub fn get_vars(&self) -> &Vec<String> {
+    pub fn get_vars(&self) -> &Vec<(String, bool)> {
         &self.vars
     }
 
+    /// Whether the latest `var` declared in the scope can be assigned
+    pub fn find_var(&self, var: &str) -> Option<bool> {
+        self.vars.iter().rev().find(|(name, _)| name == var).map(|(_, assignable)| *assignable)
+    }
+
     pub fn get_moved_vars(&self) -> &Vec<(String, Parent)> {
         &self.moved_vars
     }
@@ -6,6 +6,12 @@ pub struct Scopes {
     current: usize,
 }
 
+impl Default for Scopes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Scopes {
     pub fn new() -> Self {
         Self {
@@ -37,10 +43,39 @@ impl Scopes {
         self.scopes.get(self.current).unwrap().get_parent()
     }
 
-    pub fn get_current_vars(&self) -> &Vec<String> {
+    pub fn get_current_vars(&self) -> &Vec<(String, bool)> {
         self.scopes.get(self.current).unwrap().get_vars()
     }
 
+    /// Looks `var` up from the current scope outwards. Function and method
+    /// bodies don't see the variables around them, those belong to `main`.
+    pub fn find_var(&self, var: &str) -> Option<bool> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(assignable) = scope.find_var(var) {
+                return Some(assignable);
+            }
+            if matches!(scope.get_parent(), Parent::Function(_) | Parent::Method(_)) {
+                break;
+            }
+        }
+        None
+    }
+
+    /// Whether `var` was declared without `mut` in a block around the current
+    /// one, in the same function. Parameters can be declared again.
+    pub fn is_immutable_outside(&self, var: &str) -> bool {
+        for (index, scope) in self.scopes.iter().enumerate().rev() {
+            let is_function = matches!(scope.get_parent(), Parent::Function(_) | Parent::Method(_));
+            if let Some(assignable) = scope.find_var(var) {
+                return index != self.current && !assignable && !is_function;
+            }
+            if is_function {
+                break;
+            }
+        }
+        false
+    }
+
     pub fn get_current_moved_vars(&self) -> &Vec<(String, Parent)> {
         self.scopes.get(self.current).unwrap().get_moved_vars()
     }
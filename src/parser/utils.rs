@@ -1,4 +1,8 @@
-const COPPER_TYPES : [(&str, &str); 18] = [
+use std::num::{IntErrorKind, ParseIntError};
+
+use regex::RegexBuilder;
+
+const COPPER_TYPES : [(&str, &str); 24] = [
     ("int", "i64"),
     ("float", "f64"),
     ("uint", "u64"),
@@ -17,15 +21,21 @@ const COPPER_TYPES : [(&str, &str); 18] = [
     ("json", "JsonValue"),
     ("xml", "XmlValue"),
     ("toml", "TomlValue"),
+    ("list", "Vec"),
+    ("map", "std::collections::HashMap"),
+    ("set", "std::collections::HashSet"),
+    ("List", "Vec"),
+    ("Map", "std::collections::HashMap"),
+    ("Set", "std::collections::HashSet"),
 ];
 
 pub fn convert_type(value: &str) -> String {
     let mut kind = value.to_string();
     let mut is_optional = false;
 
-    if value.ends_with("?") {
+    if let Some(inner) = value.strip_suffix('?') {
         is_optional = true;
-        kind = value[..value.len() - 1].to_string();
+        kind = inner.to_string();
     }
 
     for (copper, rust) in COPPER_TYPES.iter() {
@@ -47,9 +57,9 @@ pub fn convert_type_with_marking(value: &str) -> (String, Option<String>) {
     let mut is_optional = false;
     let mut data_type_used = None;
 
-    if value.ends_with("?") {
+    if let Some(inner) = value.strip_suffix('?') {
         is_optional = true;
-        kind = value[..value.len() - 1].to_string();
+        kind = inner.to_string();
     }
 
     for (copper, rust) in COPPER_TYPES.iter() {
@@ -72,4 +82,114 @@ pub fn convert_type_with_marking(value: &str) -> (String, Option<String>) {
     }
 
     (kind, data_type_used)
-}
\ No newline at end of file
+}
+
+// Rust types a number literal can have as suffix
+const NUMBER_TYPES: [&str; 14] = [
+    "i8", "i16", "i32", "i64", "i128", "isize",
+    "u8", "u16", "u32", "u64", "u128", "usize",
+    "f32", "f64",
+];
+
+/// Rust suffix of a Copper (`int32`) or Rust (`i32`) number type
+pub fn number_suffix(suffix: &str) -> Option<String> {
+    let rust = convert_type(suffix);
+    NUMBER_TYPES.contains(&rust.as_str()).then_some(rust)
+}
+
+/// Splits a number literal as written into its digits and type suffix,
+/// `0xffuint8` => `("0xff", "uint8")`
+pub fn split_number(value: &str) -> (&str, &str) {
+    let prefixed = ["0x", "0b", "0o"].iter().any(|prefix| value.starts_with(prefix));
+    let hex = value.starts_with("0x");
+    let chars = value.char_indices().collect::<Vec<_>>();
+
+    let start = if prefixed { 2 } else { 0 };
+    for (i, &(index, c)) in chars.iter().enumerate().skip(start) {
+        let next = chars.get(i + 1).map(|(_, c)| *c).unwrap_or_default();
+        let previous = i.checked_sub(1).map(|p| chars[p].1).unwrap_or_default();
+
+        let is_digit = c.is_ascii_digit() || c == '_' || (hex && c.is_ascii_hexdigit());
+        let is_float = !prefixed && (c == '.'
+            || (matches!(c, 'e' | 'E') && (next.is_ascii_digit() || matches!(next, '+' | '-')))
+            || (matches!(c, '+' | '-') && matches!(previous, 'e' | 'E')));
+
+        if !is_digit && !is_float {
+            return value.split_at(index);
+        }
+    }
+
+    (value, "")
+}
+
+/// Whether a literal is a float, `1.5`, `1e3` or `2float32`
+pub fn is_float_literal(digits: &str, rust_suffix: Option<&str>) -> bool {
+    let prefixed = ["0x", "0b", "0o"].iter().any(|prefix| digits.starts_with(prefix));
+    rust_suffix.is_some_and(|suffix| suffix.starts_with('f'))
+        || (!prefixed && digits.contains(['.', 'e', 'E']))
+}
+
+/// Range of values of a Rust integer type
+pub fn integer_range(rust_type: &str) -> Option<(i128, u128)> {
+    let bits = match rust_type.trim_start_matches(['i', 'u']) {
+        "size" => 64,
+        bits => bits.parse::<u32>().ok()?,
+    };
+
+    if rust_type.starts_with('u') {
+        Some((0, u128::MAX >> (128 - bits)))
+    } else {
+        Some((i128::MIN >> (128 - bits), (i128::MAX >> (128 - bits)) as u128))
+    }
+}
+
+/// Value of the digits of an integer literal
+pub fn integer_value(digits: &str) -> Result<u128, ParseIntError> {
+    let digits = digits.replace('_', "");
+    let (radix, digits) = match digits.get(..2) {
+        Some("0x") => (16, &digits[2..]),
+        Some("0b") => (2, &digits[2..]),
+        Some("0o") => (8, &digits[2..]),
+        _ => (10, digits.as_str()),
+    };
+
+    u128::from_str_radix(digits, radix)
+}
+
+/// Whether the digits of an integer literal, negated when `negative`, are
+/// outside the range `(min, max)` of its type. Unsigned types only hold `-0`.
+pub fn integer_overflows(digits: &str, negative: bool, (min, max): (i128, u128)) -> bool {
+    match integer_value(digits) {
+        Ok(value) => value > if negative { min.unsigned_abs() } else { max },
+        Err(error) => *error.kind() == IntErrorKind::PosOverflow,
+    }
+}
+
+/// Regex literal flags and the `RegexBuilder` option each one turns on
+pub const REGEX_FLAGS: [(char, &str); 6] = [
+    ('i', "case_insensitive"),
+    ('m', "multi_line"),
+    ('s', "dot_matches_new_line"),
+    ('x', "ignore_whitespace"),
+    ('U', "swap_greed"),
+    ('R', "crlf"),
+];
+
+/// Compiles a regex literal the way the generated code will, to report
+/// invalid patterns before the program runs
+pub fn check_regex(pattern: &str, flags: &str) -> Result<(), regex::Error> {
+    let mut builder = RegexBuilder::new(pattern);
+    for flag in flags.chars() {
+        match flag {
+            'i' => builder.case_insensitive(true),
+            'm' => builder.multi_line(true),
+            's' => builder.dot_matches_new_line(true),
+            'x' => builder.ignore_whitespace(true),
+            'U' => builder.swap_greed(true),
+            'R' => builder.crlf(true),
+            _ => &mut builder,
+        };
+    }
+
+    builder.build().map(|_| ())
+}
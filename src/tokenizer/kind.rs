@@ -4,8 +4,10 @@ pub enum TokenKind {
     Keyword,
     Operator,
     String,
+    Char,
     Number,
     Comment,
+    DocComment,
     Whitespace,
     Newline,
     For,
@@ -52,6 +54,7 @@ pub enum TokenKind {
     JsonObject,
 }
 
+#[allow(clippy::to_string_trait_impl)]
 impl ToString for TokenKind {
     fn to_string(&self) -> String {
         match self {
@@ -59,8 +62,10 @@ impl ToString for TokenKind {
             TokenKind::Keyword => "Keyword".to_string(),
             TokenKind::Operator => "Operator".to_string(),
             TokenKind::String => "String".to_string(),
+            TokenKind::Char => "Char".to_string(),
             TokenKind::Number => "Number".to_string(),
             TokenKind::Comment => "Comment".to_string(),
+            TokenKind::DocComment => "DocComment".to_string(),
             TokenKind::Whitespace => "Whitespace".to_string(),
             TokenKind::Newline => "Newline".to_string(),
             TokenKind::For => "For".to_string(),
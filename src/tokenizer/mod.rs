@@ -1,3 +1,5 @@
 pub(crate) mod tokens;
 pub(crate) mod kind;
-pub(crate) mod tokenizer;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub(crate) mod tokenizer;
+pub(crate) mod syntax;
\ No newline at end of file
@@ -0,0 +1,254 @@
+//! Lossless syntax tree for tools like formatters and editors. Every byte of
+//! the source ends up in a token or in the trivia around one, so the tree
+//! gives back the exact source text.
+
+use super::{kind::TokenKind, tokenizer::Tokenizer, tokens::{LocationData, Token}};
+
+/// Whitespace, line breaks, comments and skipped text around a token
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    /// `Whitespace`, `Newline`, `Comment`, `DocComment`, or `Unknown` for text the tokenizer skipped
+    pub kind: TokenKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxToken {
+    pub kind: TokenKind,
+    /// The token as written in the source
+    pub text: String,
+    pub location: LocationData,
+    /// Trivia on the lines before the token and before it on its own line
+    pub leading: Vec<Trivia>,
+    /// Trivia after the token up to the end of its line, line break included
+    pub trailing: Vec<Trivia>,
+}
+
+impl SyntaxToken {
+    fn write(&self, text: &mut String) {
+        self.leading.iter().for_each(|trivia| text.push_str(&trivia.text));
+        text.push_str(&self.text);
+        self.trailing.iter().for_each(|trivia| text.push_str(&trivia.text));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    Root,
+    /// `(...)`, `[...]` or `{...}`, the delimiters are its first and last tokens
+    Group,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    /// The source text the node was built from, trivia included
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for token in self.tokens() {
+            token.write(&mut text);
+        }
+        text
+    }
+
+    /// The tokens of the node and of the nodes inside it, in source order
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = Vec::new();
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    fn fmt_indented(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        let indent = "  ".repeat(depth);
+        writeln!(f, "{}{:?}", indent, self.kind)?;
+
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.fmt_indented(f, depth + 1)?,
+                SyntaxElement::Token(token) => {
+                    for trivia in &token.leading {
+                        writeln!(f, "{}  {:?} {:?}", indent, trivia.kind, trivia.text)?;
+                    }
+                    let location = &token.location;
+                    writeln!(f, "{}  {:?} {:?} @{}:{}", indent, token.kind, token.text, location.first_line, location.first_column)?;
+                    for trivia in &token.trailing {
+                        writeln!(f, "{}  {:?} {:?}", indent, trivia.kind, trivia.text)?;
+                    }
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// One line per node, token and trivia, nested groups are indented
+impl std::fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+fn is_trivia(kind: TokenKind) -> bool {
+    matches!(kind, TokenKind::Whitespace | TokenKind::Newline | TokenKind::Comment | TokenKind::DocComment)
+}
+
+// Text between tokens is trivia too, `\r`s and tabs aren't tokenized
+fn gap(text: &str) -> Trivia {
+    let kind = if text.chars().all(char::is_whitespace) { TokenKind::Whitespace } else { TokenKind::Unknown };
+    Trivia { kind, text: text.to_string() }
+}
+
+// Spaces and the `\r` or tabs after them are a single piece of whitespace
+fn push_trivia(pieces: &mut Vec<(Option<Token>, Trivia)>, trivia: Trivia) {
+    match pieces.last_mut() {
+        Some((None, last)) if last.kind == TokenKind::Whitespace && trivia.kind == TokenKind::Whitespace => {
+            last.text.push_str(&trivia.text);
+        },
+        _ => pieces.push((None, trivia)),
+    }
+}
+
+/// Tokenizes `source` without cleaning it up first. The last token is `Eof`,
+/// it holds the trivia at the end of the file.
+pub fn lossless_tokens(source: &str) -> Vec<SyntaxToken> {
+    let tokens = Tokenizer::lossless(source.to_string()).tokenize();
+
+    // Everything in source order, with the text between the tokens as trivia
+    let mut pieces: Vec<(Option<Token>, Trivia)> = Vec::new();
+    let mut position = 0;
+    for token in tokens {
+        let Some(location) = token.location_data.clone() else {
+            continue;
+        };
+        let (start, end) = (location.range.0.max(position), location.range.1.min(source.len()));
+        // The `;` added at the end of the file and the `Eof` are not in the source
+        if start >= end {
+            continue;
+        }
+
+        if start > position {
+            push_trivia(&mut pieces, gap(&source[position..start]));
+        }
+        let trivia = Trivia { kind: token.kind, text: source[start..end].to_string() };
+        match is_trivia(token.kind) {
+            true => push_trivia(&mut pieces, trivia),
+            false => pieces.push((Some(token), trivia)),
+        }
+        position = end;
+    }
+    if position < source.len() {
+        push_trivia(&mut pieces, gap(&source[position..]));
+    }
+
+    let mut result = Vec::new();
+    let mut leading = Vec::new();
+    let mut pieces = pieces.into_iter().peekable();
+    while let Some((token, trivia)) = pieces.next() {
+        let Some(token) = token else {
+            leading.push(trivia);
+            continue;
+        };
+
+        // Trailing trivia stops after the line break
+        let mut trailing = Vec::new();
+        while let Some((None, next)) = pieces.peek() {
+            let newline = next.kind == TokenKind::Newline || next.text.contains('\n');
+            trailing.push(pieces.next().unwrap().1);
+            if newline {
+                break;
+            }
+        }
+
+        result.push(SyntaxToken {
+            kind: token.kind,
+            text: trivia.text,
+            location: token.location_data.unwrap_or_default(),
+            leading: std::mem::take(&mut leading),
+            trailing,
+        });
+    }
+
+    result.push(SyntaxToken {
+        kind: TokenKind::Eof,
+        text: String::new(),
+        location: LocationData::default(),
+        leading,
+        trailing: Vec::new(),
+    });
+    result
+}
+
+/// Builds the syntax tree of `source`, with a node for each bracketed group
+pub fn parse(source: &str) -> SyntaxNode {
+    let mut stack = vec![SyntaxNode { kind: NodeKind::Root, children: Vec::new() }];
+
+    for token in lossless_tokens(source) {
+        match token.text.as_str() {
+            "(" | "[" | "{" => stack.push(SyntaxNode {
+                kind: NodeKind::Group,
+                children: vec![SyntaxElement::Token(token)],
+            }),
+            ")" | "]" | "}" if stack.len() > 1 => {
+                let mut group = stack.pop().unwrap();
+                group.children.push(SyntaxElement::Token(token));
+                stack.last_mut().unwrap().children.push(SyntaxElement::Node(group));
+            },
+            _ => {
+                // Groups still open at the end of the file are closed by it
+                if token.kind == TokenKind::Eof {
+                    while stack.len() > 1 {
+                        let group = stack.pop().unwrap();
+                        stack.last_mut().unwrap().children.push(SyntaxElement::Node(group));
+                    }
+                }
+                stack.last_mut().unwrap().children.push(SyntaxElement::Token(token));
+            },
+        }
+    }
+
+    stack.pop().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trips_source() {
+        let source = "\u{feff}// header\r\nfunc void main() {   \r\n\tx = [1, /* two */ 2]\r\n    println(\"${x} \\\"q\\\"\")  // done\r\n}\n\n/// doc\nname = 'a' @\n  \n";
+        let tree = parse(source);
+
+        assert_eq!(tree.text(), source);
+        assert_eq!(parse(include_str!("../../main.crs")).text(), include_str!("../../main.crs"));
+
+        let main = tree.tokens().into_iter().find(|token| token.text == "main").unwrap();
+        assert_eq!(main.location.first_line, 2);
+        assert_eq!(main.trailing, Vec::new());
+
+        let brace = tree.tokens().into_iter().find(|token| token.text == "{").unwrap();
+        let trailing = brace.trailing.iter().map(|trivia| trivia.kind).collect::<Vec<_>>();
+        assert_eq!(trailing, vec![TokenKind::Whitespace, TokenKind::Newline]);
+
+        let SyntaxElement::Node(body) = &tree.children[4] else {
+            panic!("expected the body of `main`, found {:?}", tree.children[4]);
+        };
+        assert_eq!(body.kind, NodeKind::Group);
+        assert!(body.text().starts_with("{   \r\n\tx = ["));
+    }
+}
@@ -1,6 +1,4 @@
-use std::process::exit;
-
-use crate::{tokenizer::tokens::*, ConsumedTrait};
+use crate::{diagnostics::Diagnostic, parser::utils::{number_suffix, split_number, REGEX_FLAGS}, tokenizer::tokens::*, ConsumedTrait};
 
 use super::{kind::TokenKind, tokens::Token};
 use once_cell::sync::Lazy;
@@ -23,16 +21,16 @@ impl EndToken {
     }
 }
 
-pub(self) const TRAILING_SPACES: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s+$").unwrap());
-pub(self) const COMPOUND_SIGNS: [&'static str; 9] = ["::", "-=", "+=", "/=", "*=", "%=", "&=", "^=", "|="];
-pub(self) const COMPARE_SIGNS: [&'static str; 6] = ["==", "!=", "<=", ">=", ">", "<"];
-pub(self) const ARITHMETIC_SIGNS: [&'static str; 5] = ["+", "-", "*", "/", "%"];
-pub(self) const RANGE_SIGNS: [&'static str; 2] = ["..", "..="];
-pub(self) const OPERATORS: [&'static str; 9] = ["!", "&", "|", "^", "~", ":", "?", ".", "="];
-pub(self) const COMMA_SEPARATORS: [&'static str; 2] = [",", ";"];
-pub(self) const BOOL: [&'static str; 2] = ["true", "false"];
-pub(self) const BOM: u32 = 65279;
-pub(self) const RUST_KEYWORDS: &[&'static str] = &[
+static TRAILING_SPACES: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s+$").unwrap());
+const COMPOUND_SIGNS: [&str; 15] = ["<<=", "::", "->", "=>", "&&", "||", "<<", "-=", "+=", "/=", "*=", "%=", "&=", "^=", "|="];
+ const COMPARE_SIGNS: [&str; 6] = ["==", "!=", "<=", ">=", ">", "<"];
+ const ARITHMETIC_SIGNS: [&str; 5] = ["+", "-", "*", "/", "%"];
+ const RANGE_SIGNS: [&str; 2] = ["..", "..="];
+ const OPERATORS: [&str; 9] = ["!", "&", "|", "^", "~", ":", "?", ".", "="];
+ const COMMA_SEPARATORS: [&str; 2] = [",", ";"];
+ const BOOL: [&str; 2] = ["true", "false"];
+ const BOM: u32 = 65279;
+ const RUST_KEYWORDS: &[&str] = &[
         // Control Flow Keywords
         "if", "else", "match", "loop", "while", "for", "break", "continue", "return",
 
@@ -73,7 +71,7 @@ pub(self) const RUST_KEYWORDS: &[&'static str] = &[
         "abstract", "become", "box", "do", "final", "macro", "override", "priv", "typeof", "unsized", "virtual", "yield", "try",
 ];
 
-pub(self) const COPPER_KEYWORDS: &[&'static str] = &[
+ const COPPER_KEYWORDS: &[&str] = &[
     // Function Definition and Implementation Keywords
     "func", "$init", "$child",
 
@@ -103,12 +101,22 @@ pub(crate) struct Tokenizer {
     seen_import: bool,
     seen_public: bool,
     import_specifier_list: bool,
-    location_data_compensations: Vec<usize>,
+    // Column in the chunk where the token being read starts
+    token_start: usize,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl Tokenizer {
     pub fn new(source: String) -> Self {
-        let mut s = Self {
+        let mut s = Self::lossless(source);
+        s.clean_source();
+        s
+    }
+
+    /// Keeps the source as it is, BOM, `\r`s and trailing whitespace included,
+    /// so the token locations point at the original bytes
+    pub fn lossless(source: String) -> Self {
+        Self {
             source,
             index: 0,
             chunk: String::new(),
@@ -121,12 +129,9 @@ impl Tokenizer {
             seen_import: false,
             seen_public: false,
             import_specifier_list: false,
-            location_data_compensations: vec![],
-        };
-
-        s.clean_source();
-
-        s
+            token_start: 0,
+            diagnostics: vec![],
+        }
     }
 
     pub fn tokenize(&mut self) -> Vec<Token> {
@@ -142,9 +147,12 @@ impl Tokenizer {
             self.chunk_offset = self.index;
 
             while self.chunk_column < self.chunk.len() {
-                let consumed = self.identifier_token()
+                self.token_start = self.chunk_column;
+
+                let consumed = self.string_token()
+                    .or(|| self.identifier_token())
+                    .or(|| self.module_path_token())
                     .or(|| self.number_token())
-                    .or(|| self.string_token())
                     .or(|| self.comment_token())
                     .or(|| self.regex_token())
                     .or(|| self.operator_token())
@@ -157,17 +165,23 @@ impl Tokenizer {
                 }
             }
             
-            // Move to next line
-            self.index = line_end;
+            // Move to next line, strings spanning lines moved the chunk further
+            self.index = line_end.max(self.chunk_offset + self.chunk.len());
         }
         
-        if self.ends.len() > 0 {
-            let last = self.ends.last().unwrap();
-            let location = last.origin.as_ref().unwrap().location_data.clone().unwrap();
+        for end in std::mem::take(&mut self.ends) {
+            let Some(origin) = end.origin else {
+                continue;
+            };
 
-            self.error(&format!("Unterminated token {}\nOrigin: {}:{}", last.value, location.last_line, location.last_column));
+            let diagnostic = Diagnostic::error(&format!("Unclosed delimiter `{}`", origin.value), origin.location_data.unwrap_or_default())
+                .with_code("E0001")
+                .with_label("unclosed delimiter")
+                .with_help(&format!("add the missing `{}`", end.value));
+            self.report(diagnostic);
         }
 
+        self.token_start = self.chunk_column;
         if self.kind() != Some(TokenKind::Newline) {
             self.token(TokenKind::Newline, ";\n".to_string());
         }
@@ -228,9 +242,16 @@ impl Tokenizer {
             for sign in OPERATORS.iter() {
                 if self.chunk.get(self.chunk_column..).unwrap_or_default().starts_with(sign) {
                     if *sign == ":" && self.kind() == Some(TokenKind::Param) {
-                        value.push_str(":");
-                        consumed += sign.len() + 1;
-                        self.chunk_column += sign.len() + 1;
+                        value.push(':');
+                        consumed += sign.len();
+                        self.chunk_column += sign.len();
+
+                        // The parameter type is recognized by the colon right before it
+                        if self.current_char() == ' ' {
+                            consumed += 1;
+                            self.chunk_column += 1;
+                        }
+
                         kind = TokenKind::Colon;
                         break;
                     } else if *sign == "." {
@@ -273,9 +294,7 @@ impl Tokenizer {
             consumed += 1;
 
             kind = TokenKind::CurrencySign;
-        }
-
-        if self.current_char() == '<' || self.current_char() == '>' {
+        } else if self.current_char() == '<' || self.current_char() == '>' {
             value.push(self.current_char());
             self.next_char();
             consumed += 1;
@@ -285,9 +304,7 @@ impl Tokenizer {
                 ">" => TokenKind::AngleEnd,
                 _ => TokenKind::Symbol
             };
-        }
-
-        if self.current_char() == '(' || self.current_char() == ')' {
+        } else if self.current_char() == '(' || self.current_char() == ')' {
             value.push(self.current_char());
             self.next_char();
             consumed += 1;
@@ -313,9 +330,7 @@ impl Tokenizer {
                 },
                 _ => TokenKind::Symbol
             };
-        }
-
-        if self.current_char() == '[' || self.current_char() == ']' {
+        } else if self.current_char() == '[' || self.current_char() == ']' {
             value.push(self.current_char());
             self.next_char();
             consumed += 1;
@@ -331,9 +346,7 @@ impl Tokenizer {
                 },
                 _ => TokenKind::Symbol
             };
-        }
-
-        if self.current_char() == '{' || self.current_char() == '}' {
+        } else if self.current_char() == '{' || self.current_char() == '}' {
             value.push(self.current_char());
             self.next_char();
             consumed += 1;
@@ -357,9 +370,7 @@ impl Tokenizer {
                 },
                 _ => TokenKind::Symbol
             };
-        }
-
-        if COMMA_SEPARATORS.contains(&self.current_char().to_string().as_str()) {
+        } else if COMMA_SEPARATORS.contains(&self.current_char().to_string().as_str()) {
             value.push(self.current_char());
             self.next_char();
             consumed += 1;
@@ -376,7 +387,7 @@ impl Tokenizer {
                             self.seen_import = false;
                         }
 
-                        value.push_str("\n");
+                        value.push('\n');
 
                         TokenKind::Newline
                     } else {
@@ -386,14 +397,17 @@ impl Tokenizer {
                 },
                 _ => TokenKind::Symbol
             };
-        }
-
-        if self.current_char() == '.' {
+        } else if self.current_char() == '.' {
             value.push(self.current_char());
             self.next_char();
             consumed += 1;
 
             kind = TokenKind::Dot;
+        } else if self.current_char() == '#' || self.current_char() == '@' {
+            // Attributes and pattern bindings
+            value.push(self.current_char());
+            self.next_char();
+            consumed += 1;
         }
 
         self.token(kind, value);
@@ -401,72 +415,373 @@ impl Tokenizer {
         Consumed::consume(consumed)
     }
 
+    // Prefix (`b`, `r`, `br`), `#` count and quote of the string or char literal starting here
+    fn string_start(&self) -> Option<(usize, usize, char)> {
+        let rest = self.chunk.get(self.chunk_column..).unwrap_or_default();
+        let prefix = if rest.starts_with("br") { 2 } else if rest.starts_with(['b', 'r']) { 1 } else { 0 };
+        let raw = rest[..prefix].contains('r');
+        let hashes = if raw { rest[prefix..].chars().take_while(|c| *c == '#').count() } else { 0 };
+
+        let mut chars = rest[prefix + hashes..].chars();
+        match chars.next()? {
+            '"' => Some((prefix, hashes, '"')),
+            // `'a'`, `'\n'` and the invalid `''` and `'ab'` are chars, `'a` alone is a lifetime
+            '\'' if !raw => {
+                let is_char = match chars.next() {
+                    Some(c) if c.is_alphanumeric() || c == '_' => {
+                        chars.find(|c| !c.is_alphanumeric() && *c != '_') == Some('\'')
+                    },
+                    next => prefix > 0 || next.is_some_and(|c| !matches!(c, '\n' | '\r')),
+                };
+                is_char.then_some((prefix, 0, '\''))
+            },
+            _ => None,
+        }
+    }
+
     pub fn string_token(&mut self) -> Consumed {
-        let mut consumed = 0;
-        let mut value = String::new();
-        let kind = TokenKind::String;
-        let mut escape = false;
+        let Some((prefix, hashes, quote)) = self.string_start() else {
+            return Consumed::Empty;
+        };
+
+        let opening_length = prefix + hashes + 1;
+        let opening = self.create_location_data(self.token_start, self.token_start + opening_length);
+        let closing = format!("{}{}", quote, "#".repeat(hashes));
+        let raw = self.chunk[self.chunk_column..self.chunk_column + prefix].contains('r');
+        let kind = if quote == '"' { TokenKind::String } else { TokenKind::Char };
 
-        if self.current_char() == '"' {
+        let mut value = String::new();
+        let mut consumed = 0;
+        for _ in 0..opening_length {
             value.push(self.current_char());
             self.next_char();
             consumed += 1;
+        }
 
-            while self.current_char() != '"' || escape {
-                if self.current_char() == '\\' {
-                    escape = !escape;
-                } else {
-                    escape = false;
+        let mut escape = false;
+        let mut terminated = false;
+        // Quotes inside `${...}` belong to the expression
+        let interpolates = quote == '"' && prefix == 0;
+        let mut nesting = 0;
+        let mut nested_string = false;
+        loop {
+            let c = self.current_char();
+
+            // Strings go on over the next lines, chars don't
+            if c == '\0' || (c == '\n' && quote == '\'') {
+                if quote == '"' && self.extend_chunk() {
+                    continue;
                 }
+                break;
+            }
 
-                value.push(self.current_char());
-                self.next_char();
-                consumed += 1;
+            if nesting == 0 && c == quote && !escape && self.chunk[self.chunk_column..].starts_with(&closing) {
+                for _ in 0..closing.len() {
+                    value.push(self.current_char());
+                    self.next_char();
+                    consumed += 1;
+                }
+                terminated = true;
+                break;
             }
 
-            value.push(self.current_char());
+            if interpolates && !escape {
+                match c {
+                    // `$$` and the `${` opening are read as a pair
+                    '$' if nesting == 0 && matches!(self.peek(), '$' | '{') => {
+                        if self.peek() == '{' {
+                            nesting = 1;
+                        }
+                        for _ in 0..2 {
+                            value.push(self.current_char());
+                            self.next_char();
+                            consumed += 1;
+                        }
+                        continue;
+                    },
+                    '"' if nesting > 0 => nested_string = !nested_string,
+                    '{' if nesting > 0 && !nested_string => nesting += 1,
+                    '}' if nesting > 0 && !nested_string => nesting -= 1,
+                    _ => {},
+                }
+            }
+
+            escape = !raw && c == '\\' && !escape;
+            value.push(c);
             self.next_char();
             consumed += 1;
         }
 
-        self.token(kind, value);
+        if !terminated {
+            let name = if quote == '"' { "string" } else { "character" };
+            let diagnostic = Diagnostic::error(&format!("Unterminated {} literal", name), opening.clone())
+                .with_code("E0004")
+                .with_label(&format!("{} starts here", name))
+                .with_help(&format!("add the closing `{}`", closing));
+            self.report(diagnostic);
+        }
+
+        // The token may end on a later line than it starts
+        let lines = self.rebase_chunk();
+        let mut location = self.create_location_data(self.token_start, self.chunk_column);
+        if lines > 0 {
+            location = opening.to(&self.create_location_data(0, self.chunk_column));
+        }
+
+        if quote == '\'' && terminated {
+            self.check_char_literal(&value[opening_length..value.len() - 1], location.clone());
+        }
+
+        let value = value.trim_end_matches('\n').to_string();
+        let parts = if interpolates && terminated { self.interpolation(&value, &opening) } else { None };
+        let token = self.token(kind, value);
+        token.set_location_data(location);
+        if let Some(parts) = parts {
+            token.add_data(Data::Interpolation(parts));
+        }
 
         Consumed::consume(consumed)
     }
 
+    // A char literal holds exactly one character or escape sequence
+    fn check_char_literal(&mut self, content: &str, location: LocationData) {
+        let mut chars = content.chars();
+        let mut count = 0;
+        while let Some(c) = chars.next() {
+            count += 1;
+            match (c, chars.next()) {
+                ('\\', Some('x')) => {
+                    chars.nth(1);
+                },
+                ('\\', Some('u')) => {
+                    chars.find(|c| *c == '}');
+                },
+                ('\\', _) => {},
+                (_, Some(_)) => count += 1,
+                (_, None) => {},
+            }
+        }
+
+        let diagnostic = match count {
+            0 => Diagnostic::error("Empty char literal", location)
+                .with_label("there is no character between the quotes")
+                .with_help("write a character, `'\\''` for a quote"),
+            1 => return,
+            _ => Diagnostic::error("Char literal must be one character", location)
+                .with_label("this has more than one character")
+                .with_help("use double quotes for a string"),
+        };
+        self.report(diagnostic.with_code("E0009"));
+    }
+
+    // Splits a string into its text and the tokens of its `$name` and `${expression}`
+    // parts, `None` when it has neither of them nor `$$`
+    fn interpolation(&mut self, value: &str, opening: &LocationData) -> Option<Vec<Interpolation>> {
+        let chars = value[1..value.len() - 1].chars().collect::<Vec<_>>();
+
+        // Where each char is in the file, for the locations of the interpolated code
+        let mut positions = Vec::with_capacity(chars.len());
+        let (mut line, mut column, mut offset) = (opening.first_line, opening.first_column + 1, opening.range.0 + 1);
+        for c in &chars {
+            positions.push((line, column, offset));
+            if *c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+            offset += c.len_utf8();
+        }
+
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut interpolated = false;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied().unwrap_or('\0');
+
+            if c == '\\' {
+                text.extend(&chars[i..(i + 2).min(chars.len())]);
+                i += 2;
+                continue;
+            }
+            if c != '$' || !(next == '$' || next == '{' || next.is_alphabetic() || next == '_') {
+                text.push(c);
+                i += 1;
+                continue;
+            }
+
+            interpolated = true;
+            if next == '$' {
+                text.push('$');
+                i += 2;
+                continue;
+            }
+
+            let (start, end) = if next == '{' {
+                let Some(end) = closing_brace(&chars, i + 2) else {
+                    let (line, column, offset) = positions[i];
+                    let location = LocationData { first_line: line, first_column: column, last_line: line, last_column: column + 1, range: (offset, offset + 2) };
+                    let diagnostic = Diagnostic::error("Unclosed interpolation", location)
+                        .with_code("E0005")
+                        .with_label("interpolation starts here")
+                        .with_help("add the missing `}` or write `$$` for a `$`");
+                    self.report(diagnostic);
+                    text.extend(&chars[i..]);
+                    break;
+                };
+                (i + 2, end)
+            } else {
+                let length = chars[i + 1..].iter().take_while(|c| c.is_alphanumeric() || **c == '_').count();
+                (i + 1, i + 1 + length)
+            };
+
+            parts.push(Interpolation::Text(std::mem::take(&mut text)));
+            let code = chars[start..end].iter().collect::<String>();
+            parts.push(Interpolation::Code(self.interpolated_tokens(code, positions[start])));
+            i = if next == '{' { end + 1 } else { end };
+        }
+
+        if !interpolated {
+            return None;
+        }
+        if !text.is_empty() {
+            parts.push(Interpolation::Text(text));
+        }
+        parts.retain(|part| *part != Interpolation::Text(String::new()));
+
+        Some(parts)
+    }
+
+    // Tokens of the code of an interpolation, located where it is in the file
+    fn interpolated_tokens(&mut self, code: String, (line, column, offset): (isize, usize, usize)) -> Vec<Token> {
+        let start = LocationData { first_line: line, first_column: column, last_line: line, last_column: column, range: (offset, offset) };
+        let mut tokenizer = Tokenizer::new(code);
+        let mut tokens = tokenizer.tokenize();
+
+        for token in &mut tokens {
+            if let Some(location) = &mut token.location_data {
+                location.relocate(&start);
+            }
+        }
+        for mut diagnostic in tokenizer.diagnostics {
+            diagnostic.primary.location.relocate(&start);
+            for label in &mut diagnostic.secondary {
+                label.location.relocate(&start);
+            }
+            self.report(diagnostic);
+        }
+
+        tokens
+    }
+
     pub fn number_token(&mut self) -> Consumed {
         let mut consumed = 0;
         let mut value = String::new();
         let kind = TokenKind::Number;
 
-        if self.current_char().is_numeric() {
-            while self.current_char().is_numeric() {
-                value.push(self.current_char());
-                self.next_char();
-                consumed += 1;
+        if !self.current_char().is_ascii_digit() {
+            return Consumed::Empty;
+        }
+
+        let mut take = |tokenizer: &mut Self, value: &mut String| {
+            value.push(tokenizer.current_char());
+            tokenizer.next_char();
+            consumed += 1;
+        };
+        let is_digit = |c: char| c.is_ascii_digit() || c == '_';
+
+        if self.current_char() == '0' && matches!(self.peek(), 'x' | 'b' | 'o') {
+            // Digits of every base and the suffix, checked below
+            take(self, &mut value);
+            take(self, &mut value);
+            while self.current_char().is_ascii_alphanumeric() || self.current_char() == '_' {
+                take(self, &mut value);
+            }
+        } else {
+            while is_digit(self.current_char()) {
+                take(self, &mut value);
             }
 
             if self.current_char() == '.' {
-                if self.peek().is_numeric() {
-                    value.push(self.current_char());
-                    self.next_char();
-                    consumed += 1;
-
-                    while self.current_char().is_numeric() {
-                        value.push(self.current_char());
-                        self.next_char();
-                        consumed += 1;
+                if self.peek().is_ascii_digit() {
+                    take(self, &mut value);
+                    while is_digit(self.current_char()) {
+                        take(self, &mut value);
                     }
-                } else if self.peek() != '.' {
-                    self.error(&format!("Invalid float or range: {}", self.peek()));
+                } else if self.peek() != '.' && !self.peek().is_alphabetic() && self.peek() != '_' {
+                    let location = self.create_location_data(self.chunk_column, self.chunk_column + 1);
+                    let diagnostic = Diagnostic::error("Invalid float or range", location)
+                        .with_code("E0003")
+                        .with_label("expected digits or `..` after the dot");
+                    self.report(diagnostic);
+                }
+            }
+
+            // Exponent, `1e9` or `1.5e-3`
+            let after_sign = self.chunk.get(self.chunk_column..).unwrap_or_default().chars().nth(2).unwrap_or_default();
+            if matches!(self.current_char(), 'e' | 'E')
+                && (self.peek().is_ascii_digit() || (matches!(self.peek(), '+' | '-') && after_sign.is_ascii_digit()))
+            {
+                take(self, &mut value);
+                take(self, &mut value);
+                while is_digit(self.current_char()) {
+                    take(self, &mut value);
                 }
             }
+
+            // Type suffix, `10int32` or `10i32`
+            while self.current_char().is_ascii_alphanumeric() || self.current_char() == '_' {
+                take(self, &mut value);
+            }
         }
 
+        self.check_number(&value);
         self.token(kind, value);
-        
+
         Consumed::consume(consumed)
+    }
+
+    // Reports digits that don't belong to the base of a literal and unknown suffixes
+    fn check_number(&mut self, value: &str) {
+        let (digits, suffix) = split_number(value);
+        let location = self.create_location_data(self.token_start, self.chunk_column);
+
+        let (base, valid): (&str, fn(char) -> bool) = match digits.get(..2) {
+            Some("0x") => ("hexadecimal", |c| c.is_ascii_hexdigit()),
+            Some("0b") => ("binary", |c| matches!(c, '0' | '1')),
+            Some("0o") => ("octal", |c| matches!(c, '0'..='7')),
+            _ => ("decimal", |_| true),
+        };
 
+        if base != "decimal" && !digits[2..].chars().any(|c| c != '_') {
+            let diagnostic = Diagnostic::error(&format!("Missing digits after `{}`", &digits[..2]), location)
+                .with_code("E0006")
+                .with_label(&format!("expected {} digits", base));
+            self.report(diagnostic);
+            return;
+        }
+
+        // Out of base digits, or a suffix starting with a digit as in `0b102`
+        let invalid = digits.chars().skip(2).find(|c| *c != '_' && !valid(*c))
+            .or_else(|| suffix.chars().next().filter(|c| c.is_ascii_digit()));
+        if let Some(digit) = invalid.filter(|_| base != "decimal") {
+            let diagnostic = Diagnostic::error(&format!("Invalid digit `{}` in {} literal", digit, base), location)
+                .with_code("E0006")
+                .with_label(&format!("not a {} digit", base));
+            self.report(diagnostic);
+            return;
+        }
+
+        if !suffix.is_empty() && number_suffix(suffix).is_none() {
+            let diagnostic = Diagnostic::error(&format!("Invalid suffix `{}` for number literal", suffix), location)
+                .with_code("E0006")
+                .with_label("invalid suffix")
+                .with_help("use a number type such as `int32`, `uint8` or `float32`");
+            self.report(diagnostic);
+        }
     }
 
     pub fn identifier_token(&mut self) -> Consumed {
@@ -498,10 +813,13 @@ impl Tokenizer {
                 kind = TokenKind::Keyword;
             } else if COPPER_KEYWORDS.contains(&value.as_str()) {
                 match value.as_str() {
-                    "import" => {
+                    "import" if self.starts_import() => {
                         self.seen_import = true;
                         kind = TokenKind::Import;
                     },
+                    "import" => {
+                        kind = TokenKind::Identifier;
+                    },
                     "from" => {
                         kind = TokenKind::From;
                     },
@@ -588,6 +906,25 @@ impl Tokenizer {
 
     }
 
+    // Relative module paths such as `from ./models/user` or `from ../shared`
+    pub fn module_path_token(&mut self) -> Consumed {
+        let mut consumed = 0;
+        let mut value = String::new();
+
+        let after_from = self.last_token().is_some_and(|token| token.kind == TokenKind::From);
+        if self.seen_import && after_from && matches!(self.current_char(), '.' | '/') {
+            while !self.current_char().is_whitespace() && !matches!(self.current_char(), ';' | '\0') {
+                value.push(self.current_char());
+                self.next_char();
+                consumed += 1;
+            }
+
+            self.token(TokenKind::ModulePath, value);
+        }
+
+        Consumed::consume(consumed)
+    }
+
     pub fn whitespace_token(&mut self) -> Consumed {
         let mut consumed = 0;
         let mut value = String::new();
@@ -615,6 +952,13 @@ impl Tokenizer {
         let mut value = String::new();
         let kind = TokenKind::Newline;
 
+        // The statement goes on, the line break is just whitespace
+        if self.current_char() == '\n' && self.continues_line() {
+            self.next_char();
+            self.token(TokenKind::Whitespace, "\n".to_string());
+            return Consumed::consume(1);
+        }
+
         if self.current_char() == '\n' && self.kind() != Some(TokenKind::Newline) {
             match self.kind() {
                 Some(TokenKind::BraceStart) |
@@ -646,10 +990,49 @@ impl Tokenizer {
         Consumed::consume(consumed)
     }
 
+    // Whether the line break at the current char leaves the statement open:
+    // - inside `(...)` and `[...]`
+    // - after an infix operator, a range, `.` or `,`
+    // - before a line starting with `.`, `&&` or `||`
+    fn continues_line(&self) -> bool {
+        let innermost = self.ends.last().map(|end| end.kind);
+        if matches!(innermost, Some(TokenKind::ParenthesesEnd | TokenKind::ParametersEnd | TokenKind::BracketEnd)) {
+            return true;
+        }
+
+        let last = self.tokens
+            .iter()
+            .rposition(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment | TokenKind::DocComment));
+        let ends_open = match last.map(|index| (index, &self.tokens[index])) {
+            Some((_, token)) if matches!(token.kind, TokenKind::Comma | TokenKind::Dot | TokenKind::Range) => true,
+            Some((index, token)) if token.kind == TokenKind::Operator => {
+                let previous = index.checked_sub(1).map(|previous| &self.tokens[previous]);
+                match token.value.as_str() {
+                    "?" | "!" => false,
+                    // `Vec<String>` closes a generic, `a > b` has a space before the `>`
+                    ">" => previous.is_some_and(|previous| previous.kind == TokenKind::Whitespace),
+                    // `count++` and `count--`
+                    "+" | "-" => previous.is_none_or(|previous| previous.value != token.value),
+                    _ => true,
+                }
+            },
+            _ => false,
+        };
+        if ends_open {
+            return true;
+        }
+
+        let next_line = self.source[self.chunk_offset + self.chunk_column + 1..].trim_start_matches([' ', '\t', '\r']);
+        (next_line.starts_with('.') && !next_line.starts_with("..")) || next_line.starts_with("&&") || next_line.starts_with("||")
+    }
+
     pub fn comment_token(&mut self) -> Consumed {
         let mut consumed = 0;
         let mut value = String::new();
-        let kind = TokenKind::Comment;
+
+        if self.current_char() == '/' && self.peek() == '*' {
+            return self.block_comment_token();
+        }
 
         if self.current_char() == '/' && self.peek() == '/' {
             value.push(self.current_char());
@@ -660,88 +1043,197 @@ impl Tokenizer {
             self.next_char();
             consumed += 1;
 
-            while self.current_char() != '\n' {
+            while self.current_char() != '\n' && self.current_char() != '\0' {
                 value.push(self.current_char());
                 self.next_char();
                 consumed += 1;
             }
 
+            // `///` and `//!` document the code, `////` is a plain comment again
+            let is_doc = (value.starts_with("///") && !value.starts_with("////")) || value.starts_with("//!");
+            let kind = if is_doc { TokenKind::DocComment } else { TokenKind::Comment };
             self.token(kind, value);
         }
 
         Consumed::consume(consumed)
     }
+
+    // `/* ... */` comments nest and can go on over several lines
+    fn block_comment_token(&mut self) -> Consumed {
+        let opening = self.create_location_data(self.token_start, self.token_start + 2);
+        let mut value = String::new();
+        let mut consumed = 0;
+        let mut depth = 0;
+
+        loop {
+            let c = self.current_char();
+            if c == '\0' {
+                if self.extend_chunk() {
+                    continue;
+                }
+                break;
+            }
+
+            let delimiter = match (c, self.peek()) {
+                ('/', '*') => Some(1),
+                ('*', '/') => Some(-1),
+                _ => None,
+            };
+            let Some(change) = delimiter else {
+                value.push(c);
+                self.next_char();
+                consumed += 1;
+                continue;
+            };
+
+            for _ in 0..2 {
+                value.push(self.current_char());
+                self.next_char();
+                consumed += 1;
+            }
+            depth += change;
+            if depth == 0 {
+                break;
+            }
+        }
+
+        if depth > 0 {
+            let mut diagnostic = Diagnostic::error("Unterminated block comment", opening.clone())
+                .with_code("E0008")
+                .with_label("comment starts here")
+                .with_help("add the closing `*/`");
+            if depth > 1 {
+                diagnostic = diagnostic.with_note(&format!("block comments nest, {} of them are still open", depth));
+            }
+            self.report(diagnostic);
+        }
+
+        let lines = self.rebase_chunk();
+        let mut location = self.create_location_data(self.token_start, self.chunk_column);
+        if lines > 0 {
+            location = opening.to(&self.create_location_data(0, self.chunk_column));
+        }
+
+        let value = value.trim_end_matches('\n').to_string();
+        let token = self.token(TokenKind::Comment, value);
+        token.set_location_data(location);
+
+        Consumed::consume(consumed)
+    }
     
+    // A `/` starts a regex where an expression is expected, it divides anywhere else
+    fn expects_expression(&self) -> bool {
+        let Some(token) = self.last_token() else {
+            return true;
+        };
+
+        match token.kind {
+            TokenKind::Newline
+            | TokenKind::Comma
+            | TokenKind::Semicolon
+            | TokenKind::Colon
+            | TokenKind::ParenthesesStart
+            | TokenKind::ParametersStart
+            | TokenKind::BracketStart
+            | TokenKind::BraceStart => true,
+            TokenKind::Operator => token.value != "?",
+            TokenKind::Keyword => matches!(token.value.as_str(), "return" | "if" | "while" | "match" | "in" | "else" | "break" | "yield"),
+            _ => false,
+        }
+    }
+
     pub fn regex_token(&mut self) -> Consumed {
         let mut consumed = 0;
         let mut value = String::new();
         let kind = TokenKind::Regex;
-        
-        // Only consider regex if it starts with '/'
-        if self.current_char() != '/' {
-            return Consumed::consume(0);
+
+        // `//` is a comment
+        if self.current_char() != '/' || self.peek() == '/' || !self.expects_expression() {
+            return Consumed::Empty;
         }
-        
-        // Check if it's not a comment (//)
-        if self.peek() == '/' {
-            return Consumed::consume(0);
+
+        value.push(self.current_char());
+        self.next_char();
+        consumed += 1;
+
+        // A `/` ends the pattern unless it's escaped or in a class such as `[/]`
+        let mut escape = false;
+        let mut class = false;
+        let mut terminated = false;
+        while !matches!(self.current_char(), '\n' | '\0') {
+            let c = self.current_char();
+            value.push(c);
+            self.next_char();
+            consumed += 1;
+
+            match c {
+                _ if escape => escape = false,
+                '\\' => escape = true,
+                '[' => class = true,
+                ']' => class = false,
+                '/' if !class => {
+                    terminated = true;
+                    break;
+                },
+                _ => {},
+            }
         }
-        
-        // Process only from current position in chunk
-        let remaining_chunk = &self.chunk[self.chunk_column..];
-        
-        // Check again in chunk if it's a comment
-        if remaining_chunk.starts_with("//") {
-            return Consumed::consume(0);
+
+        if !terminated {
+            let location = self.create_location_data(self.token_start, self.chunk_column);
+            let diagnostic = Diagnostic::error("Unterminated regex literal", location)
+                .with_code("E0002")
+                .with_label("missing closing `/`");
+            self.report(diagnostic);
         }
-        
-        let r = Regex::new(r"^(/)([^/]+)(/)?").unwrap(); // Added ^ for string start
 
-        if let Some(cap) = r.captures(remaining_chunk) {
-            let start = cap.get(1).unwrap().start();
-            let end_cap = cap.get(3);
+        // Flags, `/copper/i`
+        while terminated && self.current_char().is_ascii_alphabetic() {
+            let flag = self.current_char();
+            value.push(flag);
+            self.next_char();
+            consumed += 1;
 
-            if end_cap.is_none() {
-                self.error("Unterminated regex literal");
+            if !REGEX_FLAGS.iter().any(|(known, _)| *known == flag) {
+                let location = self.create_location_data(self.chunk_column - 1, self.chunk_column);
+                let flags = REGEX_FLAGS.iter().map(|(flag, _)| format!("`{}`", flag)).collect::<Vec<_>>();
+                let diagnostic = Diagnostic::error(&format!("Unknown regex flag `{}`", flag), location)
+                    .with_code("E0007")
+                    .with_label("unknown flag")
+                    .with_help(&format!("the flags are {}", flags.join(", ")));
+                self.report(diagnostic);
             }
+        }
 
-            let end = end_cap.unwrap().end();
-            value.push_str(&remaining_chunk[start..end]);
-            consumed = end - start;
-            
-            // Advance chunk_column instead of draining
-            for _ in 0..consumed {
-                self.next_char();
-            }
+        let token = self.token(kind, value);
+        if !terminated {
+            token.add_data(Data::Unterminated);
         }
 
-        self.token(kind, value);
+        Consumed::consume(consumed)
+    }
 
-        Consumed::consume(consumed as isize)
+    pub fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
     }
 
-    pub fn error(&self, message: &str) {
-        let (line, column, _) = self.get_line_and_column(self.chunk_column);
-        eprintln!("Error: {}\nLine: {}:{}", message, line + 1, column + 1); // +1 for 1-indexed line/column
-        exit(1);
+    /// Problems found while tokenizing
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
     }
 
     pub fn value(&self, use_origin: bool) -> Option<String> {
         let token = self.tokens.last();
 
-        if token.is_none() {
-            return None;
-        }
+        let token = token?;
 
-        let token = token.unwrap();
-        let value = token.value.clone();
-        let origin = token.origin.clone();
-    
-        if use_origin && origin.is_some() {
-            return Some(origin.unwrap().value.clone());
+        if use_origin {
+            if let Some(origin) = &token.origin {
+                return Some(origin.value.clone());
+            }
         }
     
-        Some(value)
+        Some(token.value.clone())
     }
 
     pub fn add_to_value(&mut self, value: &str) {
@@ -749,12 +1241,18 @@ impl Tokenizer {
             return;
         }
 
+        let end = self.create_location_data(self.chunk_column, self.chunk_column);
         let token = self.tokens.last_mut().unwrap();
         token.value.push_str(value);
+
+        if let Some(location) = &mut token.location_data {
+            location.last_column = end.last_column;
+            location.range.1 = end.range.1;
+        }
     }
 
     pub fn last_token(&self) -> Option<&Token> {
-        self.tokens.iter().filter(|t| t.kind != TokenKind::Whitespace).last()
+        self.tokens.iter().rfind(|t| t.kind != TokenKind::Whitespace)
     } 
 
     pub fn token(&mut self, kind: TokenKind, value: String) -> &mut Token {
@@ -762,7 +1260,7 @@ impl Tokenizer {
 
         // If the length is 0, we want to modify the last token
         if length == 0 {
-            if self.tokens.len() == 0 {
+            if self.tokens.is_empty() {
                 return self.token(TokenKind::Unknown, " ".to_string());
             }
 
@@ -770,7 +1268,7 @@ impl Tokenizer {
         }
 
         let mut token = Token::new(kind, value, length, Data::None, false);
-        token.set_location_data(self.create_location_data(self.chunk_offset, length));
+        token.set_location_data(self.create_location_data(self.token_start, self.chunk_column));
 
         match kind {
             TokenKind::BraceStart      | 
@@ -787,7 +1285,7 @@ impl Tokenizer {
     }
 
     pub fn skip_end(&mut self) {
-        if self.ends.len() == 0 {
+        if self.ends.is_empty() {
             return;
         }
 
@@ -805,10 +1303,8 @@ impl Tokenizer {
     }
 
     pub fn set_kind(&mut self, kind: TokenKind) {
-        let token = self.tokens.last_mut();
-
-        if token.is_some() {
-            token.unwrap().kind = kind;
+        if let Some(token) = self.tokens.last_mut() {
+            token.kind = kind;
         }
     }
 
@@ -832,173 +1328,217 @@ impl Tokenizer {
         None
     }
 
-    pub fn create_location_data(&self, offset_in_chunk: usize, length: usize) -> LocationData {
-        let last_char = if length > 0 {
-            length - 1
-        } else {
-            0
-        };
-
-        let (first_line, first_column, range_start) = self.get_line_and_column(offset_in_chunk);
-        let (last_line, last_column, end_offset) = self.get_line_and_column(offset_in_chunk + last_char);
-
-        let range = (range_start, if length > 0 { end_offset + 1 } else { end_offset });
+    /// Location of the chunk columns `start..end` in the current line
+    pub fn create_location_data(&self, start: usize, end: usize) -> LocationData {
+        let end = end.max(start).min(self.chunk.len());
+        let start = start.min(end);
+        let first_column = self.chunk[..start].chars().count() + 1;
+        let last_column = self.chunk[..end].chars().count().max(first_column);
 
         LocationData {
-            first_line,
+            first_line: self.chunk_line,
             first_column,
-            last_line,
+            last_line: self.chunk_line,
             last_column,
-            range
+            range: (self.chunk_offset + start, self.chunk_offset + end),
         }
     }
 
-    pub fn get_location_data_compensation(&self, start: usize, mut end: usize) -> usize {
-        let mut total_compensation = 0;
-        let initial_end = end;
-        let mut current = start;
-        let mut iterations = 0;
+    // pub fn next_chunk(&self) -> String {
+    //     self.source.get(self.index..).unwrap_or_default().to_owned()
+    // }
 
-        while start >= end && iterations < 1000 {
-            iterations += 1;
-            
-            if current == end && start != initial_end {
-                break;
-            }
+    pub fn clean_source(&mut self) {
+        if let Some(source) = self.source.strip_prefix(char::from_u32(BOM).unwrap()) {
+            self.source = source.to_string();
+        }
 
-            let compensation = self.location_data_compensations.get(current);
-            
-            if compensation.is_some() {
-                let compensation = compensation.unwrap();
-                total_compensation += compensation;
-                end += compensation;
-            }
+        self.source = TRAILING_SPACES.replace_all(&self.source, "").replace('\r', "");
+    }
 
-            current += 1;
-            
-            // Additional safety condition
-            if current >= self.source.len() + 1000 {
-                break;
-            }
+    // Adds the next line of the source to the chunk, returns false at the end of the file
+    fn extend_chunk(&mut self) -> bool {
+        let next = self.chunk_offset + self.chunk.len();
+        if next >= self.source.len() {
+            return false;
         }
 
-        total_compensation
+        let line_end = self.source[next..].find('\n').map(|pos| next + pos + 1).unwrap_or(self.source.len());
+        self.chunk.push_str(&self.source[next..line_end]);
+        true
     }
 
-    #[allow(unused_assignments)]
-    pub fn get_line_and_column(&self, offset: usize) -> (isize, usize, usize) {
-        let compensation = self.get_location_data_compensation(self.chunk_offset, self.chunk_offset + offset);
-        let mut s = String::new();
-        let mut line_count: usize = 0;
-        let mut column = 0;
-        let mut previous_lines_compensation = 0;
-        let mut column_compensation = 0;
+    // Makes the line the chunk column is on the start of the chunk again, after
+    // `extend_chunk` added lines. Returns how many lines were left behind.
+    fn rebase_chunk(&mut self) -> isize {
+        let Some(line_start) = self.chunk[..self.chunk_column].rfind('\n').map(|pos| pos + 1) else {
+            return 0;
+        };
 
-        if offset == 0 {
-            return (self.chunk_line, self.chunk_column + compensation, self.chunk_offset + compensation);
-        }
+        let lines = self.chunk[..line_start].matches('\n').count() as isize;
+        self.chunk = self.chunk[line_start..].to_string();
+        self.chunk_offset += line_start;
+        self.chunk_column -= line_start;
+        self.chunk_line += lines;
+        lines
+    }
 
-        if offset >= self.chunk.len() {
-            s = self.chunk.clone();
-        } else {
-            let end = match offset {
-                o if o > 0 => o,
-                _ => self.chunk.len()
-            };
+    fn current_char(&self) -> char {
+        // `\0` once the end of the chunk is reached
+        self.chunk.get(self.chunk_column..).and_then(|rest| rest.chars().next()).unwrap_or('\0')
+    }
 
-            s = self.chunk.get(0..end.min(self.chunk.len())).unwrap().to_owned();
+    fn next_char(&mut self) {
+        // Find the next character boundary
+        if let Some((next_pos, _)) = self.chunk.char_indices().find(|(pos, _)| *pos > self.chunk_column) {
+            self.chunk_column = next_pos;
+        } else {
+            // End of string
+            self.chunk_column = self.chunk.len();
         }
+    }
 
-        line_count = self.count_occurrences(&s, "\n");
-        column = self.chunk_column;
+    fn peek(&self) -> char {
+        self.chunk.get(self.chunk_column..).unwrap_or_default().chars().nth(1).unwrap_or_default()
+    }
 
-        if line_count > 0 {
-            let r = s.split("\n").collect::<Vec<&str>>();
-            column = r.last().unwrap().len();
-            previous_lines_compensation = self.get_location_data_compensation(self.chunk_offset, self.chunk_offset + offset - column);
-            
-            column_compensation = self.get_location_data_compensation(self.chunk_offset + offset - previous_lines_compensation - column, self.chunk_offset + offset + previous_lines_compensation);
-        } else {
-            column += s.len();
-            column_compensation = compensation;
-        }
+    // `import` starts an import at the beginning of a statement, when followed by
+    // what it imports. Anywhere else it is an identifier
+    fn starts_import(&self) -> bool {
+        let at_start = self.last_token().is_none_or(|token| {
+            matches!(token.kind, TokenKind::Newline | TokenKind::Semicolon | TokenKind::Public) || token.value == "pub"
+        });
+        let rest = self.chunk.get(self.chunk_column..).unwrap_or_default().trim_start_matches([' ', '\t']);
 
-        (self.chunk_line + line_count as isize, column + column_compensation, self.chunk_offset + offset + compensation)
+        at_start && rest.starts_with(|c: char| c == '{' || c == '*' || c == '_' || c.is_alphabetic())
     }
+}
 
-    pub fn count_occurrences(&self, string: &str, substr: &str) -> usize {
-        if substr.is_empty() {
-            return usize::MAX; // Use usize::MAX to represent infinity
-        }
-        let mut num = 0;
-        let mut pos = 0;
-        
-        while let Some(p) = string[pos..].find(substr) {
-            num += 1;
-            pos += p + 1; // Increment position to continue search
+// Index of the `}` closing an interpolation whose code starts at `start`
+fn closing_brace(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 1;
+    let mut nested_string = false;
+    let mut escape = false;
+
+    for (index, c) in chars.iter().enumerate().skip(start) {
+        match c {
+            '"' if !escape => nested_string = !nested_string,
+            '{' if !nested_string => depth += 1,
+            '}' if !nested_string => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            },
+            _ => {},
         }
-        
-        num
+        escape = *c == '\\' && !escape;
     }
-    
-    // pub fn next_chunk(&self) -> String {
-    //     self.source.get(self.index..).unwrap_or_default().to_owned()
-    // }
 
-    pub fn clean_source(&mut self) {
-        let re = Regex::new(r"\r").unwrap();
-        let mut thus_far = 0;
-        let mut source = &self.source;
-    
-        if source.len() > 0 && source.chars().nth(0).unwrap() as u32 == BOM {
-            self.source = source.chars().skip(1).collect::<String>();
-            source = &self.source;
-            self.location_data_compensations[0] = 1;
-            thus_far += 1;
-        }
-        
-        if TRAILING_SPACES.is_match(&source) {
-            self.source = TRAILING_SPACES.replace_all(&source, "").to_string();
-            source = &self.source;
-            self.chunk_line -= 1;
-            self.location_data_compensations.insert(0, self.location_data_compensations.get(0).unwrap_or(&1) - 1);
-        }
+    None
+}
 
-        for (_, mat) in re.find_iter(&source).enumerate() {
-            let offset = mat.start();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            while self.location_data_compensations.len() < (thus_far + offset) + 1 {
-                self.location_data_compensations.push(0);
-            }
+    fn tokens(source: &str) -> Vec<Token> {
+        Tokenizer::new(source.to_string())
+            .tokenize()
+            .into_iter()
+            .filter(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Newline | TokenKind::Eof))
+            .collect()
+    }
 
-            self.location_data_compensations[thus_far + offset] = 1;
-            thus_far += offset;
-        }
+    #[test]
+    fn test_multi_line_string() {
+        let tokens = tokens("sql = \"SELECT *\nFROM users\"\nnext = 1\n");
+
+        assert_eq!(tokens[2].kind, TokenKind::String);
+        assert_eq!(tokens[2].value, "\"SELECT *\nFROM users\"");
+        let location = tokens[2].location_data.clone().unwrap();
+        assert_eq!((location.first_line, location.first_column, location.last_line, location.last_column), (1, 7, 2, 11));
 
-        self.source = re.replace_all(&source, "").to_string();
+        let next = tokens[3].location_data.clone().unwrap();
+        assert_eq!((tokens[3].value.as_str(), next.first_line, next.first_column), ("next", 3, 1));
     }
 
-    fn current_char(&self) -> char {
-        // Use proper UTF-8 indexing
-        if let Some((_, ch)) = self.chunk.char_indices().find(|(pos, _)| *pos == self.chunk_column) {
-            ch
-        } else {
-            // Fallback for misaligned positions
-            self.chunk.chars().nth(0).unwrap_or('\0')
-        }
+    #[test]
+    fn test_raw_byte_and_char_literals() {
+        let literals = tokens("r#\"say \"hi\"\"# b\"bytes\" br\"\\d\" 'a' '\\'' b'z' 'label")
+            .into_iter()
+            .map(|token| (token.kind, token.value))
+            .collect::<Vec<_>>();
+
+        assert_eq!(literals, vec![
+            (TokenKind::String, "r#\"say \"hi\"\"#".to_string()),
+            (TokenKind::String, "b\"bytes\"".to_string()),
+            (TokenKind::String, "br\"\\d\"".to_string()),
+            (TokenKind::Char, "'a'".to_string()),
+            (TokenKind::Char, "'\\''".to_string()),
+            (TokenKind::Char, "b'z'".to_string()),
+            (TokenKind::Lifetime, "'label".to_string()),
+        ]);
     }
 
-    fn next_char(&mut self) {
-        // Find the next character boundary
-        if let Some((next_pos, _)) = self.chunk.char_indices().find(|(pos, _)| *pos > self.chunk_column) {
-            self.chunk_column = next_pos;
-        } else {
-            // End of string
-            self.chunk_column = self.chunk.len();
-        }
+    #[test]
+    fn test_invalid_char_literals() {
+        let mut tokenizer = Tokenizer::new("a = ''\nb = 'ab'\nc = '\\x41'\nfunc void f<'a>(x: &'a str) {}\n".to_string());
+        tokenizer.tokenize();
+
+        let diagnostics = tokenizer.diagnostics().iter().map(|d| (d.message.as_str(), d.primary.location.first_column)).collect::<Vec<_>>();
+        assert_eq!(diagnostics, vec![("Empty char literal", 5), ("Char literal must be one character", 5)]);
     }
 
-    fn peek(&self) -> char {
-        self.chunk.chars().nth(self.chunk_column + 1).unwrap_or_default()
+    #[test]
+    fn test_invalid_number_literals() {
+        let mut tokenizer = Tokenizer::new("a = 0b102\nb = 0x\nc = 10string\nd = 0xffu8 + 1.5e-3f32\n".to_string());
+        tokenizer.tokenize();
+
+        let messages = tokenizer.diagnostics().iter().map(|d| d.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(messages, vec![
+            "Invalid digit `2` in binary literal",
+            "Missing digits after `0x`",
+            "Invalid suffix `string` for number literal",
+        ]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_regex_or_division() {
+        let regexes = tokens("q = a / b / c\nh = (a + b) / 2\np = /usr\\/[a-z/]+/i\nif /^a/.is_match(s) {}\n")
+            .into_iter()
+            .filter(|token| token.kind == TokenKind::Regex)
+            .map(|token| token.value)
+            .collect::<Vec<_>>();
+
+        assert_eq!(regexes, vec!["/usr\\/[a-z/]+/i", "/^a/"]);
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let mut tokenizer = Tokenizer::new("x = 1\ny = r#\"never closed\"\nz = 2\n".to_string());
+        tokenizer.tokenize();
+
+        let diagnostic = &tokenizer.diagnostics()[0];
+        assert_eq!(diagnostic.message, "Unterminated string literal");
+        assert_eq!(diagnostic.help.as_deref(), Some("add the closing `\"#`"));
+        let location = &diagnostic.primary.location;
+        assert_eq!((location.first_line, location.first_column, location.last_column), (2, 5, 7));
+    }
+
+    #[test]
+    fn test_comments() {
+        let comments = tokens("/* outer /* inner */\nstill outer */ x = 1\n/// doc\n//! module\n//// plain\n")
+            .into_iter()
+            .filter(|token| matches!(token.kind, TokenKind::Comment | TokenKind::DocComment))
+            .map(|token| (token.kind, token.value, token.location_data.unwrap().first_line))
+            .collect::<Vec<_>>();
+
+        assert_eq!(comments, vec![
+            (TokenKind::Comment, "/* outer /* inner */\nstill outer */".to_string(), 1),
+            (TokenKind::DocComment, "/// doc".to_string(), 3),
+            (TokenKind::DocComment, "//! module".to_string(), 4),
+            (TokenKind::Comment, "//// plain".to_string(), 5),
+        ]);
+    }
+}
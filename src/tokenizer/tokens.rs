@@ -1,12 +1,26 @@
+use serde::{Deserialize, Serialize};
+
 use super::kind::TokenKind;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Data {
     None,
     String(String),
+    /// Pieces of an interpolated string, `"Hi $name"`
+    Interpolation(Vec<Interpolation>),
+    /// A regex missing its closing `/`, reported by the tokenizer already
+    Unterminated,
 }
 
 #[derive(Debug, PartialEq, Clone)]
+pub enum Interpolation {
+    /// Text as written, with `$$` already turned into `$`
+    Text(String),
+    /// Tokens of `$name` or `${expression}`
+    Code(Vec<Token>),
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
 pub struct LocationData {
     pub(crate) first_line: isize,
     pub(crate) first_column: usize,
@@ -15,6 +29,33 @@ pub struct LocationData {
     pub(crate) range: (usize, usize)
 }
 
+impl LocationData {
+    /// Returns the location spanning from the start of `self` to the end of `end`
+    pub fn to(&self, end: &LocationData) -> LocationData {
+        LocationData {
+            first_line: self.first_line,
+            first_column: self.first_column,
+            last_line: end.last_line,
+            last_column: end.last_column,
+            range: (self.range.0, end.range.1.max(self.range.0)),
+        }
+    }
+
+    /// Moves a location of code that was tokenized on its own to where that
+    /// code starts in the file
+    pub fn relocate(&mut self, start: &LocationData) {
+        if self.first_line == 1 {
+            self.first_column += start.first_column - 1;
+        }
+        if self.last_line == 1 {
+            self.last_column += start.first_column - 1;
+        }
+        self.first_line += start.first_line - 1;
+        self.last_line += start.first_line - 1;
+        self.range = (self.range.0 + start.range.0, self.range.1 + start.range.0);
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Token {
     pub(crate) kind: TokenKind,
@@ -55,6 +96,7 @@ impl Token {
     }
 }
 
+#[allow(clippy::to_string_trait_impl)]
 impl ToString for Token {
     fn to_string(&self) -> String {
         format!("[{}, {}]", self.kind.to_string(), self.value)